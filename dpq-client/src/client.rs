@@ -0,0 +1,472 @@
+use crate::error::ClientError;
+use crate::job_management::consumer_group_service_client::ConsumerGroupServiceClient;
+use crate::job_management::job_service_client::JobServiceClient;
+use crate::job_management::long_polling_service_client::LongPollingServiceClient;
+use crate::job_management::{
+    AckRequest, AckResponse, CancelJobRequest, EnqueueBatchRequest, EnqueueRequest, EnqueueResult,
+    GroupAssignment, HeartbeatRequest, HeartbeatResponse, Job, JobProgress, JobResult,
+    JobStatusRequest, JobStatusResponse, JoinGroupRequest, LeaveGroupRequest, NackRequest,
+    PollJobRequest, PollJobResponse, ResultRequest, ResultResponse,
+};
+use crate::pool::NodePool;
+use futures::stream::{self, Stream};
+use std::sync::Arc;
+use std::time::Duration;
+use uuid::Uuid;
+
+/// ClientConfig holds the nodes a client talks to and how it talks to them.
+/// `leaders`: The url addresses of the nodes that may be the leader. Producer requests are sent
+/// to whichever of them runs the job service.
+/// `followers`: The url addresses of the followers consumers poll.
+/// `consumer_id`: The id sent with every poll.
+/// `connect_timeout`: How long to wait for a connection to a node.
+/// `request_timeout`: How long a single attempt at a request may take, on top of any time the
+/// request is asked to wait on the node.
+/// `max_retries`: How many times a request that failed to reach a node is retried.
+/// `retry_backoff`: The delay before the first retry, doubled on every retry after it.
+#[derive(Debug, Clone)]
+pub struct ClientConfig {
+    pub leaders: Vec<String>,
+    pub followers: Vec<String>,
+    pub consumer_id: i32,
+    pub connect_timeout: Duration,
+    pub request_timeout: Duration,
+    pub max_retries: u32,
+    pub retry_backoff: Duration,
+}
+
+impl Default for ClientConfig {
+    fn default() -> Self {
+        ClientConfig {
+            leaders: Vec::new(),
+            followers: Vec::new(),
+            consumer_id: 0,
+            connect_timeout: Duration::from_secs(5),
+            request_timeout: Duration::from_secs(10),
+            max_retries: 3,
+            retry_backoff: Duration::from_millis(100),
+        }
+    }
+}
+
+impl ClientConfig {
+    /// Creates a configuration from the comma separated `LEADERS` and `FOLLOWERS` environment
+    /// variables, e.g. LEADERS=http://node1:50051,http://node2:50051
+    pub fn from_env() -> Self {
+        let addresses = |key: &str| -> Vec<String> {
+            match std::env::var(key) {
+                Ok(a) => a
+                    .split(',')
+                    .map(|address| address.trim().to_string())
+                    .filter(|address| !address.is_empty())
+                    .collect(),
+                Err(_) => Vec::new(),
+            }
+        };
+
+        ClientConfig {
+            leaders: addresses("LEADERS"),
+            followers: addresses("FOLLOWERS"),
+            ..ClientConfig::default()
+        }
+    }
+}
+
+/// LeasedJob is a job handed to this consumer, held until it is acked or nacked or its lease
+/// expires.
+/// `lease_id`: Identifies the lease in heartbeats, acks and nacks.
+/// `visibility_timeout`: How long the lease lasts without a heartbeat.
+/// `node`: The follower the job was polled from, which later requests about it are sent to first.
+#[derive(Debug, Clone)]
+pub struct LeasedJob {
+    pub job: Job,
+    pub lease_id: String,
+    pub visibility_timeout: Duration,
+    pub node: String,
+}
+
+/// Client is a typed, async client for producers and consumers. Connections are opened lazily,
+/// kept open and shared by every clone of the client. Requests that cannot reach a node are
+/// retried on the next one.
+/// `member_id`: Identifies this client in consumer groups, unique to every client created.
+#[derive(Clone)]
+pub struct Client {
+    leaders: Arc<NodePool>,
+    followers: Arc<NodePool>,
+    config: Arc<ClientConfig>,
+    member_id: Arc<String>,
+}
+
+impl Client {
+    pub fn new(config: ClientConfig) -> Self {
+        let pool = |addresses: &[String]| {
+            Arc::new(NodePool::new(
+                addresses.to_vec(),
+                config.connect_timeout,
+                config.max_retries,
+                config.retry_backoff,
+            ))
+        };
+
+        Client {
+            leaders: pool(&config.leaders),
+            followers: pool(&config.followers),
+            member_id: Arc::new(format!("{}-{}", config.consumer_id, Uuid::new_v4())),
+            config: Arc::new(config),
+        }
+    }
+
+    /// The id this client joins consumer groups with.
+    pub fn member_id(&self) -> &str {
+        &self.member_id
+    }
+
+    /// The url addresses of the followers polled.
+    pub fn followers(&self) -> Vec<String> {
+        self.followers.addresses()
+    }
+
+    /// Replaces the followers polled, e.g. with the followers a consumer group assigned. Jobs
+    /// already leased are still heartbeated, acked and nacked on the follower they came from.
+    pub fn set_followers(&self, followers: Vec<String>) {
+        self.followers.set_addresses(followers);
+    }
+
+    /// Enqueues a job. A tracking id is generated if the request has none, so the job can be
+    /// looked up with `status` even if the response is lost. The request is not retried once it
+    /// reached a node, so a job is never enqueued twice by a timed out attempt.
+    ///
+    /// # Arguments
+    /// `request`: The job to enqueue.
+    ///
+    /// # Returns
+    /// A Result object that is either Ok(Job) holding the created job, or Err(ClientError)
+    pub async fn enqueue(&self, mut request: EnqueueRequest) -> Result<Job, ClientError> {
+        if request.tracking_id.is_empty() {
+            request.tracking_id = Uuid::new_v4().to_string();
+        }
+
+        self.leaders
+            .call_at_most_once(None, self.config.request_timeout, |channel, _| {
+                let request = request.clone();
+                async move {
+                    let response = JobServiceClient::new(channel).enqueue_job(request).await?;
+                    Ok(response.into_inner())
+                }
+            })
+            .await
+    }
+
+    /// Enqueues many jobs at once. Tracking ids are generated for jobs that have none. Like
+    /// `enqueue`, the batch is not retried once it reached a node.
+    ///
+    /// # Arguments
+    /// `requests`: The jobs to enqueue.
+    ///
+    /// # Returns
+    /// A Result object that is either Ok(Vec<EnqueueResult>) holding the outcome of every job in
+    /// the order they were given, or Err(ClientError)
+    pub async fn enqueue_batch(
+        &self,
+        mut requests: Vec<EnqueueRequest>,
+    ) -> Result<Vec<EnqueueResult>, ClientError> {
+        for request in &mut requests {
+            if request.tracking_id.is_empty() {
+                request.tracking_id = Uuid::new_v4().to_string();
+            }
+        }
+        let batch: EnqueueBatchRequest = EnqueueBatchRequest { jobs: requests };
+
+        self.leaders
+            .call_at_most_once(None, self.config.request_timeout, |channel, _| {
+                let batch = batch.clone();
+                async move {
+                    let response = JobServiceClient::new(channel).enqueue_batch(batch).await?;
+                    Ok(response.into_inner().results)
+                }
+            })
+            .await
+    }
+
+    /// Cancels a job.
+    ///
+    /// # Returns
+    /// A Result object that is either Ok(bool), true if the job was cancelled, or Err(ClientError)
+    pub async fn cancel(&self, job_id: &str) -> Result<bool, ClientError> {
+        self.leaders
+            .call(None, self.config.request_timeout, |channel, _| {
+                let request = CancelJobRequest {
+                    job_id: job_id.to_string(),
+                };
+                async move {
+                    let response = JobServiceClient::new(channel).cancel_job(request).await?;
+                    Ok(response.into_inner().success)
+                }
+            })
+            .await
+    }
+
+    /// Looks up the state of a job by its job id or its tracking id.
+    ///
+    /// # Returns
+    /// A Result object that is either Ok(JobStatusResponse) or Err(ClientError)
+    pub async fn status(&self, id: &str) -> Result<JobStatusResponse, ClientError> {
+        self.leaders
+            .call(None, self.config.request_timeout, |channel, _| {
+                let request = JobStatusRequest { id: id.to_string() };
+                async move {
+                    let response = JobServiceClient::new(channel)
+                        .get_job_status(request)
+                        .await?;
+                    Ok(response.into_inner())
+                }
+            })
+            .await
+    }
+
+    /// Fetches the result of a job, waiting up to `wait` for the job to finish.
+    ///
+    /// # Returns
+    /// A Result object that is either Ok(ResultResponse) or Err(ClientError)
+    pub async fn result(
+        &self,
+        job_id: &str,
+        wait: Duration,
+    ) -> Result<ResultResponse, ClientError> {
+        self.leaders
+            .call(None, self.config.request_timeout + wait, |channel, _| {
+                let request = ResultRequest {
+                    job_id: job_id.to_string(),
+                    timeout_seconds: wait.as_secs() as i32,
+                };
+                async move {
+                    let response = JobServiceClient::new(channel).get_result(request).await?;
+                    Ok(response.into_inner())
+                }
+            })
+            .await
+    }
+
+    /// Long polls a follower for a job, waiting up to `wait` for one to become available.
+    /// Consecutive polls that find nothing move on to the next follower.
+    ///
+    /// # Arguments
+    /// `queues`: The queues to poll, every queue if empty.
+    /// `wait`: How long the follower may hold the request open.
+    ///
+    /// # Returns
+    /// A Result object that is either Ok(Option<LeasedJob>), None if no job became available, or
+    /// Err(ClientError)
+    pub async fn poll(
+        &self,
+        queues: &[String],
+        wait: Duration,
+    ) -> Result<Option<LeasedJob>, ClientError> {
+        let (node, response): (String, PollJobResponse) = self
+            .followers
+            .call(None, self.config.request_timeout + wait, |channel, node| {
+                let request = PollJobRequest {
+                    consumer_id: self.config.consumer_id,
+                    timeout_seconds: wait.as_secs() as i32,
+                    queues: queues.to_vec(),
+                };
+                async move {
+                    let response = LongPollingServiceClient::new(channel).poll(request).await?;
+                    Ok((node, response.into_inner()))
+                }
+            })
+            .await?;
+
+        match response.job {
+            Some(job) if response.success => Ok(Some(LeasedJob {
+                job,
+                lease_id: response.lease_id,
+                visibility_timeout: Duration::from_secs(
+                    response.visibility_timeout_seconds.max(0) as u64
+                ),
+                node,
+            })),
+            _ => {
+                self.followers.advance();
+                Ok(None)
+            }
+        }
+    }
+
+    /// Polls the followers continuously, yielding every job handed to this consumer. Failed
+    /// polls are yielded as errors and polling carries on.
+    ///
+    /// # Arguments
+    /// `queues`: The queues to poll, every queue if empty.
+    /// `wait`: How long each poll may be held open.
+    pub fn subscribe(
+        &self,
+        queues: Vec<String>,
+        wait: Duration,
+    ) -> impl Stream<Item = Result<LeasedJob, ClientError>> + '_ {
+        stream::unfold(queues, move |queues| async move {
+            loop {
+                match self.poll(&queues, wait).await {
+                    Ok(Some(job)) => return Some((Ok(job), queues)),
+                    Ok(None) => continue,
+                    Err(e) => return Some((Err(e), queues)),
+                }
+            }
+        })
+    }
+
+    /// Extends the lease on a job and reports its progress.
+    ///
+    /// # Returns
+    /// A Result object that is either Ok(HeartbeatResponse) or Err(ClientError)
+    pub async fn heartbeat(
+        &self,
+        job: &LeasedJob,
+        progress: Option<JobProgress>,
+    ) -> Result<HeartbeatResponse, ClientError> {
+        self.followers
+            .call(
+                Some(&job.node),
+                self.config.request_timeout,
+                |channel, _| {
+                    let request = HeartbeatRequest {
+                        job_id: job.job.job_id.clone(),
+                        lease_id: job.lease_id.clone(),
+                        progress: progress.clone(),
+                    };
+                    async move {
+                        let response = LongPollingServiceClient::new(channel)
+                            .heartbeat(request)
+                            .await?;
+                        Ok(response.into_inner())
+                    }
+                },
+            )
+            .await
+    }
+
+    /// Acknowledges that a job has been processed, attaching its result if there is one.
+    ///
+    /// # Returns
+    /// A Result object that is either Ok(AckResponse) or Err(ClientError)
+    pub async fn ack(
+        &self,
+        job: &LeasedJob,
+        result: Option<JobResult>,
+    ) -> Result<AckResponse, ClientError> {
+        self.followers
+            .call_at_most_once(
+                Some(&job.node),
+                self.config.request_timeout,
+                |channel, _| {
+                    let request = AckRequest {
+                        job_id: job.job.job_id.clone(),
+                        lease_id: job.lease_id.clone(),
+                        result: result.clone(),
+                    };
+                    async move {
+                        let response = LongPollingServiceClient::new(channel).ack(request).await?;
+                        Ok(response.into_inner())
+                    }
+                },
+            )
+            .await
+    }
+
+    /// Reports that a job could not be processed, so it is retried or dead lettered.
+    ///
+    /// # Returns
+    /// A Result object that is either Ok(AckResponse) or Err(ClientError)
+    pub async fn nack(&self, job: &LeasedJob) -> Result<AckResponse, ClientError> {
+        self.followers
+            .call_at_most_once(
+                Some(&job.node),
+                self.config.request_timeout,
+                |channel, _| {
+                    let request = NackRequest {
+                        job_id: job.job.job_id.clone(),
+                        lease_id: job.lease_id.clone(),
+                    };
+                    async move {
+                        let response = LongPollingServiceClient::new(channel).nack(request).await?;
+                        Ok(response.into_inner())
+                    }
+                },
+            )
+            .await
+    }
+
+    /// Hands a job back without processing it, so another consumer can take it. The release does
+    /// not count as a failed attempt.
+    ///
+    /// # Returns
+    /// A Result object that is either Ok(AckResponse) or Err(ClientError)
+    pub async fn release(&self, job: &LeasedJob) -> Result<AckResponse, ClientError> {
+        self.followers
+            .call(
+                Some(&job.node),
+                self.config.request_timeout,
+                |channel, _| {
+                    let request = NackRequest {
+                        job_id: job.job.job_id.clone(),
+                        lease_id: job.lease_id.clone(),
+                    };
+                    async move {
+                        let response = LongPollingServiceClient::new(channel)
+                            .release(request)
+                            .await?;
+                        Ok(response.into_inner())
+                    }
+                },
+            )
+            .await
+    }
+
+    /// Joins a consumer group, or stays in it, and returns the followers this client is assigned.
+    /// Members must join again within the session timeout of the assignment or they are removed
+    /// from the group.
+    ///
+    /// # Arguments
+    /// `group`: The name of the group.
+    ///
+    /// # Returns
+    /// A Result object that is either Ok(GroupAssignment) or Err(ClientError)
+    pub async fn join_group(&self, group: &str) -> Result<GroupAssignment, ClientError> {
+        self.leaders
+            .call(None, self.config.request_timeout, |channel, _| {
+                let request = JoinGroupRequest {
+                    group: group.to_string(),
+                    member_id: self.member_id.to_string(),
+                };
+                async move {
+                    let response = ConsumerGroupServiceClient::new(channel)
+                        .join_group(request)
+                        .await?;
+                    Ok(response.into_inner())
+                }
+            })
+            .await
+    }
+
+    /// Leaves a consumer group, so its followers are handed to the other members straight away.
+    ///
+    /// # Returns
+    /// A Result object that is either Ok(bool), true if this client was in the group, or
+    /// Err(ClientError)
+    pub async fn leave_group(&self, group: &str) -> Result<bool, ClientError> {
+        self.leaders
+            .call(None, self.config.request_timeout, |channel, _| {
+                let request = LeaveGroupRequest {
+                    group: group.to_string(),
+                    member_id: self.member_id.to_string(),
+                };
+                async move {
+                    let response = ConsumerGroupServiceClient::new(channel)
+                        .leave_group(request)
+                        .await?;
+                    Ok(response.into_inner().success)
+                }
+            })
+            .await
+    }
+}
@@ -0,0 +1,40 @@
+use std::fmt;
+
+/// Custom Error type for client requests.
+#[derive(Debug)]
+pub enum ClientError {
+    /// No node could be reached, or every node failed, within the configured retries.
+    Unavailable(String),
+    /// The node handled the request and rejected it.
+    Rpc(tonic::Status),
+    /// The request did not complete within its timeout.
+    Timeout,
+    /// The request was invalid and was not sent.
+    InvalidArgument(String),
+}
+
+impl fmt::Display for ClientError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ClientError::Unavailable(s) => write!(f, "Unavailable: {}", s),
+            ClientError::Rpc(status) => {
+                write!(
+                    f,
+                    "Request failed: {:?} {}",
+                    status.code(),
+                    status.message()
+                )
+            }
+            ClientError::Timeout => write!(f, "Request timed out"),
+            ClientError::InvalidArgument(s) => write!(f, "Invalid argument: {}", s),
+        }
+    }
+}
+
+impl std::error::Error for ClientError {}
+
+impl From<tonic::Status> for ClientError {
+    fn from(status: tonic::Status) -> Self {
+        ClientError::Rpc(status)
+    }
+}
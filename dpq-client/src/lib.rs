@@ -0,0 +1,13 @@
+pub mod client;
+pub mod error;
+pub mod job_management {
+    include!("proto/job_management.rs");
+}
+pub mod pool;
+
+pub use client::{Client, ClientConfig, LeasedJob};
+pub use error::ClientError;
+pub use job_management::{
+    EnqueueRequest, EnqueueResult, GroupAssignment, Job, JobProgress, JobResult, JobStatusResponse,
+    ResultResponse,
+};
@@ -0,0 +1,402 @@
+use crate::error::ClientError;
+use log::{error, info};
+use std::collections::HashMap;
+use std::future::Future;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::RwLock;
+use std::time::Duration;
+use tokio::sync::Mutex;
+use tonic::transport::{Channel, Endpoint};
+use tonic::Code;
+
+/// NodePool keeps one connection to each of a set of interchangeable nodes and sends requests to
+/// whichever node last answered, moving on to the next node when one is unreachable.
+/// `addresses`: The url addresses of the nodes, which may be replaced while the pool is in use.
+/// `channels`: The open connection to each node, keyed by address. A channel multiplexes
+/// concurrent requests, so one per node is enough.
+/// `current`: The index of the node requests are sent to first.
+/// `connect_timeout`: How long to wait for a connection to a node.
+/// `max_retries`: How many more nodes, or attempts, a failed request is retried on.
+/// `retry_backoff`: The delay before the first retry, doubled on every retry after it.
+pub struct NodePool {
+    addresses: RwLock<Vec<String>>,
+    channels: Mutex<HashMap<String, Channel>>,
+    current: AtomicUsize,
+    connect_timeout: Duration,
+    max_retries: u32,
+    retry_backoff: Duration,
+}
+
+impl NodePool {
+    pub fn new(
+        addresses: Vec<String>,
+        connect_timeout: Duration,
+        max_retries: u32,
+        retry_backoff: Duration,
+    ) -> Self {
+        NodePool {
+            addresses: RwLock::new(addresses),
+            channels: Mutex::new(HashMap::new()),
+            current: AtomicUsize::new(0),
+            connect_timeout,
+            max_retries,
+            retry_backoff,
+        }
+    }
+
+    /// The url addresses of the nodes in the pool.
+    pub fn addresses(&self) -> Vec<String> {
+        self.addresses.read().unwrap().clone()
+    }
+
+    /// Replaces the nodes in the pool, e.g. when a consumer group is rebalanced. Connections to
+    /// nodes that left the pool are kept, since requests about jobs leased from them are still
+    /// sent there.
+    pub fn set_addresses(&self, addresses: Vec<String>) {
+        *self.addresses.write().unwrap() = addresses;
+        self.current.store(0, Ordering::Relaxed);
+    }
+
+    /// Moves on to the next node, so the following request is sent there first.
+    pub fn advance(&self) {
+        let len: usize = self.addresses.read().unwrap().len();
+        if len > 0 {
+            let next: usize = (self.current.load(Ordering::Relaxed) + 1) % len;
+            self.current.store(next, Ordering::Relaxed);
+        }
+    }
+
+    /// Returns the open connection to a node, connecting to it if there is none.
+    ///
+    /// # Arguments
+    /// `address`: The url address of the node.
+    ///
+    /// # Returns
+    /// A Result object that is either Ok(Channel) or Err(ClientError)
+    pub async fn channel(&self, address: &str) -> Result<Channel, ClientError> {
+        if let Some(channel) = self.channels.lock().await.get(address) {
+            return Ok(channel.clone());
+        }
+
+        info!(target:"request_logger","Connecting to node at {}",address);
+
+        let channel: Channel = Endpoint::from_shared(address.to_string())
+            .map_err(|_| ClientError::InvalidArgument(format!("Invalid node url {}", address)))?
+            .connect_timeout(self.connect_timeout)
+            .connect()
+            .await
+            .map_err(|e| ClientError::Unavailable(format!("{}: {}", address, e)))?;
+
+        self.channels
+            .lock()
+            .await
+            .insert(address.to_string(), channel.clone());
+
+        Ok(channel)
+    }
+
+    /// Sends a request, retrying it on the next node when a node cannot be reached or cannot
+    /// serve it. Only requests that are safe to repeat should be sent this way, since a request
+    /// that timed out or failed on the node may still have been carried out.
+    ///
+    /// # Arguments
+    /// `prefer`: The node to try first, the node that last answered if None. The node is tried
+    /// even if it is no longer in the pool.
+    /// `timeout`: How long a single attempt may take.
+    /// `request`: Sends the request over a connection to the node at the given address.
+    ///
+    /// # Returns
+    /// A Result object that is either Ok(T) holding the response, or Err(ClientError)
+    pub async fn call<T, F, Fut>(
+        &self,
+        prefer: Option<&str>,
+        timeout: Duration,
+        request: F,
+    ) -> Result<T, ClientError>
+    where
+        F: FnMut(Channel, String) -> Fut,
+        Fut: Future<Output = Result<T, tonic::Status>>,
+    {
+        self.send(prefer, timeout, true, request).await
+    }
+
+    /// Sends a request that must not be carried out twice, such as an enqueue or an ack. The
+    /// request is only retried when no connection to a node could be opened, so it cannot have
+    /// been delivered. Once it has been sent, a timeout or failure is returned to the caller.
+    ///
+    /// # Arguments
+    /// `prefer`: The node to try first, the node that last answered if None.
+    /// `timeout`: How long a single attempt may take.
+    /// `request`: Sends the request over a connection to the node at the given address.
+    ///
+    /// # Returns
+    /// A Result object that is either Ok(T) holding the response, or Err(ClientError)
+    pub async fn call_at_most_once<T, F, Fut>(
+        &self,
+        prefer: Option<&str>,
+        timeout: Duration,
+        request: F,
+    ) -> Result<T, ClientError>
+    where
+        F: FnMut(Channel, String) -> Fut,
+        Fut: Future<Output = Result<T, tonic::Status>>,
+    {
+        self.send(prefer, timeout, false, request).await
+    }
+
+    /// Sends a request to the nodes in turn until one answers. Nodes that answer with
+    /// `Unimplemented` do not run the requested service, which is how the leader is discovered
+    /// among the nodes. Every node is tried once that way without waiting and without using up
+    /// a retry.
+    ///
+    /// # Arguments
+    /// `prefer`: The node to try first, the node that last answered if None.
+    /// `timeout`: How long a single attempt may take.
+    /// `idempotent`: Whether the request may be retried after it reached a node.
+    /// `request`: Sends the request over a connection to the node at the given address.
+    ///
+    /// # Returns
+    /// A Result object that is either Ok(T) holding the response, or Err(ClientError)
+    async fn send<T, F, Fut>(
+        &self,
+        prefer: Option<&str>,
+        timeout: Duration,
+        idempotent: bool,
+        mut request: F,
+    ) -> Result<T, ClientError>
+    where
+        F: FnMut(Channel, String) -> Fut,
+        Fut: Future<Output = Result<T, tonic::Status>>,
+    {
+        let pooled: Vec<String> = self.addresses();
+        let mut addresses: Vec<String> = pooled.clone();
+        let start: usize = match prefer {
+            Some(p) => match addresses.iter().position(|a| a == p) {
+                Some(index) => index,
+                None => {
+                    addresses.insert(0, p.to_string());
+                    0
+                }
+            },
+            None if addresses.is_empty() => {
+                return Err(ClientError::InvalidArgument(
+                    "No node addresses configured".to_string(),
+                ));
+            }
+            None => self.current.load(Ordering::Relaxed) % addresses.len(),
+        };
+        let mut last_error: ClientError = ClientError::Unavailable("No node reached".to_string());
+        let mut delay: Duration = self.retry_backoff;
+        let mut retries: u32 = 0;
+        let mut skipped: usize = 0;
+
+        for index in (start..).map(|i| i % addresses.len()) {
+            let address: &str = &addresses[index];
+
+            let outcome: Result<T, ClientError> = match self.channel(address).await {
+                Ok(channel) => {
+                    match tokio::time::timeout(timeout, request(channel, address.to_string())).await
+                    {
+                        Ok(Ok(response)) => Ok(response),
+                        Ok(Err(status)) => Err(ClientError::Rpc(status)),
+                        Err(_) => Err(ClientError::Timeout),
+                    }
+                }
+                Err(e) => Err(e),
+            };
+
+            match outcome {
+                Ok(response) => {
+                    if let Some(index) = pooled.iter().position(|a| a == address) {
+                        self.current.store(index, Ordering::Relaxed);
+                    }
+                    return Ok(response);
+                }
+                Err(ClientError::Rpc(status)) if status.code() == Code::Unimplemented => {
+                    last_error = ClientError::Rpc(status);
+                    skipped += 1;
+                    if skipped >= addresses.len() {
+                        break;
+                    }
+                    continue;
+                }
+                // A connection that could not be opened never carried the request
+                Err(e @ ClientError::Unavailable(_)) => {
+                    error!(target:"error_logger","Failed to connect to node at {}: {}",address,e);
+                    last_error = e;
+                }
+                Err(e) if idempotent && is_retryable(&e) => {
+                    error!(target:"error_logger","Request to node at {} failed: {}",address,e);
+                    self.channels.lock().await.remove(address);
+                    last_error = e;
+                }
+                Err(e) => return Err(e),
+            }
+
+            if retries == self.max_retries {
+                break;
+            }
+            retries += 1;
+            tokio::time::sleep(delay).await;
+            delay *= 2;
+        }
+
+        Err(last_error)
+    }
+}
+
+/// Whether a request that failed with the error may succeed if it is sent again, possibly to
+/// another node.
+pub fn is_retryable(error: &ClientError) -> bool {
+    match error {
+        ClientError::Unavailable(_) | ClientError::Timeout => true,
+        ClientError::Rpc(status) => matches!(
+            status.code(),
+            Code::Unavailable | Code::Unknown | Code::Aborted | Code::DeadlineExceeded
+        ),
+        ClientError::InvalidArgument(_) => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{is_retryable, NodePool};
+    use crate::error::ClientError;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::time::Duration;
+    use tokio::net::{TcpListener, TcpStream};
+    use tonic::Status;
+
+    #[test]
+    fn test_is_retryable() {
+        assert!(is_retryable(&ClientError::Timeout));
+        assert!(is_retryable(&ClientError::Unavailable("down".to_string())));
+        assert!(is_retryable(&ClientError::Rpc(Status::unavailable("down"))));
+        assert!(!is_retryable(&ClientError::Rpc(Status::not_found("gone"))));
+        assert!(!is_retryable(&ClientError::Rpc(
+            Status::resource_exhausted("quota")
+        )));
+        assert!(!is_retryable(&ClientError::InvalidArgument(
+            "bad".to_string()
+        )));
+    }
+
+    #[tokio::test]
+    async fn test_call_gives_up_on_unreachable_nodes() {
+        let pool: NodePool = NodePool::new(
+            vec!["http://[::1]:1".to_string()],
+            Duration::from_millis(100),
+            1,
+            Duration::from_millis(1),
+        );
+        let attempts: AtomicUsize = AtomicUsize::new(0);
+
+        let outcome: Result<(), ClientError> = pool
+            .call(None, Duration::from_millis(100), |_, _| async {
+                attempts.fetch_add(1, Ordering::Relaxed);
+                Ok(())
+            })
+            .await;
+
+        assert!(matches!(outcome, Err(ClientError::Unavailable(_))));
+        assert_eq!(attempts.load(Ordering::Relaxed), 0);
+
+        let empty: NodePool =
+            NodePool::new(Vec::new(), Duration::from_millis(100), 1, Duration::ZERO);
+        let outcome: Result<(), ClientError> = empty
+            .call(None, Duration::from_millis(100), |_, _| async { Ok(()) })
+            .await;
+        assert!(matches!(outcome, Err(ClientError::InvalidArgument(_))));
+
+        // A preferred node is tried even when it is not in the pool
+        let outcome: Result<(), ClientError> = empty
+            .call(
+                Some("http://[::1]:1"),
+                Duration::from_millis(100),
+                |_, _| async { Ok(()) },
+            )
+            .await;
+        assert!(matches!(outcome, Err(ClientError::Unavailable(_))));
+    }
+
+    /// Listens for connections and holds them open, standing in for a node the pool can connect
+    /// to. The requests in these tests never use the connection.
+    async fn listener() -> String {
+        let listener: TcpListener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let address: String = format!("http://{}", listener.local_addr().unwrap());
+        tokio::spawn(async move {
+            let mut connections: Vec<TcpStream> = Vec::new();
+            while let Ok((connection, _)) = listener.accept().await {
+                connections.push(connection);
+            }
+        });
+        address
+    }
+
+    #[tokio::test]
+    async fn test_call_retries() {
+        let follower: String = listener().await;
+        let leader: String = listener().await;
+
+        // Discovering the leader does not use up the retries
+        let pool: NodePool = NodePool::new(
+            vec![follower.clone(), leader.clone()],
+            Duration::from_millis(500),
+            0,
+            Duration::ZERO,
+        );
+        let outcome: Result<String, ClientError> = pool
+            .call_at_most_once(None, Duration::from_millis(500), |_, node| {
+                let leader: String = leader.clone();
+                async move {
+                    match node == leader {
+                        true => Ok(node),
+                        false => Err(Status::unimplemented("not the leader")),
+                    }
+                }
+            })
+            .await;
+        assert_eq!(outcome.unwrap(), leader);
+
+        // A request that reached a node is only repeated if it is safe to
+        let pool: NodePool = NodePool::new(
+            vec![leader.clone()],
+            Duration::from_millis(500),
+            3,
+            Duration::ZERO,
+        );
+        let attempts: AtomicUsize = AtomicUsize::new(0);
+        let failing = |_, _| async {
+            attempts.fetch_add(1, Ordering::Relaxed);
+            Err::<(), Status>(Status::unknown("connection reset"))
+        };
+
+        let outcome: Result<(), ClientError> = pool
+            .call_at_most_once(None, Duration::from_millis(500), failing)
+            .await;
+        assert!(matches!(outcome, Err(ClientError::Rpc(_))));
+        assert_eq!(attempts.load(Ordering::Relaxed), 1);
+
+        let outcome: Result<(), ClientError> = pool
+            .call(None, Duration::from_millis(500), failing)
+            .await;
+        assert!(matches!(outcome, Err(ClientError::Rpc(_))));
+        assert_eq!(attempts.load(Ordering::Relaxed), 5);
+    }
+
+    #[test]
+    fn test_set_addresses() {
+        let pool: NodePool = NodePool::new(
+            vec!["http://a".to_string(), "http://b".to_string()],
+            Duration::from_millis(100),
+            1,
+            Duration::ZERO,
+        );
+        pool.advance();
+
+        pool.set_addresses(vec!["http://c".to_string()]);
+        pool.advance();
+        assert_eq!(pool.addresses(), vec!["http://c".to_string()]);
+        assert_eq!(pool.current.load(Ordering::Relaxed), 0);
+    }
+}
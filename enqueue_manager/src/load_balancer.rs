@@ -2,14 +2,97 @@
 pub mod load_balancer_logic {
     use crate::job_management::{
         job_service_client::JobServiceClient, node_health_service_client::NodeHealthServiceClient,
-        EnqueueRequest, NodeHealthRequest,
+        EnqueueRequest, EnqueueResult, NodeHealthRequest,
     };
     use log::{error, info};
-    use std::collections::VecDeque;
+    use std::collections::{HashMap, VecDeque};
     use std::fmt::Display;
+    use std::sync::{Arc, Mutex};
+    use tokio::sync::mpsc;
     use tokio::time::{timeout, Duration};
+    use tokio_stream::wrappers::ReceiverStream;
     use tonic::transport::Channel;
 
+    /// The number of jobs that may be queued on a node's enqueue stream before sending waits.
+    const STREAM_BUFFER: usize = 1024;
+
+    /// The number of rejected jobs remembered for status requests, the oldest are forgotten first.
+    const REJECTED_LIMIT: usize = 10_000;
+
+    /// Streamed keeps track of the jobs sent over the enqueue streams until the nodes answer them,
+    /// and of the jobs the nodes rejected.
+    /// `in_flight`: The jobs sent that have not been answered, keyed by tracking id, along with
+    /// the id of the stream they were sent over.
+    /// `rejected`: The jobs that were not enqueued, keyed by tracking id, along with the reason.
+    /// `rejected_order`: The tracking ids of the rejected jobs, oldest first.
+    #[derive(Debug, Default)]
+    struct Streamed {
+        in_flight: HashMap<String, (u64, EnqueueRequest)>,
+        rejected: HashMap<String, (EnqueueRequest, String)>,
+        rejected_order: VecDeque<String>,
+    }
+
+    impl Streamed {
+        /// Records that a job was not enqueued, forgetting the oldest rejection if there are more
+        /// than REJECTED_LIMIT.
+        fn reject(&mut self, job: EnqueueRequest, reason: String) {
+            let tracking_id: String = job.tracking_id.clone();
+            if self
+                .rejected
+                .insert(tracking_id.clone(), (job, reason))
+                .is_none()
+            {
+                self.rejected_order.push_back(tracking_id);
+            }
+
+            if self.rejected_order.len() > REJECTED_LIMIT {
+                if let Some(oldest) = self.rejected_order.pop_front() {
+                    self.rejected.remove(&oldest);
+                }
+            }
+        }
+
+        /// Records the answer a node streamed back for a job.
+        fn answer(&mut self, result: EnqueueResult) {
+            let job: EnqueueRequest = match self.in_flight.remove(&result.tracking_id) {
+                Some((_, job)) => job,
+                None => return,
+            };
+
+            if !result.success {
+                self.reject(job, result.error);
+            }
+        }
+
+        /// Rejects every job sent over a stream that ended before answering them. The node may
+        /// have enqueued some of them, so they are not sent again; status requests still find
+        /// those on the leader.
+        ///
+        /// # Arguments
+        /// `stream`: The id of the stream that ended.
+        /// `node`: The url address of the node the stream was open to.
+        fn abandon(&mut self, stream: u64, node: &str) {
+            let unanswered: Vec<String> = self
+                .in_flight
+                .iter()
+                .filter(|(_, (id, _))| *id == stream)
+                .map(|(tracking_id, _)| tracking_id.clone())
+                .collect();
+
+            for tracking_id in unanswered {
+                if let Some((_, job)) = self.in_flight.remove(&tracking_id) {
+                    self.reject(
+                        job,
+                        format!(
+                            "The enqueue stream to node at {} ended before the job was answered",
+                            node
+                        ),
+                    );
+                }
+            }
+        }
+    }
+
     /// Node represents a replica in the distributed system
     /// `address` is url address of the replica to recieved gRPC requests
     /// `weight` is the calculated weight for the node based on CPU utilization, memory usage,queue
@@ -71,10 +154,17 @@ pub mod load_balancer_logic {
     /// `buffer`: The buffer jobs are added to before being distributed.
     /// `nodes`: A vector of nodes in the distributed system.
     /// `lamport_timestamp`: The logical clock.
+    /// `streams`: The open enqueue stream to each node and its id, keyed by the node's address.
+    /// `streams_opened`: The number of streams opened, which the next stream is identified by.
+    /// `streamed`: The jobs sent over the streams that have not been answered, and the jobs the
+    /// nodes rejected, shared with the tasks reading the answers.
     pub struct LoadBalancer {
         buffer: VecDeque<EnqueueRequest>,
         nodes: Vec<Node>,
         lamport_timestamp: u64,
+        streams: HashMap<String, (u64, mpsc::Sender<EnqueueRequest>)>,
+        streams_opened: u64,
+        streamed: Arc<Mutex<Streamed>>,
     }
 
     impl LoadBalancer {
@@ -90,6 +180,47 @@ pub mod load_balancer_logic {
             self.buffer.push_back(job);
         }
 
+        /// Finds a job that is still waiting in the buffer to be distributed, or that was sent to
+        /// a node which has not answered yet.
+        ///
+        /// # Arguments
+        /// `tracking_id`: The tracking id the job was enqueued with.
+        ///
+        /// # Returns
+        /// The buffered job, if it has not been distributed yet.
+        pub fn find_buffered(&self, tracking_id: &str) -> Option<EnqueueRequest> {
+            if let Some(job) = self
+                .buffer
+                .iter()
+                .find(|job| job.tracking_id == tracking_id)
+            {
+                return Some(job.clone());
+            }
+
+            self.streamed
+                .lock()
+                .unwrap()
+                .in_flight
+                .get(tracking_id)
+                .map(|(_, job)| job.clone())
+        }
+
+        /// Finds a job that was distributed but not enqueued.
+        ///
+        /// # Arguments
+        /// `tracking_id`: The tracking id the job was enqueued with.
+        ///
+        /// # Returns
+        /// The rejected job along with the reason it was rejected, if it was.
+        pub fn find_rejected(&self, tracking_id: &str) -> Option<(EnqueueRequest, String)> {
+            self.streamed
+                .lock()
+                .unwrap()
+                .rejected
+                .get(tracking_id)
+                .cloned()
+        }
+
         /// Creates a new load balancer state.
         ///
         /// # Arguments
@@ -133,6 +264,9 @@ pub mod load_balancer_logic {
                 buffer: VecDeque::new(),
                 nodes,
                 lamport_timestamp: 0,
+                streams: HashMap::new(),
+                streams_opened: 0,
+                streamed: Arc::new(Mutex::new(Streamed::default())),
             })
         }
 
@@ -273,6 +407,67 @@ pub mod load_balancer_logic {
             }
         }
 
+        /// Returns the enqueue stream open to a node, opening one if there is none or the previous
+        /// one was closed. The jobs the node rejects are kept so their status can be reported, as
+        /// are the jobs left unanswered when the stream ends.
+        ///
+        /// # Arguments
+        /// `address`: The url address of the node.
+        ///
+        /// # Returns
+        /// A Result object that is either Ok((u64, mpsc::Sender<EnqueueRequest>)) holding the id of
+        /// the stream and its sender, or Err(Box<dyn std::error::Error + 'static>)
+        async fn stream_to(
+            &mut self,
+            address: &str,
+        ) -> Result<(u64, mpsc::Sender<EnqueueRequest>), Box<dyn std::error::Error + 'static>>
+        {
+            if let Some((id, stream)) = self.streams.get(address) {
+                if !stream.is_closed() {
+                    return Ok((*id, stream.clone()));
+                }
+            }
+
+            info!(target:"request_logger","Opening Job Service enqueue stream to address {}", address);
+
+            let mut client: JobServiceClient<Channel> =
+                JobServiceClient::connect(address.to_string()).await?;
+
+            let (stream, jobs) = mpsc::channel(STREAM_BUFFER);
+            let mut results = client
+                .enqueue_stream(ReceiverStream::new(jobs))
+                .await?
+                .into_inner();
+
+            let id: u64 = self.streams_opened;
+            self.streams_opened += 1;
+
+            let node: String = address.to_string();
+            let streamed: Arc<Mutex<Streamed>> = self.streamed.clone();
+            tokio::spawn(async move {
+                loop {
+                    match results.message().await {
+                        Ok(Some(result)) => {
+                            if !result.success {
+                                error!(target:"error_logger","Node at {} rejected job {}: {}",node,result.tracking_id,result.error);
+                            }
+                            streamed.lock().unwrap().answer(result);
+                        }
+                        Ok(None) => break,
+                        Err(_) => {
+                            error!(target:"error_logger","Enqueue stream to node at {} failed",node);
+                            break;
+                        }
+                    }
+                }
+                streamed.lock().unwrap().abandon(id, &node);
+            });
+
+            self.streams
+                .insert(address.to_string(), (id, stream.clone()));
+            Ok((id, stream))
+        }
+
         /// Distributes jobs to the nodes in the distributed system periodically.
         ///
         /// # Returns
@@ -298,28 +493,30 @@ pub mod load_balancer_logic {
 
                     info!(target:"request_logger","Job Service Request to address {}", node.address);
 
-                    let mut client: JobServiceClient<Channel> =
-                        JobServiceClient::connect(node.address.clone()).await?;
-
-                    let response = match timeout(
-                        Duration::from_millis(10),
-                        client.enqueue_job(enqueue_request),
-                    )
-                    .await
+                    let (id, stream): (u64, mpsc::Sender<EnqueueRequest>) =
+                        match self.stream_to(&node.address).await {
+                            Ok(s) => s,
+                            Err(e) => {
+                                self.buffer.push_front(enqueue_request);
+                                return Err(e);
+                            }
+                        };
+
+                    let tracking_id: String = enqueue_request.tracking_id.clone();
+                    self.streamed
+                        .lock()
+                        .unwrap()
+                        .in_flight
+                        .insert(tracking_id.clone(), (id, enqueue_request.clone()));
+
+                    // Jobs that could not be sent stay buffered for the next distribution
+                    if let Err(mpsc::error::SendError(request)) = stream.send(enqueue_request).await
                     {
-                        Ok(value) => value,
-                        Err(_) => {
-                            error!(target:"error_logger","Failed to get response from node at {} request timeout",node.address);
-                            return Err(Box::new(RpcError::FailedRequest));
-                        }
-                    };
-
-                    match response {
-                        Ok(_) => (),
-                        Err(_) => {
-                            error!(target:"error_logger","Failed to obtain enqueue response from node at {}",node.address);
-                            return Err(Box::new(RpcError::FailedRequest));
-                        }
+                        error!(target:"error_logger","Enqueue stream to node at {} was closed",node.address);
+                        self.streamed.lock().unwrap().in_flight.remove(&tracking_id);
+                        self.streams.remove(&node.address);
+                        self.buffer.push_front(request);
+                        return Err(Box::new(RpcError::FailedRequest));
                     }
                 }
             }
@@ -339,7 +536,131 @@ pub mod load_balancer_logic {
             write!(f, "gRPC error")
         }
     }
-}
 
-#[cfg(test)]
-mod tests {}
+    #[cfg(test)]
+    mod tests {
+        use super::{LoadBalancer, Streamed, REJECTED_LIMIT};
+        use crate::job_management::{EnqueueRequest, EnqueueResult};
+
+        fn job(tracking_id: &str) -> EnqueueRequest {
+            EnqueueRequest {
+                tracking_id: tracking_id.to_string(),
+                ..EnqueueRequest::default()
+            }
+        }
+
+        fn result(tracking_id: &str, success: bool, error: &str) -> EnqueueResult {
+            EnqueueResult {
+                success,
+                job: None,
+                error: error.to_string(),
+                tracking_id: tracking_id.to_string(),
+            }
+        }
+
+        #[test]
+        fn test_answers_only_keep_rejected_jobs() {
+            let mut streamed: Streamed = Streamed::default();
+            streamed.in_flight.insert("a".to_string(), (1, job("a")));
+            streamed.in_flight.insert("b".to_string(), (1, job("b")));
+
+            // An enqueued job is forgotten, the leader reports its status from then on
+            streamed.answer(result("a", true, ""));
+            assert!(!streamed.in_flight.contains_key("a"));
+            assert!(!streamed.rejected.contains_key("a"));
+
+            streamed.answer(result("b", false, "Tenant t1 exceeded its enqueue rate"));
+            assert!(streamed.in_flight.is_empty());
+            assert_eq!(
+                streamed.rejected["b"].1,
+                "Tenant t1 exceeded its enqueue rate"
+            );
+
+            // Answers to jobs that are no longer in flight are ignored
+            streamed.answer(result("c", false, "Unknown parent job provided"));
+            assert!(!streamed.rejected.contains_key("c"));
+            assert_eq!(streamed.rejected_order, vec!["b".to_string()]);
+        }
+
+        #[test]
+        fn test_oldest_rejections_are_forgotten() {
+            let mut streamed: Streamed = Streamed::default();
+            for i in 0..REJECTED_LIMIT {
+                streamed.reject(job(&i.to_string()), "rejected".to_string());
+            }
+
+            // Rejecting a job again updates the reason without counting it twice
+            streamed.reject(job("0"), "rejected again".to_string());
+            assert_eq!(streamed.rejected.len(), REJECTED_LIMIT);
+            assert_eq!(streamed.rejected_order.len(), REJECTED_LIMIT);
+            assert_eq!(streamed.rejected["0"].1, "rejected again");
+
+            streamed.reject(job("newest"), "rejected".to_string());
+            assert_eq!(streamed.rejected.len(), REJECTED_LIMIT);
+            assert_eq!(streamed.rejected_order.len(), REJECTED_LIMIT);
+            assert!(!streamed.rejected.contains_key("0"));
+            assert!(streamed.rejected.contains_key("1"));
+            assert!(streamed.rejected.contains_key("newest"));
+        }
+
+        #[test]
+        fn test_abandon_rejects_the_jobs_of_the_ended_stream() {
+            let mut streamed: Streamed = Streamed::default();
+            streamed.in_flight.insert("a".to_string(), (1, job("a")));
+            streamed.in_flight.insert("b".to_string(), (1, job("b")));
+            streamed.in_flight.insert("c".to_string(), (2, job("c")));
+
+            streamed.abandon(1, "http://node-1");
+
+            assert_eq!(streamed.in_flight.len(), 1);
+            assert!(streamed.in_flight.contains_key("c"));
+            for tracking_id in ["a", "b"] {
+                let (rejected, reason): &(EnqueueRequest, String) = &streamed.rejected[tracking_id];
+                assert_eq!(rejected.tracking_id, tracking_id);
+                assert!(reason.contains("http://node-1"));
+            }
+
+            // A stream reopened to the same node has a new id, its jobs are not abandoned
+            streamed.abandon(3, "http://node-2");
+            assert!(streamed.in_flight.contains_key("c"));
+            assert!(!streamed.rejected.contains_key("c"));
+        }
+
+        #[tokio::test]
+        async fn test_status_of_streamed_jobs() {
+            let load_balancer: LoadBalancer = LoadBalancer::new(&mut Vec::new()).await.unwrap();
+            {
+                let mut streamed = load_balancer.streamed.lock().unwrap();
+                streamed.in_flight.insert("a".to_string(), (1, job("a")));
+                streamed.in_flight.insert("b".to_string(), (2, job("b")));
+            }
+
+            // Jobs waiting on an answer are still reported as buffered
+            assert!(load_balancer.find_buffered("a").is_some());
+            assert!(load_balancer.find_rejected("a").is_none());
+
+            load_balancer.streamed.lock().unwrap().answer(result(
+                "a",
+                false,
+                "Unknown parent job provided",
+            ));
+            assert!(load_balancer.find_buffered("a").is_none());
+            assert_eq!(
+                load_balancer.find_rejected("a").unwrap().1,
+                "Unknown parent job provided"
+            );
+
+            load_balancer
+                .streamed
+                .lock()
+                .unwrap()
+                .abandon(2, "http://node-2");
+            assert!(load_balancer.find_buffered("b").is_none());
+            assert!(load_balancer
+                .find_rejected("b")
+                .unwrap()
+                .1
+                .contains("http://node-2"));
+        }
+    }
+}
@@ -1,25 +1,43 @@
 use dotenv::dotenv;
-use enqueue_manager::job_management::EnqueueRequest;
+use dpq_client::pool::NodePool;
+use dpq_client::ClientError;
+use enqueue_manager::job_management::job_service_client::JobServiceClient;
+use enqueue_manager::job_management::{
+    CancelJobRequest, EnqueueBatchRequest, EnqueueBatchResponse, EnqueueRequest, JobStatusRequest,
+    JobStatusResponse, ResultRequest, ResultResponse,
+};
 use enqueue_manager::load_balancer::load_balancer_logic::LoadBalancer;
 use enqueue_manager::manager_state::ManagerState;
-use log::error;
-use rocket::http::Status;
+use log::{error, info};
+use rocket::http::{ContentType, Status};
 use rocket::serde::json::Json;
 use rocket::{Build, Rocket};
 use serde::{Deserialize, Serialize};
 use std::env;
 use std::sync::Arc;
+use std::time::Duration;
 use tokio::sync::Mutex;
+use uuid::Uuid;
 
 #[macro_use]
 extern crate rocket;
 
+/// How long to wait for a connection to a node.
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(5);
+/// How long a request to a node may take, on top of any time it is asked to wait on the node.
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(10);
+/// How many times a request that failed to reach a node is retried.
+const MAX_RETRIES: u32 = 3;
+/// The delay before the first retry, doubled on every retry after it.
+const RETRY_BACKOFF: Duration = Duration::from_millis(100);
+
 #[launch]
 async fn rocket() -> Rocket<Build> {
     let mut nodes: Vec<String> = get_nodes();
     log4rs::init_file("log4rs.yaml", Default::default()).unwrap();
 
     let state = ManagerState::new(nodes.clone());
+    let pool: NodePool = NodePool::new(nodes.clone(), CONNECT_TIMEOUT, MAX_RETRIES, RETRY_BACKOFF);
 
     let load_balancer: LoadBalancer = match LoadBalancer::new(&mut nodes).await {
         Ok(lb) => lb,
@@ -31,8 +49,9 @@ async fn rocket() -> Rocket<Build> {
 
     rocket::build()
         .manage(state)
-        .manage(load_balancer)
-        .mount("/", routes![enqueue])
+        .manage(pool)
+        .manage(Arc::new(Mutex::new(load_balancer)))
+        .mount("/", routes![enqueue, enqueue_batch, cancel, result, status])
 }
 
 fn get_nodes() -> Vec<String> {
@@ -51,11 +70,100 @@ fn get_nodes() -> Vec<String> {
 pub struct EnqueueJobRequest {
     priority: i32,
     payload: Vec<u8>,
+    /// Jobs that must be acked before this job is queued
+    #[serde(default)]
+    depends_on: Vec<String>,
+    /// Named queue to add the job to, the default queue if not provided
+    #[serde(default)]
+    queue: String,
+    /// Tenant that owns the job, the default tenant if not provided
+    #[serde(default)]
+    tenant_id: String,
+    /// Jobs sharing a key are delivered one at a time in the order they were enqueued
+    #[serde(default)]
+    ordering_key: String,
+    /// Notified when the job completes, fails or is dead lettered
+    #[serde(default)]
+    callback_url: String,
+    /// Unix time in milliseconds by which the job should be completed, 0 for none
+    #[serde(default)]
+    deadline: i64,
 }
 
+/// The response to an enqueue request.
+/// `tracking_id`: Identifies the job in `GET /jobs/<id>` before and after it is assigned a job id.
 #[derive(Debug, Serialize, Deserialize)]
 pub struct EnqueueResponse {
     message: String,
+    tracking_id: String,
+}
+
+/// A request to enqueue many jobs at once.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct BatchEnqueueRequest {
+    jobs: Vec<EnqueueJobRequest>,
+}
+
+/// The outcome of enqueueing a single job of a batch.
+/// `job_id`: The id assigned to the job, set if it was enqueued.
+/// `error`: Why the job was rejected, set if it was not.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct BatchEnqueueResult {
+    tracking_id: String,
+    success: bool,
+    job_id: Option<String>,
+    error: Option<String>,
+}
+
+/// The response to a batch enqueue request, with one result per job in the order they were
+/// submitted.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct BatchEnqueueResponse {
+    results: Vec<BatchEnqueueResult>,
+}
+
+/// The state of a job aggregated from the load balancer buffer and the leader.
+/// `job_id`: The id of the job, not assigned while the job is buffered.
+/// `state`: buffered, rejected, waiting, queued, leased, failed, completed, dead_lettered or
+/// cancelled.
+/// `created_at`: Unix time the job was created, not set while the job is buffered.
+/// `finished_at`: Unix time the job finished, if it has.
+/// `error`: Why the job was not enqueued, set if it was rejected.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct JobStatus {
+    job_id: Option<String>,
+    tracking_id: String,
+    state: String,
+    priority: i32,
+    attempts: i32,
+    queue: String,
+    tenant_id: String,
+    progress_percent: i32,
+    progress_message: String,
+    created_at: Option<i64>,
+    finished_at: Option<i64>,
+    error: Option<String>,
+}
+
+impl JobStatus {
+    /// The status of a job that was never assigned a job id, because it is still buffered or was
+    /// rejected.
+    fn unassigned(job: EnqueueRequest, state: &str, error: Option<String>) -> Self {
+        JobStatus {
+            job_id: None,
+            tracking_id: job.tracking_id,
+            state: state.to_string(),
+            priority: job.priority,
+            attempts: 0,
+            queue: job.queue,
+            tenant_id: job.tenant_id,
+            progress_percent: 0,
+            progress_message: String::new(),
+            created_at: None,
+            finished_at: None,
+            error,
+        }
+    }
 }
 
 #[post("/enqueue", format = "json", data = "<request>")]
@@ -67,7 +175,15 @@ pub async fn enqueue(
     let enqueue_request: EnqueueRequest = EnqueueRequest {
         priority: request.priority,
         payload: request.payload.clone(),
+        depends_on: request.depends_on.clone(),
+        queue: request.queue.clone(),
+        tenant_id: request.tenant_id.clone(),
+        ordering_key: request.ordering_key.clone(),
+        callback_url: request.callback_url.clone(),
+        deadline: request.deadline,
+        tracking_id: Uuid::new_v4().to_string(),
     };
+    let tracking_id: String = enqueue_request.tracking_id.clone();
 
     let mut state = manager_state.lock().await;
     let mut load_bal = load_balancer.lock().await;
@@ -77,5 +193,251 @@ pub async fn enqueue(
 
     Ok(Json(EnqueueResponse {
         message: "Job successfully added to queue".to_string(),
+        tracking_id,
+    }))
+}
+
+/// Enqueues many jobs at once. Unlike `/enqueue` the batch is not buffered but sent straight to
+/// a node, so the response reports whether each job was enqueued along with its job id. Jobs
+/// that were rejected can be resubmitted on their own.
+#[post("/enqueue/batch", format = "json", data = "<request>")]
+pub async fn enqueue_batch(
+    request: Json<BatchEnqueueRequest>,
+    manager_state: &rocket::State<Arc<Mutex<ManagerState>>>,
+    pool: &rocket::State<NodePool>,
+) -> Result<Json<BatchEnqueueResponse>, Status> {
+    let batch: EnqueueBatchRequest = EnqueueBatchRequest {
+        jobs: request
+            .into_inner()
+            .jobs
+            .into_iter()
+            .map(|job| EnqueueRequest {
+                priority: job.priority,
+                payload: job.payload,
+                depends_on: job.depends_on,
+                queue: job.queue,
+                tenant_id: job.tenant_id,
+                ordering_key: job.ordering_key,
+                callback_url: job.callback_url,
+                deadline: job.deadline,
+                tracking_id: Uuid::new_v4().to_string(),
+            })
+            .collect(),
+    };
+
+    manager_state.lock().await.increment_time();
+
+    // A batch that reached a node is not sent again, so its jobs are never enqueued twice
+    let response: EnqueueBatchResponse = match pool
+        .call_at_most_once(None, REQUEST_TIMEOUT, |channel, node| {
+            info!(target:"request_logger","Job Service EnqueueBatch request to address {}", node);
+            let batch = batch.clone();
+            async move {
+                let response = JobServiceClient::new(channel).enqueue_batch(batch).await?;
+                Ok(response.into_inner())
+            }
+        })
+        .await
+    {
+        Ok(res) => res,
+        Err(ClientError::Rpc(status)) if status.code() == tonic::Code::InvalidArgument => {
+            return Err(Status::BadRequest)
+        }
+        Err(e) => {
+            error!(target:"error_logger","Failed to obtain enqueue batch response: {}",e);
+            return Err(Status::ServiceUnavailable);
+        }
+    };
+
+    Ok(Json(BatchEnqueueResponse {
+        results: response
+            .results
+            .into_iter()
+            .map(|result| BatchEnqueueResult {
+                tracking_id: result.tracking_id,
+                success: result.success,
+                job_id: result.job.map(|job| job.job_id),
+                error: Some(result.error).filter(|e| !e.is_empty()),
+            })
+            .collect(),
+    }))
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct CancelResponse {
+    message: String,
+}
+
+/// Cancels a job that has already been distributed to the nodes.
+/// Every node is asked to cancel the job, the request succeeds if any node cancelled it.
+#[delete("/jobs/<job_id>")]
+pub async fn cancel(
+    job_id: String,
+    manager_state: &rocket::State<Arc<Mutex<ManagerState>>>,
+    pool: &rocket::State<NodePool>,
+) -> Result<Json<CancelResponse>, Status> {
+    manager_state.lock().await.increment_time();
+
+    let mut cancelled: bool = false;
+
+    for node in pool.addresses() {
+        info!(target:"request_logger","Job Service CancelJob request to address {}", node);
+
+        let mut client = match pool.channel(&node).await {
+            Ok(channel) => JobServiceClient::new(channel),
+            Err(_) => {
+                error!(target:"error_logger","Failed to connect to node at {}",node);
+                continue;
+            }
+        };
+
+        match client
+            .cancel_job(CancelJobRequest {
+                job_id: job_id.clone(),
+            })
+            .await
+        {
+            Ok(res) => cancelled |= res.into_inner().success,
+            Err(_) => {
+                error!(target:"error_logger","Failed to obtain cancel response from node at {}",node);
+            }
+        }
+    }
+
+    if !cancelled {
+        return Err(Status::NotFound);
+    }
+
+    Ok(Json(CancelResponse {
+        message: format!("Job with job_id={} has been cancelled", job_id),
+    }))
+}
+
+/// JobResultResponse is the response sent when the result of a job is requested.
+#[derive(Responder)]
+pub enum JobResultResponse {
+    /// The job completed, the body is its result.
+    #[response(status = 200)]
+    Ready(Vec<u8>, ContentType),
+    /// The job completed without attaching a result.
+    #[response(status = 204)]
+    Empty(()),
+    /// The job has not finished yet.
+    #[response(status = 202)]
+    Pending(String),
+}
+
+/// Returns the result attached to a job when it was acked.
+/// If `timeout` is provided the request waits up to that many seconds for the job to finish.
+#[get("/jobs/<job_id>/result?<timeout>")]
+pub async fn result(
+    job_id: String,
+    timeout: Option<i32>,
+    manager_state: &rocket::State<Arc<Mutex<ManagerState>>>,
+    pool: &rocket::State<NodePool>,
+) -> Result<JobResultResponse, Status> {
+    manager_state.lock().await.increment_time();
+
+    let wait: Duration = Duration::from_secs(timeout.unwrap_or(0).max(0) as u64);
+    let response: ResultResponse = match pool
+        .call(None, REQUEST_TIMEOUT + wait, |channel, node| {
+            info!(target:"request_logger","Job Service GetResult request to address {}", node);
+            let request = ResultRequest {
+                job_id: job_id.clone(),
+                timeout_seconds: timeout.unwrap_or(0),
+            };
+            async move {
+                let response = JobServiceClient::new(channel).get_result(request).await?;
+                Ok(response.into_inner())
+            }
+        })
+        .await
+    {
+        Ok(res) => res,
+        Err(ClientError::Rpc(status)) if status.code() == tonic::Code::NotFound => {
+            return Err(Status::NotFound)
+        }
+        Err(e) => {
+            error!(target:"error_logger","Failed to obtain result response: {}",e);
+            return Err(Status::ServiceUnavailable);
+        }
+    };
+
+    match (response.status.as_str(), response.result) {
+        ("completed", Some(result)) => {
+            let content_type: ContentType =
+                ContentType::parse_flexible(&result.content_type).unwrap_or(ContentType::Binary);
+            Ok(JobResultResponse::Ready(result.data, content_type))
+        }
+        ("completed", None) => Ok(JobResultResponse::Empty(())),
+        // The job will never produce a result
+        ("dead_lettered", _) | ("cancelled", _) => Err(Status::Gone),
+        (status, _) => Ok(JobResultResponse::Pending(format!(
+            "Job with job_id={} is {}",
+            job_id, status
+        ))),
+    }
+}
+
+/// Returns the state of a job, looked up by its job id or by the tracking id returned when it was
+/// enqueued. Jobs that have not been distributed yet are reported as buffered, and jobs the nodes
+/// did not enqueue as rejected along with the reason.
+#[get("/jobs/<id>")]
+pub async fn status(
+    id: String,
+    manager_state: &rocket::State<Arc<Mutex<ManagerState>>>,
+    load_balancer: &rocket::State<Arc<Mutex<LoadBalancer>>>,
+    pool: &rocket::State<NodePool>,
+) -> Result<Json<JobStatus>, Status> {
+    if let Some(job) = load_balancer.lock().await.find_buffered(&id) {
+        return Ok(Json(JobStatus::unassigned(job, "buffered", None)));
+    }
+
+    manager_state.lock().await.increment_time();
+
+    let response: JobStatusResponse = match pool
+        .call(None, REQUEST_TIMEOUT, |channel, node| {
+            info!(target:"request_logger","Job Service GetJobStatus request to address {}", node);
+            let request = JobStatusRequest { id: id.clone() };
+            async move {
+                let response = JobServiceClient::new(channel)
+                    .get_job_status(request)
+                    .await?;
+                Ok(response.into_inner())
+            }
+        })
+        .await
+    {
+        Ok(res) => res,
+        // A job the nodes rejected was never stored, so only the load balancer knows of it
+        Err(ClientError::Rpc(status)) if status.code() == tonic::Code::NotFound => {
+            return match load_balancer.lock().await.find_rejected(&id) {
+                Some((job, reason)) => {
+                    Ok(Json(JobStatus::unassigned(job, "rejected", Some(reason))))
+                }
+                None => Err(Status::NotFound),
+            };
+        }
+        Err(e) => {
+            error!(target:"error_logger","Failed to obtain job status response: {}",e);
+            return Err(Status::ServiceUnavailable);
+        }
+    };
+
+    let progress = response.progress.unwrap_or_default();
+
+    Ok(Json(JobStatus {
+        job_id: Some(response.job_id),
+        tracking_id: response.tracking_id,
+        state: response.status,
+        priority: response.priority,
+        attempts: response.attempts,
+        queue: response.queue,
+        tenant_id: response.tenant_id,
+        progress_percent: progress.percent,
+        progress_message: progress.message,
+        created_at: Some(response.created_at),
+        finished_at: Some(response.finished_at).filter(|t| *t > 0),
+        error: None,
     }))
 }
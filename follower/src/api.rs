@@ -1,8 +1,12 @@
 use crate::error::ApiError;
-use crate::min_heap::{HeapNode, MinHeap};
+use crate::job_management::JobResult;
+use crate::jobs::{self, LeasedJob, NodeId, QueuedJob};
+use crate::leader::Leader;
+use crate::queues::{self, QueueSet};
+use crate::tenants::{self, TenantConfig};
 use log::error;
 use rocket::serde::json::Json;
-use rocket::{get, post};
+use rocket::{get, post, FromForm};
 use serde::{Deserialize, Serialize};
 use std::sync::Arc;
 use tokio::sync::Mutex;
@@ -10,14 +14,21 @@ use tokio_postgres::Client;
 use uuid::Uuid;
 
 /// DequeueResponse represents the response sent by the node when the /dequeue route is used.
-/// `job_id`: The randomly generated job_id
+/// `job_id`: The id the job was given when it was created
 /// `priority`: The assigned priority of the job.
 /// `payload': the byte payload the job contains.
+/// `queue`: The named queue the job was taken from.
+/// `lease_id`: The consumer's lease on the job, renewed with heartbeats.
+/// `visibility_timeout`: The number of seconds until the job is requeued unless the lease is
+/// renewed.
 #[derive(Debug, Deserialize, Serialize)]
 pub struct DequeueResponse {
-    job_id: Uuid,
+    job_id: i64,
     priority: i32,
     payload: Vec<u8>,
+    queue: String,
+    lease_id: Uuid,
+    visibility_timeout: i32,
 }
 
 /// BatchDequeueResponse represents the response sent by the node when the /dequeue/<amount> route
@@ -31,10 +42,27 @@ pub struct BatchDequeueResponse {
 /// EnqueueRequest represents the request expected when the /enqueue route is used.
 /// `priority`: The priority of the potential job.
 /// `payload`: The byte payload of the job to be processed.
+/// `queue`: The named queue to add the job to, the default queue if not provided.
+/// `tenant_id`: The tenant that owns the job, the default tenant if not provided.
+/// `ordering_key`: Jobs sharing a key are delivered in the order they were enqueued, one at a
+/// time. Jobs without a key are not ordered against other jobs.
+/// `callback_url`: The url notified when the job completes, fails or is dead lettered.
+/// `deadline`: Unix time in milliseconds by which the job should be completed, if it has one.
+/// Whether the deadline was met is recorded when the job finishes.
 #[derive(Debug, Deserialize, Serialize)]
 pub struct EnqueueRequest {
     priority: i32,
     payload: Vec<u8>,
+    #[serde(default)]
+    queue: String,
+    #[serde(default)]
+    tenant_id: String,
+    #[serde(default)]
+    ordering_key: String,
+    #[serde(default)]
+    callback_url: String,
+    #[serde(default)]
+    deadline: Option<i64>,
 }
 
 /// CreationResponse is the response sent by the node when a job is successfully added into the
@@ -44,7 +72,7 @@ pub struct EnqueueRequest {
 #[derive(Debug, Serialize, Deserialize)]
 pub struct CreationResponse {
     message: String,
-    job_id: Uuid,
+    job_id: i64,
 }
 
 /// Update Request to update a job currently in the queue.
@@ -53,122 +81,311 @@ pub struct CreationResponse {
 #[derive(Debug, Serialize, Deserialize)]
 pub struct UpdateRequest {
     priority: i32,
-    job_id: Uuid,
+    job_id: i64,
 }
 #[derive(Debug, Serialize, Deserialize)]
 pub struct UpdateResponse {
     message: String,
 }
 
-#[get("/dequeue")]
+/// JobStatusResponse represents the response sent by the node when the /jobs/<job_id> route is
+/// used.
+/// `status`: The lifecycle state of the job.
+/// `attempts`: The number of failed processing attempts.
+/// `progress_percent`: The progress last reported by the consumer processing the job.
+/// `progress_message`: The message last reported by the consumer processing the job.
+/// `lease_expires_at`: Unix time at which the current lease expires, if the job is leased.
+#[derive(Debug, Deserialize, Serialize)]
+pub struct JobStatusResponse {
+    job_id: i64,
+    status: String,
+    priority: i32,
+    queue: String,
+    tenant_id: String,
+    attempts: i32,
+    progress_percent: i32,
+    progress_message: String,
+    lease_expires_at: Option<i64>,
+}
+
+/// CallbackDelivery represents a single delivery of a job's callback.
+/// `event`: The event being delivered.
+/// `status`: The state of the delivery: pending, delivered or failed.
+/// `attempts`: The number of attempts made so far.
+/// `response_status`: The HTTP status of the last response, if one was received.
+/// `last_error`: Why the last attempt failed.
+#[derive(Debug, Deserialize, Serialize)]
+pub struct CallbackDelivery {
+    delivery_id: i64,
+    event: String,
+    url: String,
+    status: String,
+    attempts: i32,
+    response_status: Option<i32>,
+    last_error: Option<String>,
+}
+
+/// AckRequest represents the optional body of the /jobs/<job_id>/ack route.
+/// `result`: The output of the job.
+/// `content_type`: The media type of the result, application/octet-stream if not provided.
+#[derive(Debug, Deserialize, Serialize)]
+pub struct AckRequest {
+    result: Vec<u8>,
+    #[serde(default)]
+    content_type: String,
+}
+
+/// AckResponse is the response sent by the node when a job is acked or nacked.
+/// `message`: The message describing the outcome for the job and its dependents.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct AckResponse {
+    message: String,
+}
+
+/// JobFilter holds the query parameters of the /admin/jobs route. Every filter is optional.
+/// `state`: Only list jobs in this lifecycle state.
+/// `min_priority`, `max_priority`: Only list jobs with a priority in this range.
+/// `created_after`, `created_before`: Only list jobs created in this range, as unix time.
+/// `cursor`: The `next_cursor` of the previous page.
+/// `limit`: The maximum number of jobs in the page, 100 by default.
+#[derive(Debug, FromForm)]
+pub struct JobFilter {
+    state: Option<String>,
+    min_priority: Option<i32>,
+    max_priority: Option<i32>,
+    queue: Option<String>,
+    tenant_id: Option<String>,
+    created_after: Option<i64>,
+    created_before: Option<i64>,
+    cursor: Option<String>,
+    limit: Option<i64>,
+}
+
+/// JobSummary represents a single job listed by the /admin/jobs route.
+/// `created_at`: Unix time at which the job was created.
+#[derive(Debug, Deserialize, Serialize)]
+pub struct JobSummary {
+    job_id: i64,
+    status: String,
+    priority: i32,
+    queue: String,
+    tenant_id: String,
+    attempts: i32,
+    created_at: i64,
+}
+
+/// JobListResponse represents the response sent by the node when the /admin/jobs route is used.
+/// `next_cursor`: The cursor of the next page, None if this is the last page.
+#[derive(Debug, Deserialize, Serialize)]
+pub struct JobListResponse {
+    jobs: Vec<JobSummary>,
+    next_cursor: Option<String>,
+}
+
+/// QueuedJobSummary represents a job at the top of a queue on this node.
+/// `effective_priority`: The priority of the job after aging, which decides its position.
+/// `enqueue_time`: The logical time at which the job was inserted into the heap, unix time in
+/// milliseconds in earliest deadline first queues.
+/// `deadline`: Unix time in milliseconds by which the job should be completed, if it has one.
+#[derive(Debug, Deserialize, Serialize)]
+pub struct QueuedJobSummary {
+    job_id: i64,
+    priority: u32,
+    effective_priority: u32,
+    enqueue_time: u64,
+    deadline: Option<u64>,
+}
+
+/// SlaSummary reports how many jobs of a priority in a queue met their deadline, as returned by
+/// the /admin/sla route. Only jobs with a deadline are counted.
+/// `met`: The number of jobs completed by their deadline.
+/// `missed`: The number of jobs completed after their deadline or dead lettered.
+/// `overdue`: The number of unfinished jobs already past their deadline.
+/// `attainment`: The share of finished jobs that met their deadline, None if none have finished.
+#[derive(Debug, Deserialize, Serialize)]
+pub struct SlaSummary {
+    queue: String,
+    priority: i32,
+    met: i64,
+    missed: i64,
+    overdue: i64,
+    attainment: Option<f64>,
+}
+
+/// The content type of a job result when the consumer does not provide one.
+const DEFAULT_CONTENT_TYPE: &str = "application/octet-stream";
+/// The number of jobs listed per page when no limit is provided.
+const DEFAULT_PAGE_SIZE: i64 = 100;
+/// The largest page of jobs that may be requested.
+const MAX_PAGE_SIZE: i64 = 1000;
+
+#[get("/dequeue?<queue>")]
 pub async fn dequeue(
-    db: &rocket::State<Arc<Mutex<Client>>>,
-    heap: &rocket::State<Arc<Mutex<MinHeap>>>,
+    queue: Option<String>,
+    db: &rocket::State<Arc<Client>>,
+    node: &rocket::State<NodeId>,
+    queues: &rocket::State<Arc<Mutex<QueueSet>>>,
     clock: &rocket::State<Arc<Mutex<u64>>>,
 ) -> Result<Json<DequeueResponse>, ApiError> {
-    let client = db.lock().await;
+    let client: &Client = db;
+    let queue: String = queues::queue_name(&queue.unwrap_or_default()).to_string();
+    let queues: &Mutex<QueueSet> = queues;
 
-    let node: HeapNode = match heap.lock().await.get_top() {
-        Some(n) => n,
+    // Increment logical time
+    let time: u64 = {
+        let mut clock = clock.lock().await;
+        *clock += 1;
+        *clock
+    };
+
+    jobs::expire_leases(client, **node, queues, |queues| queues, time).await?;
+
+    let names: Vec<String> = vec![queue.clone()];
+    let (job, visibility_timeout): (LeasedJob, i32) = match jobs::lease_next(
+        queues,
+        |queues| queues,
+        &names,
+        time,
+        |job_id, timeout| jobs::lease_job(client, job_id, timeout),
+    )
+    .await?
+    {
+        Some(leased) => leased,
         None => {
             error!(target:"error_logger","Error: Attempt to pull from empty heap");
             return Err(ApiError::EmptyHeapError);
         }
     };
 
-    // Increment logical time
-    *clock.lock().await += 1;
-
-    let query = client
-        .prepare("SELECT * FROM jobs WHERE job_id = $1")
-        .await
-        .map_err(|_| {
-            error!(target:"error_logger","Error: Failed to create SELECT query");
-            ApiError::DatabaseError("Error creating query".to_string())
-        })?;
-
-    let row = client
-        .query_one(&query, &[&(node.job_id)])
-        .await
-        .map_err(|_| {
-            error!(target:"error_logger","Error: Attempt to SELECT from database failed");
-            ApiError::DatabaseError("Error database SELECT query failed.".to_string())
-        })?;
-
     Ok(Json(DequeueResponse {
-        job_id: row.get(0),
-        priority: row.get(1),
-        payload: row.get(2),
+        job_id: job.job_id,
+        priority: job.priority,
+        payload: job.payload,
+        queue,
+        lease_id: job.lease_id,
+        visibility_timeout,
     }))
 }
 
-#[get("/dequeue/<amount>")]
+#[get("/dequeue/<amount>?<queue>")]
 pub async fn dequeue_amount(
     amount: String,
-    db: &rocket::State<Arc<Mutex<Client>>>,
-    heap: &rocket::State<Arc<Mutex<MinHeap>>>,
+    queue: Option<String>,
+    db: &rocket::State<Arc<Client>>,
+    node: &rocket::State<NodeId>,
+    queues: &rocket::State<Arc<Mutex<QueueSet>>>,
     clock: &rocket::State<Arc<Mutex<u64>>>,
 ) -> Result<Json<BatchDequeueResponse>, ApiError> {
-    let client = db.lock().await;
-    let mut heap = heap.lock().await;
-
-    if heap.heap.is_empty() {
-        return Err(ApiError::EmptyHeapError);
-    }
-
-    let mut jobs: Vec<DequeueResponse> = Vec::new();
+    let client: &Client = db;
+    let queue: String = queues::queue_name(&queue.unwrap_or_default()).to_string();
+    let queues: &Mutex<QueueSet> = queues;
 
     let amount: usize = amount.parse::<usize>().map_err(|_| {
         error!(target:"error_logger","Error: Non-numerical amount provided by GET request in /dequeue/<amount>");
-        ApiError::InternalServerError("Provided non numerical amount".to_string())
+        ApiError::BadRequestError("Provided non numerical amount".to_string())
     })?;
 
     // Increment logical time
-    *clock.lock().await += 1;
+    let time: u64 = {
+        let mut clock = clock.lock().await;
+        *clock += 1;
+        *clock
+    };
 
-    for _ in 0..amount {
-        let node: HeapNode = match heap.get_top() {
-            Some(n) => n,
-            None => return Err(ApiError::EmptyHeapError),
-        };
+    jobs::expire_leases(client, **node, queues, |queues| queues, time).await?;
 
-        let query = client
-            .prepare("SELECT * FROM jobs WHERE job_id = $1")
-            .await
-            .map_err(|_| {
-                error!("Error: Failed to create SELECT query");
-                ApiError::DatabaseError(format!("Error creating query"))
-            })?;
-
-        let row = client
-            .query_one(&query, &[&(node.job_id)])
-            .await
-            .map_err(|_| {
-                error!(target:"error_logger","Error: Failed to run SELECT query");
-                ApiError::DatabaseError("Error database SELECT query failed.".to_string())
-            })?;
+    let names: Vec<String> = vec![queue.clone()];
+    let mut jobs: Vec<DequeueResponse> = Vec::new();
+
+    // A queue that runs dry part way through returns the jobs leased so far
+    while jobs.len() < amount {
+        let (job, visibility_timeout): (LeasedJob, i32) = match jobs::lease_next(
+            queues,
+            |queues| queues,
+            &names,
+            time,
+            |job_id, timeout| jobs::lease_job(client, job_id, timeout),
+        )
+        .await?
+        {
+            Some(leased) => leased,
+            None => break,
+        };
 
         jobs.push(DequeueResponse {
-            job_id: row.get(0),
-            priority: row.get(1),
-            payload: row.get(2),
+            job_id: job.job_id,
+            priority: job.priority,
+            payload: job.payload,
+            queue: queue.clone(),
+            lease_id: job.lease_id,
+            visibility_timeout,
         });
     }
 
+    if jobs.is_empty() {
+        error!(target:"error_logger","Error: Attempt to pull from empty heap");
+        return Err(ApiError::EmptyHeapError);
+    }
+
     Ok(Json(BatchDequeueResponse { jobs }))
 }
 
 #[post("/enqueue", format = "json", data = "<request>")]
 pub async fn enqueue(
     request: Json<EnqueueRequest>,
-    db: &rocket::State<Arc<Mutex<Client>>>,
-    heap: &rocket::State<Arc<Mutex<MinHeap>>>,
+    db: &rocket::State<Arc<Client>>,
+    node: &rocket::State<NodeId>,
+    queues: &rocket::State<Arc<Mutex<QueueSet>>>,
     clock: &rocket::State<Arc<Mutex<u64>>>,
 ) -> Result<Json<CreationResponse>, ApiError> {
-    let client = db.lock().await;
+    let client: &Client = db;
+    let queue: String = queues::queue_name(&request.queue).to_string();
+    let tenant_id: String = tenants::tenant_name(&request.tenant_id).to_string();
+
+    {
+        let mut queues = queues.lock().await;
+        let tenant: &TenantConfig = queues.tenant_config(&tenant_id);
+
+        if request.payload.len() > tenant.max_payload_bytes {
+            error!(target:"error_logger","Error: Payload of {} bytes exceeds the quota of tenant {}",request.payload.len(),tenant_id);
+            return Err(ApiError::QuotaExceededError(format!(
+                "Payload exceeds {} bytes",
+                tenant.max_payload_bytes
+            )));
+        }
+
+        if queues.tenant_depth(&tenant_id) >= tenant.max_depth {
+            error!(target:"error_logger","Error: Tenant {} has reached its maximum queue depth",tenant_id);
+            return Err(ApiError::QuotaExceededError(format!(
+                "Tenant {} has reached its maximum queue depth",
+                tenant_id
+            )));
+        }
+
+        if !request.callback_url.is_empty() && !valid_callback_url(&request.callback_url) {
+            error!(target:"error_logger","Error: Invalid callback url {} provided",request.callback_url);
+            return Err(ApiError::BadRequestError(
+                "Callback url must be an http or https url".to_string(),
+            ));
+        }
+
+        if request.deadline.is_some_and(|deadline| deadline <= 0) {
+            error!(target:"error_logger","Error: Invalid deadline {:?} provided",request.deadline);
+            return Err(ApiError::BadRequestError(
+                "Deadline must be a positive unix time in milliseconds".to_string(),
+            ));
+        }
+
+        if queues.queue_mut(&queue).is_full() {
+            error!(target:"error_logger","Error: Attempt to enqueue onto full queue {}",queue);
+            return Err(ApiError::QueueFullError(queue));
+        }
+    }
 
     let query = client
-        .prepare("INSERT INTO jobs (priority, payload) VALUES ($1,$2) RETURNING job_id")
+        .prepare(
+            "INSERT INTO jobs (priority, payload, queue, tenant_id, ordering_key, callback_url, deadline) VALUES ($1,$2,$3,$4,$5,$6,to_timestamp($7::BIGINT / 1000.0)) RETURNING job_id",
+        )
         .await
         .map_err(|_| {
             error!(target: "error_logger","Error: Failed to create INSERT query");
@@ -176,25 +393,44 @@ pub async fn enqueue(
         })?;
 
     let row = client
-        .query_one(&query, &[&request.priority, &request.payload])
+        .query_one(
+            &query,
+            &[
+                &request.priority,
+                &request.payload,
+                &queue,
+                &tenant_id,
+                &request.ordering_key,
+                &request.callback_url,
+                &request.deadline,
+            ],
+        )
         .await
         .map_err(|_| {
             error!(target:"error_logger","Error: Failed to run INSERT query");
             ApiError::DatabaseError("Error creating row".to_string())
         })?;
 
-    let job_id: Uuid = row.get(0);
+    let job_id: i64 = row.get(0);
 
     // Increment logical time
-    *clock.lock().await += 1;
+    let time: u64 = {
+        let mut clock = clock.lock().await;
+        *clock += 1;
+        *clock
+    };
 
-    heap.lock()
-        .await
-        .insert(request.priority as u32, job_id, *clock.lock().await);
+    let job: QueuedJob = QueuedJob {
+        job_id,
+        priority: request.priority,
+        queue,
+        tenant_id,
+        ordering_key: request.ordering_key.clone(),
+        deadline: request.deadline,
+    };
 
-    heap.lock()
-        .await
-        .calculate_effective_priority(*clock.lock().await);
+    let enqueue_time: u64 = queues.lock().await.insert_job(&job, time);
+    jobs::record_enqueue_times(client, **node, &[(job_id, enqueue_time)]).await?;
 
     println!("Inserted job with job_id {} into jobs table", job_id);
 
@@ -207,15 +443,16 @@ pub async fn enqueue(
 #[post("/update", format = "json", data = "<request>")]
 pub async fn update(
     request: Json<UpdateRequest>,
-    db: &rocket::State<Arc<Mutex<Client>>>,
-    heap: &rocket::State<Arc<Mutex<MinHeap>>>,
+    db: &rocket::State<Arc<Client>>,
+    queues: &rocket::State<Arc<Mutex<QueueSet>>>,
     clock: &rocket::State<Arc<Mutex<u64>>>,
 ) -> Result<Json<UpdateResponse>, ApiError> {
-    let mut heap = heap.lock().await;
-
-    heap.change_priority(request.job_id, request.priority as u32);
+    queues
+        .lock()
+        .await
+        .change_priority(request.job_id, request.priority as u32);
 
-    let client = db.lock().await;
+    let client: &Client = db;
 
     let _ = client
         .execute(
@@ -231,8 +468,6 @@ pub async fn update(
     // Increment logical time
     *clock.lock().await += 1;
 
-    heap.calculate_effective_priority(*clock.lock().await);
-
     Ok(Json(UpdateResponse {
         message: format!(
             "Job with job_id={} has been successfully updated",
@@ -240,3 +475,388 @@ pub async fn update(
         ),
     }))
 }
+
+/// Acknowledges that a job was processed successfully, optionally attaching its result.
+/// Dependent jobs whose parents have now all completed are released and replicated to every
+/// follower through the leader.
+#[post("/jobs/<job_id>/ack?<lease_id>", data = "<request>")]
+#[allow(clippy::too_many_arguments)]
+pub async fn ack(
+    job_id: String,
+    lease_id: Option<String>,
+    request: Option<Json<AckRequest>>,
+    db: &rocket::State<Arc<Client>>,
+    node: &rocket::State<NodeId>,
+    leader: &rocket::State<Leader>,
+    queues: &rocket::State<Arc<Mutex<QueueSet>>>,
+    clock: &rocket::State<Arc<Mutex<u64>>>,
+) -> Result<Json<AckResponse>, ApiError> {
+    let job_id: i64 = parse_job_id(&job_id)?;
+    let lease_id: Option<Uuid> = lease_id.as_deref().map(parse_lease_id).transpose()?;
+    let result: Option<JobResult> = request.map(|r| {
+        let r: AckRequest = r.into_inner();
+        JobResult {
+            data: r.result,
+            content_type: if r.content_type.is_empty() {
+                DEFAULT_CONTENT_TYPE.to_string()
+            } else {
+                r.content_type
+            },
+        }
+    });
+    let client: &Client = db;
+
+    let released: Vec<QueuedJob> =
+        jobs::complete_job(client, job_id, lease_id, result.as_ref()).await?;
+    let count: usize = released.len();
+
+    // Released dependents come back from the leader like any other replicated job
+    let unreplicated: Vec<QueuedJob> = jobs::replicate_released(leader, released).await;
+
+    // Increment logical time
+    let time: u64 = {
+        let mut clock = clock.lock().await;
+        *clock += 1;
+        *clock
+    };
+
+    jobs::release_dependents(
+        client,
+        **node,
+        queues,
+        |queues| queues,
+        job_id,
+        &unreplicated,
+        time,
+    )
+    .await?;
+
+    Ok(Json(AckResponse {
+        message: format!(
+            "Job with job_id={} completed, {} dependent jobs released",
+            job_id, count
+        ),
+    }))
+}
+
+/// Reports that a job failed.
+/// The job is requeued until it has used up the attempts allowed by its queue's retry policy,
+/// after which it is dead lettered and the failure cascades to every job that depends on it.
+#[post("/jobs/<job_id>/nack?<lease_id>")]
+pub async fn nack(
+    job_id: String,
+    lease_id: Option<String>,
+    db: &rocket::State<Arc<Client>>,
+    node: &rocket::State<NodeId>,
+    queues: &rocket::State<Arc<Mutex<QueueSet>>>,
+    clock: &rocket::State<Arc<Mutex<u64>>>,
+) -> Result<Json<AckResponse>, ApiError> {
+    let job_id: i64 = parse_job_id(&job_id)?;
+    let lease_id: Option<Uuid> = lease_id.as_deref().map(parse_lease_id).transpose()?;
+    let client: &Client = db;
+
+    let (attempts, job): (i32, QueuedJob) = jobs::record_failure(client, job_id, lease_id).await?;
+
+    // Increment logical time
+    let time: u64 = {
+        let mut clock = clock.lock().await;
+        *clock += 1;
+        *clock
+    };
+
+    match jobs::retry_or_dead_letter(
+        client,
+        **node,
+        queues,
+        |queues| queues,
+        attempts,
+        &job,
+        time,
+    )
+    .await?
+    {
+        None => Ok(Json(AckResponse {
+            message: format!(
+                "Job with job_id={} requeued after attempt {}",
+                job_id, attempts
+            ),
+        })),
+        Some(cascaded) => Ok(Json(AckResponse {
+            message: format!(
+                "Job with job_id={} dead lettered, {} dependent jobs dead lettered",
+                job_id, cascaded
+            ),
+        })),
+    }
+}
+
+/// Returns the state of a job along with the progress last reported by the consumer processing
+/// it.
+#[get("/jobs/<job_id>")]
+pub async fn job_status(
+    job_id: String,
+    db: &rocket::State<Arc<Client>>,
+) -> Result<Json<JobStatusResponse>, ApiError> {
+    let job_id: i64 = parse_job_id(&job_id)?;
+    let client: &Client = db;
+
+    let row = client
+        .query_opt(
+            "SELECT job_id, status, priority, queue, tenant_id, attempts, progress_percent,
+                 progress_message, EXTRACT(EPOCH FROM lease_expires_at)::BIGINT
+             FROM jobs WHERE job_id = $1",
+            &[&job_id],
+        )
+        .await
+        .map_err(|_| {
+            error!(target:"error_logger","Error: Failed to run SELECT query on job {}",job_id);
+            ApiError::DatabaseError("Error database SELECT query failed.".to_string())
+        })?
+        .ok_or_else(|| ApiError::NotFoundError(format!("No job with job_id={}", job_id)))?;
+
+    let status: String = row.get(1);
+    let lease_expires_at: Option<i64> = if status == "leased" { row.get(8) } else { None };
+
+    Ok(Json(JobStatusResponse {
+        job_id: row.get(0),
+        status,
+        priority: row.get(2),
+        queue: row.get(3),
+        tenant_id: row.get(4),
+        attempts: row.get(5),
+        progress_percent: row.get(6),
+        progress_message: row.get(7),
+        lease_expires_at,
+    }))
+}
+
+/// Lists every delivery of a job's callback so failed deliveries can be inspected.
+#[get("/jobs/<job_id>/callbacks")]
+pub async fn callbacks(
+    job_id: String,
+    db: &rocket::State<Arc<Client>>,
+) -> Result<Json<Vec<CallbackDelivery>>, ApiError> {
+    let job_id: i64 = parse_job_id(&job_id)?;
+    let client: &Client = db;
+
+    let rows = client
+        .query(
+            "SELECT delivery_id, event, url, status, attempts, response_status, last_error
+             FROM callback_deliveries WHERE job_id = $1 ORDER BY delivery_id",
+            &[&job_id],
+        )
+        .await
+        .map_err(|_| {
+            error!(target:"error_logger","Error: Failed to run SELECT query on callbacks of job {}",job_id);
+            ApiError::DatabaseError("Error database SELECT query failed.".to_string())
+        })?;
+
+    Ok(Json(
+        rows.iter()
+            .map(|row| CallbackDelivery {
+                delivery_id: row.get(0),
+                event: row.get(1),
+                url: row.get(2),
+                status: row.get(3),
+                attempts: row.get(4),
+                response_status: row.get(5),
+                last_error: row.get(6),
+            })
+            .collect(),
+    ))
+}
+
+/// Lists jobs matching the given filters, oldest first. Pages are chained with the returned
+/// cursor, which stays stable while new jobs are being enqueued.
+#[get("/admin/jobs?<filter..>")]
+pub async fn list_jobs(
+    filter: JobFilter,
+    db: &rocket::State<Arc<Client>>,
+) -> Result<Json<JobListResponse>, ApiError> {
+    let limit: i64 = filter
+        .limit
+        .unwrap_or(DEFAULT_PAGE_SIZE)
+        .clamp(1, MAX_PAGE_SIZE);
+
+    let (cursor_time, cursor_id): (Option<i64>, Option<i64>) = match &filter.cursor {
+        Some(c) => {
+            let (time, id) = parse_cursor(c)?;
+            (Some(time), Some(id))
+        }
+        None => (None, None),
+    };
+
+    let client: &Client = db;
+
+    // Fetch one more job than requested to know whether there is a next page
+    let rows = client
+        .query(
+            "SELECT job_id, status, priority, queue, tenant_id, attempts,
+                 EXTRACT(EPOCH FROM created_at)::BIGINT,
+                 (EXTRACT(EPOCH FROM created_at) * 1000000)::BIGINT AS created_micros
+             FROM jobs
+             WHERE ($1::TEXT IS NULL OR status = $1)
+                 AND ($2::INT IS NULL OR priority >= $2)
+                 AND ($3::INT IS NULL OR priority <= $3)
+                 AND ($4::TEXT IS NULL OR queue = $4)
+                 AND ($5::TEXT IS NULL OR tenant_id = $5)
+                 AND ($6::BIGINT IS NULL OR created_at >= to_timestamp($6))
+                 AND ($7::BIGINT IS NULL OR created_at < to_timestamp($7))
+                 AND ($8::BIGINT IS NULL
+                     OR ((EXTRACT(EPOCH FROM created_at) * 1000000)::BIGINT, job_id) > ($8, $9))
+             ORDER BY created_micros, job_id
+             LIMIT $10",
+            &[
+                &filter.state,
+                &filter.min_priority,
+                &filter.max_priority,
+                &filter.queue,
+                &filter.tenant_id,
+                &filter.created_after,
+                &filter.created_before,
+                &cursor_time,
+                &cursor_id,
+                &(limit + 1),
+            ],
+        )
+        .await
+        .map_err(|_| {
+            error!(target:"error_logger","Error: Failed to run SELECT query listing jobs");
+            ApiError::DatabaseError("Error database SELECT query failed.".to_string())
+        })?;
+
+    let next_cursor: Option<String> = if rows.len() as i64 > limit {
+        let last = &rows[limit as usize - 1];
+        let created_micros: i64 = last.get(7);
+        let job_id: i64 = last.get(0);
+        Some(format!("{}_{}", created_micros, job_id))
+    } else {
+        None
+    };
+
+    Ok(Json(JobListResponse {
+        jobs: rows
+            .iter()
+            .take(limit as usize)
+            .map(|row| JobSummary {
+                job_id: row.get(0),
+                status: row.get(1),
+                priority: row.get(2),
+                queue: row.get(3),
+                tenant_id: row.get(4),
+                attempts: row.get(5),
+                created_at: row.get(6),
+            })
+            .collect(),
+        next_cursor,
+    }))
+}
+
+/// Lists the next `n` jobs that would be dequeued from a queue on this node, without removing
+/// them.
+#[get("/admin/queues/<queue>/top?<n>")]
+pub async fn peek_queue(
+    queue: String,
+    n: Option<usize>,
+    queues: &rocket::State<Arc<Mutex<QueueSet>>>,
+    clock: &rocket::State<Arc<Mutex<u64>>>,
+) -> Result<Json<Vec<QueuedJobSummary>>, ApiError> {
+    let n: usize = n.unwrap_or(10).min(MAX_PAGE_SIZE as usize);
+    let queues = queues.lock().await;
+    let now: u64 = *clock.lock().await;
+
+    let target = queues
+        .get(&queue)
+        .ok_or_else(|| ApiError::NotFoundError(format!("No queue named {}", queue)))?;
+
+    Ok(Json(
+        target
+            .peek_n(n)
+            .iter()
+            .map(|node| QueuedJobSummary {
+                job_id: node.job_id,
+                priority: node.priority,
+                effective_priority: target.effective_priority(node, now),
+                enqueue_time: node.enqueue_time,
+                deadline: node.deadline,
+            })
+            .collect(),
+    ))
+}
+
+/// Reports SLA attainment per queue and priority: how many jobs with a deadline met it, missed
+/// it, or are still unfinished past it.
+#[get("/admin/sla?<queue>")]
+pub async fn sla(
+    queue: Option<String>,
+    db: &rocket::State<Arc<Client>>,
+) -> Result<Json<Vec<SlaSummary>>, ApiError> {
+    let client: &Client = db;
+
+    let rows = client
+        .query(
+            "SELECT queue, priority,
+                 COUNT(*) FILTER (WHERE sla_met),
+                 COUNT(*) FILTER (WHERE NOT sla_met),
+                 COUNT(*) FILTER (WHERE finished_at IS NULL AND deadline < now())
+             FROM jobs
+             WHERE deadline IS NOT NULL AND status <> 'cancelled'
+                 AND ($1::TEXT IS NULL OR queue = $1)
+             GROUP BY queue, priority
+             ORDER BY queue, priority",
+            &[&queue],
+        )
+        .await
+        .map_err(|_| {
+            error!(target:"error_logger","Error: Failed to run SELECT query on SLA attainment");
+            ApiError::DatabaseError("Error database SELECT query failed.".to_string())
+        })?;
+
+    Ok(Json(
+        rows.iter()
+            .map(|row| {
+                let met: i64 = row.get(2);
+                let missed: i64 = row.get(3);
+                SlaSummary {
+                    queue: row.get(0),
+                    priority: row.get(1),
+                    met,
+                    missed,
+                    overdue: row.get(4),
+                    attainment: (met + missed > 0).then(|| met as f64 / (met + missed) as f64),
+                }
+            })
+            .collect(),
+    ))
+}
+
+fn valid_callback_url(url: &str) -> bool {
+    url.starts_with("http://") || url.starts_with("https://")
+}
+
+fn parse_job_id(job_id: &str) -> Result<i64, ApiError> {
+    job_id.parse::<i64>().map_err(|_| {
+        error!(target:"error_logger","Error: Invalid job id {} provided",job_id);
+        ApiError::BadRequestError("Provided invalid job id".to_string())
+    })
+}
+
+fn parse_lease_id(lease_id: &str) -> Result<Uuid, ApiError> {
+    Uuid::parse_str(lease_id).map_err(|_| {
+        error!(target:"error_logger","Error: Invalid lease id {} provided",lease_id);
+        ApiError::BadRequestError("Provided invalid lease id".to_string())
+    })
+}
+
+fn parse_cursor(cursor: &str) -> Result<(i64, i64), ApiError> {
+    let invalid = || {
+        error!(target:"error_logger","Error: Invalid cursor {} provided",cursor);
+        ApiError::BadRequestError("Provided invalid cursor".to_string())
+    };
+
+    let (time, id) = cursor.split_once('_').ok_or_else(invalid)?;
+    let time: i64 = time.parse::<i64>().map_err(|_| invalid())?;
+    let id: i64 = id.parse::<i64>().map_err(|_| invalid())?;
+
+    Ok((time, id))
+}
@@ -0,0 +1,287 @@
+use crate::error::ApiError;
+use hmac::{Hmac, Mac};
+use log::{error, info};
+use serde::{Deserialize, Serialize};
+use sha2::Sha256;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio_postgres::Client;
+
+/// Header carrying the HMAC-SHA256 signature of the request body.
+pub const SIGNATURE_HEADER: &str = "X-DPQ-Signature";
+/// Header carrying the name of the event being delivered.
+pub const EVENT_HEADER: &str = "X-DPQ-Event";
+
+/// How often the dispatcher looks for deliveries that are due.
+const DISPATCH_INTERVAL: Duration = Duration::from_secs(1);
+/// The longest the dispatcher waits between two attempts at the same delivery.
+const MAX_BACKOFF_SECONDS: i64 = 3600;
+/// The maximum number of deliveries claimed at once.
+const BATCH_SIZE: i64 = 100;
+
+/// CallbackEvent is the JSON body posted to a job's callback url.
+/// `delivery_id`: Unique id of the delivery, the same across retries so receivers can deduplicate.
+/// `event`: What happened to the job: completed, failed, dead_lettered or cancelled.
+/// `job_id`: The job the event is about.
+/// `status`: The state of the job when the event was delivered.
+/// `attempts`: The number of failed processing attempts of the job.
+/// `occurred_at`: Unix time at which the event was recorded.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct CallbackEvent {
+    pub delivery_id: i64,
+    pub event: String,
+    pub job_id: i64,
+    pub status: String,
+    pub attempts: i32,
+    pub occurred_at: i64,
+}
+
+/// Records an event for every given job that has a callback url. The event is delivered later by
+/// the `CallbackDispatcher`, so recording it never blocks the job lifecycle on the receiver.
+///
+/// # Arguments
+/// `client`: The database client.
+/// `job_ids`: The jobs the event happened to.
+/// `event`: The name of the event.
+///
+/// # Returns
+/// A Result object that is either Ok(u64) holding the number of deliveries recorded, or
+/// Err(ApiError)
+pub async fn record_event(client: &Client, job_ids: &[i64], event: &str) -> Result<u64, ApiError> {
+    client
+        .execute(
+            "INSERT INTO callback_deliveries (job_id, event, url, status, attempts)
+             SELECT job_id, $2, callback_url, 'pending', 0 FROM jobs
+             WHERE job_id = ANY($1) AND callback_url IS NOT NULL AND callback_url <> ''",
+            &[&job_ids, &event],
+        )
+        .await
+        .map_err(|_| {
+            error!(target:"error_logger","Error: Failed to record {} callback",event);
+            ApiError::DatabaseError("Error recording callback".to_string())
+        })
+}
+
+/// The delay before the next attempt at a delivery, doubling with every failed attempt.
+///
+/// # Arguments
+/// `attempts`: The number of attempts made so far.
+///
+/// # Returns
+/// The number of seconds to wait.
+pub fn backoff(attempts: i32) -> i64 {
+    let exponent: u32 = attempts.clamp(0, 31) as u32;
+    2_i64.pow(exponent).min(MAX_BACKOFF_SECONDS)
+}
+
+/// CallbackDispatcher delivers recorded events to producers, retrying failed deliveries with
+/// exponential backoff.
+/// `http`: The client used to post events.
+/// `secret`: The key used to sign every request body.
+/// `max_attempts`: The number of attempts after which a delivery is marked as failed.
+pub struct CallbackDispatcher {
+    http: reqwest::Client,
+    secret: Vec<u8>,
+    max_attempts: i32,
+}
+
+impl CallbackDispatcher {
+    pub fn new(secret: Vec<u8>, max_attempts: i32) -> Self {
+        CallbackDispatcher {
+            http: reqwest::Client::builder()
+                .timeout(Duration::from_secs(10))
+                .build()
+                .unwrap_or_default(),
+            secret,
+            max_attempts,
+        }
+    }
+
+    /// Creates a dispatcher signing with the `CALLBACK_SECRET` environment variable.
+    pub fn from_env() -> Self {
+        let secret: String = std::env::var("CALLBACK_SECRET").unwrap_or_default();
+        if secret.is_empty() {
+            error!(target:"error_logger","No CALLBACK_SECRET set, callbacks will be signed with an empty key");
+        }
+
+        let max_attempts: i32 = std::env::var("CALLBACK_MAX_ATTEMPTS")
+            .ok()
+            .and_then(|a| a.parse::<i32>().ok())
+            .unwrap_or(8);
+
+        CallbackDispatcher::new(secret.into_bytes(), max_attempts)
+    }
+
+    /// Signs a request body with HMAC-SHA256.
+    ///
+    /// # Returns
+    /// The signature in the form `sha256=<hex digest>`.
+    pub fn sign(&self, body: &[u8]) -> String {
+        let mut mac =
+            Hmac::<Sha256>::new_from_slice(&self.secret).expect("HMAC accepts keys of any length");
+        mac.update(body);
+        format!("sha256={}", hex::encode(mac.finalize().into_bytes()))
+    }
+
+    /// Delivers deliveries that are due until the node shuts down.
+    pub async fn run(self, db: Arc<Client>) {
+        loop {
+            if let Err(e) = self.deliver_pending(&db).await {
+                error!(target:"error_logger","Callback delivery failed: {}",e);
+            }
+
+            tokio::time::sleep(DISPATCH_INTERVAL).await;
+        }
+    }
+
+    /// Claims the deliveries that are due and posts each of them once.
+    ///
+    /// # Arguments
+    /// `db`: The database client.
+    ///
+    /// # Returns
+    /// A Result object that is either Ok(usize) holding the number of deliveries attempted, or
+    /// Err(ApiError)
+    pub async fn deliver_pending(&self, db: &Client) -> Result<usize, ApiError> {
+        // Push the claimed deliveries back so other nodes skip them while they are in flight
+        let rows = db
+            .query(
+                "UPDATE callback_deliveries d SET next_attempt_at = now() + interval '1 minute'
+                 FROM jobs j
+                 WHERE j.job_id = d.job_id AND d.delivery_id IN (
+                     SELECT delivery_id FROM callback_deliveries
+                     WHERE status = 'pending' AND next_attempt_at <= now()
+                     ORDER BY next_attempt_at LIMIT $1
+                     FOR UPDATE SKIP LOCKED
+                 )
+                 RETURNING d.delivery_id, d.event, d.job_id, d.url, d.attempts, j.status,
+                     j.attempts, EXTRACT(EPOCH FROM d.created_at)::BIGINT",
+                &[&BATCH_SIZE],
+            )
+            .await
+            .map_err(|_| {
+                error!(target:"error_logger","Error: Failed to claim pending callbacks");
+                ApiError::DatabaseError("Error claiming callbacks".to_string())
+            })?;
+
+        for row in &rows {
+            let url: String = row.get(3);
+            let attempts: i32 = row.get(4);
+            let event: CallbackEvent = CallbackEvent {
+                delivery_id: row.get(0),
+                event: row.get(1),
+                job_id: row.get(2),
+                status: row.get(5),
+                attempts: row.get(6),
+                occurred_at: row.get(7),
+            };
+
+            let outcome: Result<u16, String> = self.post(&url, &event).await;
+            self.record_attempt(db, event.delivery_id, attempts + 1, outcome)
+                .await?;
+        }
+
+        Ok(rows.len())
+    }
+
+    /// Posts a signed event to a callback url.
+    ///
+    /// # Returns
+    /// A Result object that is either Ok(u16) holding the response status, or Err(String)
+    /// describing why the delivery failed
+    async fn post(&self, url: &str, event: &CallbackEvent) -> Result<u16, String> {
+        let body: Vec<u8> = serde_json::to_vec(event).map_err(|e| e.to_string())?;
+
+        let response = self
+            .http
+            .post(url)
+            .header(reqwest::header::CONTENT_TYPE, "application/json")
+            .header(SIGNATURE_HEADER, self.sign(&body))
+            .header(EVENT_HEADER, &event.event)
+            .body(body)
+            .send()
+            .await
+            .map_err(|e| e.to_string())?;
+
+        let status: u16 = response.status().as_u16();
+        if response.status().is_success() {
+            Ok(status)
+        } else {
+            Err(format!("Callback responded with status {}", status))
+        }
+    }
+
+    /// Records the outcome of an attempt, scheduling a retry if it failed.
+    async fn record_attempt(
+        &self,
+        client: &Client,
+        delivery_id: i64,
+        attempts: i32,
+        outcome: Result<u16, String>,
+    ) -> Result<(), ApiError> {
+        let result = match outcome {
+            Ok(code) => {
+                info!(target:"request_logger","Delivered callback {}",delivery_id);
+                client
+                    .execute(
+                        "UPDATE callback_deliveries SET status = 'delivered', attempts = $2,
+                             response_status = $3, last_error = NULL, delivered_at = now()
+                         WHERE delivery_id = $1",
+                        &[&delivery_id, &attempts, &(code as i32)],
+                    )
+                    .await
+            }
+            Err(reason) => {
+                error!(target:"error_logger","Callback {} failed on attempt {}: {}",delivery_id,attempts,reason);
+                let status: &str = if attempts >= self.max_attempts {
+                    "failed"
+                } else {
+                    "pending"
+                };
+                client
+                    .execute(
+                        "UPDATE callback_deliveries SET status = $2, attempts = $3, last_error = $4,
+                             next_attempt_at = now() + $5 * interval '1 second'
+                         WHERE delivery_id = $1",
+                        &[
+                            &delivery_id,
+                            &status,
+                            &attempts,
+                            &reason,
+                            &(backoff(attempts) as f64),
+                        ],
+                    )
+                    .await
+            }
+        };
+
+        result.map(|_| ()).map_err(|_| {
+            error!(target:"error_logger","Error: Failed to record attempt on callback {}",delivery_id);
+            ApiError::DatabaseError("Error updating callback".to_string())
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{backoff, CallbackDispatcher, MAX_BACKOFF_SECONDS};
+
+    #[test]
+    fn test_backoff() {
+        assert_eq!(backoff(0), 1);
+        assert_eq!(backoff(1), 2);
+        assert_eq!(backoff(5), 32);
+        assert_eq!(backoff(12), MAX_BACKOFF_SECONDS);
+        assert_eq!(backoff(i32::MAX), MAX_BACKOFF_SECONDS);
+    }
+
+    #[test]
+    fn test_sign() {
+        // RFC 4231 test case 2
+        let dispatcher: CallbackDispatcher = CallbackDispatcher::new(b"Jefe".to_vec(), 3);
+        assert_eq!(
+            dispatcher.sign(b"what do ya want for nothing?"),
+            "sha256=5bdcc146bf60754e6a042426089575c75a003f089d2739839dec58b964ec3843"
+        );
+    }
+}
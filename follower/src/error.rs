@@ -14,6 +14,14 @@ pub enum ApiError {
     InternalServerError(String),
     /// Empty heap error occurs when the heap is empty
     EmptyHeapError,
+    /// Not found error occurs when the requested job does not exist or is in the wrong state.
+    NotFoundError(String),
+    /// Queue full error occurs when a job is added to a queue that has reached its maximum size.
+    QueueFullError(String),
+    /// Quota exceeded error occurs when a tenant exceeds one of its configured limits.
+    QuotaExceededError(String),
+    /// Bad request error occurs when the request holds a malformed or invalid value.
+    BadRequestError(String),
 }
 
 impl fmt::Display for ApiError {
@@ -22,6 +30,10 @@ impl fmt::Display for ApiError {
             ApiError::DatabaseError(s) => write!(f, "Database Error: {}", s),
             ApiError::InternalServerError(s) => write!(f, "Internal Server Error: {}", s),
             ApiError::EmptyHeapError => write!(f, "Empty Heap Error"),
+            ApiError::NotFoundError(s) => write!(f, "Not Found Error: {}", s),
+            ApiError::QueueFullError(s) => write!(f, "Queue Full Error: {}", s),
+            ApiError::QuotaExceededError(s) => write!(f, "Quota Exceeded Error: {}", s),
+            ApiError::BadRequestError(s) => write!(f, "Bad Request Error: {}", s),
         }
     }
 }
@@ -35,6 +47,10 @@ impl<'r> Responder<'r, 'static> for ApiError {
             ApiError::DatabaseError(_) => Status::InternalServerError,
             ApiError::InternalServerError(_) => Status::InternalServerError,
             ApiError::EmptyHeapError => Status::InternalServerError,
+            ApiError::NotFoundError(_) => Status::NotFound,
+            ApiError::QueueFullError(_) => Status::ServiceUnavailable,
+            ApiError::QuotaExceededError(_) => Status::TooManyRequests,
+            ApiError::BadRequestError(_) => Status::BadRequest,
         };
 
         Response::build()
@@ -44,3 +60,18 @@ impl<'r> Responder<'r, 'static> for ApiError {
             .ok()
     }
 }
+
+impl From<ApiError> for tonic::Status {
+    fn from(error: ApiError) -> Self {
+        let message = error.to_string();
+        match error {
+            ApiError::DatabaseError(_) => tonic::Status::internal(message),
+            ApiError::InternalServerError(_) => tonic::Status::internal(message),
+            ApiError::EmptyHeapError => tonic::Status::internal(message),
+            ApiError::NotFoundError(_) => tonic::Status::not_found(message),
+            ApiError::QueueFullError(_) => tonic::Status::unavailable(message),
+            ApiError::QuotaExceededError(_) => tonic::Status::resource_exhausted(message),
+            ApiError::BadRequestError(_) => tonic::Status::invalid_argument(message),
+        }
+    }
+}
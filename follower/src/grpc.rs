@@ -1,13 +1,27 @@
+use crate::error::ApiError;
+use crate::job_management::long_polling_service_server::LongPollingService;
 use crate::job_management::paxos_service_server::PaxosService;
-use crate::job_management::{Job, PaxosAccept, PaxosAck, PaxosPrepare, PaxosPromise};
-use crate::min_heap::MinHeap;
+use crate::job_management::{
+    AckRequest, AckResponse, HeartbeatRequest, HeartbeatResponse, Job, JobProgress, NackRequest,
+    PaxosAccept, PaxosAck, PaxosCancel, PaxosPrepare, PaxosPromise, PollJobRequest,
+    PollJobResponse,
+};
+use crate::jobs::{self, Lease, LeasedJob, NodeId, QueuedJob};
+use crate::leader::Leader;
+use crate::queues::QueueSet;
 use log::{error, info};
 use std::str::FromStr;
 use std::sync::Arc;
+use std::time::Duration;
 use tokio::sync::Mutex;
+use tokio::time::Instant;
+use tokio_postgres::Client;
 use tonic::{Request, Response, Status};
 use uuid::Uuid;
 
+/// How long a long poll waits between checks of the local queues
+const POLL_INTERVAL: Duration = Duration::from_millis(100);
+
 /// The current Paxos state
 #[derive(Debug)]
 pub struct PaxosState {
@@ -17,16 +31,26 @@ pub struct PaxosState {
     pub accepted_proposal: i32,
     // The accepted job
     pub accepted_value: Option<Job>,
-    // Local min heap
-    pub queue: MinHeap,
+    // Local named queues, each with its own min heap
+    pub queues: QueueSet,
     // Lamport timestamp
     pub lamport_timestamp: u64,
+    // The id of this node, which the enqueue times of its jobs are recorded under
+    pub node_id: NodeId,
 }
 
 impl PaxosState {
-    pub fn new() -> Result<Self, String> {
+    /// Creates the state of this node and recovers the jobs it had queued before a restart, so it
+    /// serves them again before it accepts anything new.
+    ///
+    /// # Arguments
+    /// `client`: The database client.
+    ///
+    /// # Returns
+    /// A Result object that is either Ok(PaxosState) or Err(String)
+    pub async fn new(client: &Client) -> Result<Self, String> {
         log4rs::init_file("log4rs.yaml", Default::default()).unwrap();
-        let _: u64 = match std::env::args().collect::<Vec<String>>().get(1) {
+        let node_id: u64 = match std::env::args().collect::<Vec<String>>().get(1) {
             Some(id) => match id.parse::<u64>() {
                 Ok(i) => i,
                 Err(_) => {
@@ -40,13 +64,50 @@ impl PaxosState {
             }
         };
 
-        Ok(PaxosState {
+        let queues: QueueSet = QueueSet::from_files("queues.json", "tenants.json")?;
+
+        let mut state: PaxosState = PaxosState::with_queues(NodeId(node_id as i64), queues);
+        state.recover(client).await.map_err(|e| {
+            error!(target:"error_logger","Failed to recover the queues of node {}",node_id);
+            e.to_string()
+        })?;
+
+        Ok(state)
+    }
+
+    /// Creates the state of an acceptor that has not promised or accepted anything yet.
+    pub fn with_queues(node_id: NodeId, queues: QueueSet) -> Self {
+        PaxosState {
             promised_proposal: 0,
             accepted_proposal: 0,
             accepted_value: None,
-            queue: MinHeap::new(0.5),
+            queues,
             lamport_timestamp: 0,
-        })
+            node_id,
+        }
+    }
+
+    /// Rebuilds the local queues from the jobs table, so a restart does not lose the jobs that
+    /// were queued on this node, and moves the clock past every recovered enqueue time. Called by
+    /// `new` on startup.
+    ///
+    /// # Arguments
+    /// `client`: The database client.
+    ///
+    /// # Returns
+    /// A Result object that is either Ok(usize) holding the number of jobs queued after the
+    /// recovery, or Err(ApiError)
+    pub async fn recover(&mut self, client: &Client) -> Result<usize, ApiError> {
+        self.lamport_timestamp = jobs::recover_queues(
+            client,
+            self.node_id,
+            &mut self.queues,
+            self.lamport_timestamp,
+        )
+        .await?;
+
+        info!(target:"request_logger","Recovered {} queued jobs",self.queues.len());
+        Ok(self.queues.len())
     }
 
     pub fn increment_time(&mut self) -> u64 {
@@ -54,11 +115,39 @@ impl PaxosState {
         self.lamport_timestamp += 1;
         temp
     }
+
+    /// Queues the jobs of an accepted proposal, which is recorded as the accepted value.
+    ///
+    /// # Arguments
+    /// `jobs`: The jobs proposed by the leader, along with their parsed ids.
+    ///
+    /// # Returns
+    /// The enqueue time given to every job, to be recorded so it survives a restart.
+    pub fn accept_jobs(&mut self, jobs: &[(i64, Job)]) -> Vec<(i64, u64)> {
+        let time = self.increment_time();
+
+        let mut enqueue_times: Vec<(i64, u64)> = Vec::with_capacity(jobs.len());
+        for (job_id, job) in jobs {
+            let queued: QueuedJob = QueuedJob {
+                job_id: *job_id,
+                priority: job.priority,
+                queue: job.queue.clone(),
+                tenant_id: job.tenant_id.clone(),
+                ordering_key: job.ordering_key.clone(),
+                deadline: (job.deadline > 0).then_some(job.deadline),
+            };
+            enqueue_times.push((*job_id, self.queues.insert_job(&queued, time)));
+        }
+        self.accepted_value = jobs.last().map(|(_, job)| job.clone());
+
+        enqueue_times
+    }
 }
 
 #[derive(Debug)]
 pub struct LocalPaxosService {
     pub state: Arc<Mutex<PaxosState>>,
+    pub db: Arc<Client>,
 }
 
 #[tonic::async_trait]
@@ -95,32 +184,60 @@ impl PaxosService for LocalPaxosService {
     /// # Return
     /// A Result object that is either an Ok(tonic::Response) or Err(tonic::Status)
     async fn accept(&self, request: Request<PaxosAccept>) -> Result<Response<PaxosAck>, Status> {
+        let client: &Client = &self.db;
         let mut state = self.state.lock().await;
         let propose = request.into_inner();
 
-        let job = match propose.proposed_job {
-            Some(job) => job,
-            None => {
-                error!(target: "error_logger","Failed Accept: no job provided in accept message");
-                return Err(Status::internal("No job provided in accept message"));
-            }
-        };
+        // A batch is accepted as a single entry, every job of it is queued or none is
+        let proposed: Vec<Job> = propose
+            .proposed_job
+            .into_iter()
+            .chain(propose.proposed_jobs)
+            .collect();
 
-        let job_id: Uuid = match Uuid::from_str(job.job_id.as_str()) {
-            Ok(id) => id,
-            Err(_) => {
-                error!(target: "error_logger","Failed Accept: no job provided in accept message");
-                return Err(Status::internal("No job provided in accept message"));
-            }
-        };
+        if proposed.is_empty() {
+            error!(target: "error_logger","Failed Accept: no job provided in accept message");
+            return Err(Status::internal("No job provided in accept message"));
+        }
 
-        let time = state.increment_time();
+        let mut jobs: Vec<(i64, Job)> = Vec::with_capacity(proposed.len());
+        for job in proposed {
+            match job.job_id.parse::<i64>() {
+                Ok(id) => jobs.push((id, job)),
+                Err(_) => {
+                    error!(target: "error_logger","Failed Accept: invalid job id {} provided in accept message",job.job_id);
+                    return Err(Status::internal(
+                        "Invalid job id provided in accept message",
+                    ));
+                }
+            }
+        }
 
         info!(target:"error_logger","Paxos Accept message recieved with proposal number {}",propose.proposal_number);
 
+        // Replicated jobs are held to the same limit as jobs enqueued on this node directly
+        if let Some(queue) = state
+            .queues
+            .overflowing(jobs.iter().map(|(_, job)| job.queue.as_str()))
+        {
+            error!(target:"error_logger","Failed Accept: queue {} has no room for proposal {}",queue,propose.proposal_number);
+            return Err(ApiError::QueueFullError(queue).into());
+        }
+
         if propose.proposal_number > state.accepted_proposal {
-            state.accepted_value = Some(job.clone());
-            state.queue.insert(job.priority as u32, job_id, time);
+            let enqueue_times: Vec<(i64, u64)> = state.accept_jobs(&jobs);
+            let node_id: NodeId = state.node_id;
+            drop(state);
+
+            // The jobs are queued either way, only their aging after a restart depends on the
+            // recorded enqueue times
+            if jobs::record_enqueue_times(client, node_id, &enqueue_times)
+                .await
+                .is_err()
+            {
+                error!(target:"error_logger","Failed to record enqueue times for proposal {}",propose.proposal_number);
+            }
+
             Ok(Response::new(PaxosAck {
                 proposal_number: propose.proposal_number,
             }))
@@ -131,4 +248,355 @@ impl PaxosService for LocalPaxosService {
             ))
         }
     }
+
+    /// Recieves the Cancel message from the leader and removes the cancelled job from the local
+    /// queue so that it is never handed to a consumer.
+    ///
+    /// # Arguments
+    /// `request`: The Paxos Cancel message from the proposer.
+    ///
+    /// # Return
+    /// A Result object that is either an Ok(tonic::Response) or Err(tonic::Status)
+    async fn cancel(&self, request: Request<PaxosCancel>) -> Result<Response<PaxosAck>, Status> {
+        let mut state = self.state.lock().await;
+        let cancel = request.into_inner();
+
+        let job_id: i64 = match cancel.job_id.parse::<i64>() {
+            Ok(id) => id,
+            Err(_) => {
+                error!(target: "error_logger","Failed Cancel: invalid job id {} provided in cancel message",cancel.job_id);
+                return Err(Status::invalid_argument(
+                    "Invalid job id provided in cancel message",
+                ));
+            }
+        };
+
+        info!(target:"request_logger","Paxos Cancel message recieved with proposal number {} for job {}",cancel.proposal_number,job_id);
+
+        if cancel.proposal_number < state.accepted_proposal {
+            error!(target:"error_logger","Failed Paxos Cancel: proposal number was less than promised");
+            return Err(Status::failed_precondition(
+                "Proposal number is less than promised.",
+            ));
+        }
+
+        // The job may live on another replica, in which case there is nothing to remove locally
+        state.queues.remove(job_id);
+
+        Ok(Response::new(PaxosAck {
+            proposal_number: cancel.proposal_number,
+        }))
+    }
+}
+
+/// Serves consumers: hands out jobs from the local queues under a lease and keeps leases alive
+/// while consumers report progress.
+#[derive(Debug)]
+pub struct LocalLongPollingService {
+    pub state: Arc<Mutex<PaxosState>>,
+    pub db: Arc<Client>,
+    pub leader: Leader,
+}
+
+#[tonic::async_trait]
+impl LongPollingService for LocalLongPollingService {
+    /// Waits up to the requested timeout for a job on one of the requested queues and leases it
+    /// to the consumer.
+    ///
+    /// # Arguments
+    /// `request`: The poll request from the consumer.
+    ///
+    /// # Return
+    /// A Result object that is either an Ok(tonic::Response) or Err(tonic::Status)
+    async fn poll(
+        &self,
+        request: Request<PollJobRequest>,
+    ) -> Result<Response<PollJobResponse>, Status> {
+        let poll = request.into_inner();
+        let deadline: Instant =
+            Instant::now() + Duration::from_secs(poll.timeout_seconds.max(0) as u64);
+
+        info!(target:"request_logger","Poll recieved from consumer {}",poll.consumer_id);
+
+        loop {
+            {
+                let client: &Client = &self.db;
+                let (time, node_id): (u64, NodeId) = {
+                    let mut state = self.state.lock().await;
+                    (state.increment_time(), state.node_id)
+                };
+
+                jobs::expire_leases(client, node_id, &self.state, queues_of, time).await?;
+
+                let leased: Option<(LeasedJob, i32)> = jobs::lease_next(
+                    &self.state,
+                    queues_of,
+                    &poll.queues,
+                    time,
+                    |job_id, timeout| jobs::lease_job(client, job_id, timeout),
+                )
+                .await?;
+
+                if let Some((job, visibility_timeout)) = leased {
+                    return Ok(Response::new(PollJobResponse {
+                        success: true,
+                        job: Some(Job {
+                            job_id: job.job_id.to_string(),
+                            priority: job.priority,
+                            payload: job.payload,
+                            queue: job.queue,
+                            tenant_id: job.tenant_id,
+                            ordering_key: job.ordering_key,
+                            deadline: job.deadline.unwrap_or(0),
+                        }),
+                        lease_id: job.lease_id.to_string(),
+                        visibility_timeout_seconds: visibility_timeout,
+                    }));
+                }
+            }
+
+            if Instant::now() >= deadline {
+                return Ok(Response::new(PollJobResponse {
+                    success: false,
+                    job: None,
+                    lease_id: String::new(),
+                    visibility_timeout_seconds: 0,
+                }));
+            }
+
+            tokio::time::sleep(POLL_INTERVAL).await;
+        }
+    }
+
+    /// Renews the consumer's lease on a job and records the progress it reported.
+    ///
+    /// # Arguments
+    /// `request`: The heartbeat from the consumer holding the job.
+    ///
+    /// # Return
+    /// A Result object that is either an Ok(tonic::Response) or Err(tonic::Status)
+    async fn heartbeat(
+        &self,
+        request: Request<HeartbeatRequest>,
+    ) -> Result<Response<HeartbeatResponse>, Status> {
+        let heartbeat = request.into_inner();
+
+        let (job_id, lease_id): (i64, Uuid) =
+            parse_lease(&heartbeat.job_id, &heartbeat.lease_id)
+                .ok_or_else(|| Status::invalid_argument("Invalid job id or lease id"))?;
+
+        let progress: JobProgress = heartbeat.progress.unwrap_or_default();
+        if !(0..=100).contains(&progress.percent) {
+            return Err(Status::invalid_argument(
+                "Progress must be between 0 and 100 percent",
+            ));
+        }
+
+        let client: &Client = &self.db;
+        let lease: Option<Lease> = jobs::heartbeat(
+            client,
+            job_id,
+            lease_id,
+            progress.percent,
+            &progress.message,
+        )
+        .await?;
+
+        match lease {
+            Some(lease) => Ok(Response::new(HeartbeatResponse {
+                success: true,
+                cancelled: lease.cancelled,
+                visibility_timeout_seconds: lease.visibility_timeout,
+            })),
+            None => {
+                error!(target:"error_logger","Failed Heartbeat: lease {} on job {} has expired",lease_id,job_id);
+                Ok(Response::new(HeartbeatResponse {
+                    success: false,
+                    cancelled: false,
+                    visibility_timeout_seconds: 0,
+                }))
+            }
+        }
+    }
+
+    /// Completes a job on behalf of the consumer holding its lease, storing the result it
+    /// attached and releasing any dependents.
+    ///
+    /// # Arguments
+    /// `request`: The ack from the consumer holding the job.
+    ///
+    /// # Return
+    /// A Result object that is either an Ok(tonic::Response) or Err(tonic::Status)
+    async fn ack(&self, request: Request<AckRequest>) -> Result<Response<AckResponse>, Status> {
+        let ack = request.into_inner();
+        let (job_id, lease_id): (i64, Uuid) = parse_lease(&ack.job_id, &ack.lease_id)
+            .ok_or_else(|| Status::invalid_argument("Invalid job id or lease id"))?;
+
+        let client: &Client = &self.db;
+        let released: Vec<QueuedJob> =
+            jobs::complete_job(client, job_id, Some(lease_id), ack.result.as_ref()).await?;
+        let count: usize = released.len();
+
+        // Released dependents come back from the leader like any other replicated job
+        let unreplicated: Vec<QueuedJob> = jobs::replicate_released(&self.leader, released).await;
+
+        let (time, node_id): (u64, NodeId) = {
+            let mut state = self.state.lock().await;
+            (state.increment_time(), state.node_id)
+        };
+        jobs::release_dependents(
+            client,
+            node_id,
+            &self.state,
+            queues_of,
+            job_id,
+            &unreplicated,
+            time,
+        )
+        .await?;
+
+        Ok(Response::new(AckResponse {
+            success: true,
+            message: format!(
+                "Job with job_id={} completed, {} dependent jobs released",
+                job_id, count
+            ),
+        }))
+    }
+
+    /// Reports that the consumer holding a job failed to process it. The job is retried or dead
+    /// lettered according to its queue's retry policy.
+    ///
+    /// # Arguments
+    /// `request`: The nack from the consumer holding the job.
+    ///
+    /// # Return
+    /// A Result object that is either an Ok(tonic::Response) or Err(tonic::Status)
+    async fn nack(&self, request: Request<NackRequest>) -> Result<Response<AckResponse>, Status> {
+        let nack = request.into_inner();
+        let (job_id, lease_id): (i64, Uuid) = parse_lease(&nack.job_id, &nack.lease_id)
+            .ok_or_else(|| Status::invalid_argument("Invalid job id or lease id"))?;
+
+        let client: &Client = &self.db;
+        let (attempts, job): (i32, QueuedJob) =
+            jobs::record_failure(client, job_id, Some(lease_id)).await?;
+
+        let (time, node_id): (u64, NodeId) = {
+            let mut state = self.state.lock().await;
+            (state.increment_time(), state.node_id)
+        };
+
+        let message: String = match jobs::retry_or_dead_letter(
+            client,
+            node_id,
+            &self.state,
+            queues_of,
+            attempts,
+            &job,
+            time,
+        )
+        .await?
+        {
+            None => format!(
+                "Job with job_id={} requeued after attempt {}",
+                job_id, attempts
+            ),
+            Some(cascaded) => format!(
+                "Job with job_id={} dead lettered, {} dependent jobs dead lettered",
+                job_id, cascaded
+            ),
+        };
+
+        Ok(Response::new(AckResponse {
+            success: true,
+            message,
+        }))
+    }
+
+    async fn release(
+        &self,
+        request: Request<NackRequest>,
+    ) -> Result<Response<AckResponse>, Status> {
+        let release = request.into_inner();
+        let (job_id, lease_id): (i64, Uuid) = parse_lease(&release.job_id, &release.lease_id)
+            .ok_or_else(|| Status::invalid_argument("Invalid job id or lease id"))?;
+
+        let client: &Client = &self.db;
+        let job: QueuedJob = jobs::release_job(client, job_id, lease_id).await?;
+
+        let (node_id, enqueue_time): (NodeId, u64) = {
+            let mut state = self.state.lock().await;
+            let time = state.increment_time();
+            (state.node_id, state.queues.requeue(&job, time))
+        };
+        jobs::record_enqueue_times(client, node_id, &[(job_id, enqueue_time)]).await?;
+
+        Ok(Response::new(AckResponse {
+            success: true,
+            message: format!("Job with job_id={} released", job_id),
+        }))
+    }
+}
+
+/// Picks the local queues out of the Paxos state, so the job helpers can take them under the
+/// state lock only for as long as they touch them.
+fn queues_of(state: &mut PaxosState) -> &mut QueueSet {
+    &mut state.queues
+}
+
+/// Parses the job id and lease id sent by a consumer.
+///
+/// # Returns
+/// The job id and lease id, or None if either is not valid.
+fn parse_lease(job_id: &str, lease_id: &str) -> Option<(i64, Uuid)> {
+    match (job_id.parse::<i64>(), Uuid::from_str(lease_id)) {
+        (Ok(job_id), Ok(lease_id)) => Some((job_id, lease_id)),
+        _ => {
+            error!(target: "error_logger","Invalid job id {} or lease id {} provided by consumer",job_id,lease_id);
+            None
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::PaxosState;
+    use crate::job_management::{Job, PaxosCancel};
+    use crate::jobs::NodeId;
+    use crate::queues::QueueSet;
+    use std::collections::HashMap;
+
+    #[test]
+    fn test_leader_cancel_removes_replicated_job() {
+        let mut state: PaxosState =
+            PaxosState::with_queues(NodeId(1), QueueSet::new(HashMap::new(), HashMap::new()));
+
+        // Jobs are proposed and cancelled with the ids the leader gives them
+        let jobs: Vec<(i64, Job)> = [41i64, 42]
+            .iter()
+            .map(|id| {
+                let job: Job = Job {
+                    job_id: id.to_string(),
+                    priority: 1,
+                    ..Job::default()
+                };
+                (job.job_id.parse::<i64>().unwrap(), job)
+            })
+            .collect();
+        state.accept_jobs(&jobs);
+        assert_eq!(state.queues.len(), 2);
+
+        let cancel: PaxosCancel = PaxosCancel {
+            proposal_number: 1,
+            job_id: 42.to_string(),
+        };
+        let job_id: i64 = cancel.job_id.parse::<i64>().unwrap();
+        assert_eq!(job_id, jobs[1].0);
+        assert_eq!(state.queues.remove(job_id).unwrap().job_id, job_id);
+        assert_eq!(state.queues.len(), 1);
+        assert_eq!(
+            state.queues.get_top_from(&[], 2).unwrap().1.job_id,
+            jobs[0].0
+        );
+    }
 }
@@ -0,0 +1,801 @@
+use crate::callbacks;
+use crate::error::ApiError;
+use crate::job_management::JobResult;
+use crate::leader::Leader;
+use crate::queues::QueueSet;
+use log::error;
+use std::future::Future;
+use tokio::sync::Mutex;
+use tokio_postgres::Client;
+use uuid::Uuid;
+
+/// NodeId is the id of this node, given on the command line. The enqueue times of the jobs a node
+/// queues are recorded under its id, so each node recovers the jobs it held itself.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct NodeId(pub i64);
+
+/// A job that has been placed back in the queued state and must be inserted into the local heap
+/// of its queue.
+/// `deadline`: Unix time in milliseconds by which the job should be completed, if it has one.
+#[derive(Debug)]
+pub struct QueuedJob {
+    pub job_id: i64,
+    pub priority: i32,
+    pub queue: String,
+    pub tenant_id: String,
+    pub ordering_key: String,
+    pub deadline: Option<i64>,
+}
+
+/// A job that has been leased to a consumer.
+/// `lease_id`: Identifies the lease, a consumer must present it when sending heartbeats.
+/// `deadline`: Unix time in milliseconds by which the job should be completed, if it has one.
+#[derive(Debug)]
+pub struct LeasedJob {
+    pub job_id: i64,
+    pub priority: i32,
+    pub payload: Vec<u8>,
+    pub queue: String,
+    pub tenant_id: String,
+    pub ordering_key: String,
+    pub lease_id: Uuid,
+    pub deadline: Option<i64>,
+}
+
+/// The state of a lease after a heartbeat.
+/// `cancelled`: True if the job was cancelled while the consumer held it.
+/// `visibility_timeout`: The number of seconds until the renewed lease expires.
+#[derive(Debug)]
+pub struct Lease {
+    pub cancelled: bool,
+    pub visibility_timeout: i32,
+}
+
+/// Leases a job taken off the local queue to a consumer. The job is requeued if the lease is not
+/// renewed with a heartbeat within the visibility timeout.
+/// A job is not leased while an earlier job with its ordering key is queued or leased, which may
+/// be the case when the earlier job was handed out by another follower.
+///
+/// # Arguments
+/// `client`: The database client.
+/// `job_id`: The job taken off the queue.
+/// `visibility_timeout`: The number of seconds the lease lasts.
+///
+/// # Returns
+/// A Result object that is either Ok(LeasedJob) or Err(ApiError)
+pub async fn lease_job(
+    client: &Client,
+    job_id: i64,
+    visibility_timeout: i32,
+) -> Result<LeasedJob, ApiError> {
+    let lease_id: Uuid = Uuid::new_v4();
+
+    let row = client
+        .query_opt(
+            "UPDATE jobs SET status = 'leased', lease_id = $2, visibility_timeout = $3,
+                 lease_expires_at = now() + $3 * interval '1 second'
+             WHERE job_id = $1 AND status = 'queued' AND NOT EXISTS (
+                 SELECT 1 FROM jobs earlier
+                 WHERE jobs.ordering_key <> '' AND earlier.ordering_key = jobs.ordering_key
+                     AND earlier.queue = jobs.queue AND earlier.job_id <> jobs.job_id
+                     AND (earlier.status = 'leased'
+                         OR (earlier.status = 'queued' AND earlier.created_at < jobs.created_at))
+             )
+             RETURNING job_id, priority, payload, queue, tenant_id, ordering_key,
+                 (EXTRACT(EPOCH FROM deadline) * 1000)::BIGINT",
+            &[&job_id, &lease_id, &visibility_timeout],
+        )
+        .await
+        .map_err(|_| {
+            error!(target:"error_logger","Error: Failed to lease job {}",job_id);
+            ApiError::DatabaseError("Error database UPDATE query failed.".to_string())
+        })?
+        .ok_or_else(|| ApiError::NotFoundError(format!("No queued job with job_id={}", job_id)))?;
+
+    Ok(LeasedJob {
+        job_id: row.get(0),
+        priority: row.get(1),
+        payload: row.get(2),
+        queue: row.get(3),
+        tenant_id: row.get(4),
+        ordering_key: row.get(5),
+        lease_id,
+        deadline: row.get(6),
+    })
+}
+
+/// Takes the most urgent job off the given local queues and leases it to a consumer.
+/// Every follower queues every job the leader replicates, so the job may already have been
+/// leased through another follower, or be held back behind a job that was. Such a job is dropped
+/// from the local queues, which releases the next job on its ordering key, and the next job is
+/// tried instead. The follower holding the earlier job hands the dropped one out once the earlier
+/// job is finished.
+/// The lock on the local queues is only held while a job is taken off them, never across the
+/// lease query, so other requests are not held up by the database.
+///
+/// # Arguments
+/// `queues`: The lock guarding the local queues.
+/// `queues_of`: Picks the local queues out of the state guarded by the lock.
+/// `names`: The queues to take the job from, every queue if empty.
+/// `timestamp`: The current logical time.
+/// `lease`: Leases a job in the database for a visibility timeout, `lease_job` outside of tests.
+///
+/// # Returns
+/// A Result object that is either Ok(Option<(LeasedJob, i32)>) holding the job and the
+/// visibility timeout of its queue, None if there is no job left to lease, or Err(ApiError)
+pub async fn lease_next<Q, F, Fut>(
+    queues: &Mutex<Q>,
+    queues_of: fn(&mut Q) -> &mut QueueSet,
+    names: &[String],
+    timestamp: u64,
+    mut lease: F,
+) -> Result<Option<(LeasedJob, i32)>, ApiError>
+where
+    F: FnMut(i64, i32) -> Fut,
+    Fut: Future<Output = Result<LeasedJob, ApiError>>,
+{
+    loop {
+        let (job_id, visibility_timeout): (i64, i32) = {
+            let mut guard = queues.lock().await;
+            let local: &mut QueueSet = queues_of(&mut guard);
+            match local.get_top_from(names, timestamp) {
+                Some((queue, node)) => (
+                    node.job_id,
+                    local.queue_mut(&queue).config.visibility_timeout,
+                ),
+                None => return Ok(None),
+            }
+        };
+
+        match lease(job_id, visibility_timeout).await {
+            Ok(job) => return Ok(Some((job, visibility_timeout))),
+            Err(ApiError::NotFoundError(_)) => queues_of(&mut *queues.lock().await).finish(job_id),
+            Err(e) => return Err(e),
+        }
+    }
+}
+
+/// Renews a consumer's lease on a job and records the progress it reported.
+///
+/// # Arguments
+/// `client`: The database client.
+/// `job_id`: The job being processed.
+/// `lease_id`: The lease the consumer was given when the job was dequeued.
+/// `percent`: How much of the job has been processed, from 0 to 100.
+/// `message`: A description of what the consumer is currently doing.
+///
+/// # Returns
+/// A Result object that is either Ok(Option<Lease>), None if the lease has expired or does not
+/// belong to the consumer, or Err(ApiError)
+pub async fn heartbeat(
+    client: &Client,
+    job_id: i64,
+    lease_id: Uuid,
+    percent: i32,
+    message: &str,
+) -> Result<Option<Lease>, ApiError> {
+    // A cancelled job keeps its lease so the consumer can be told to stop
+    let row = client
+        .query_opt(
+            "UPDATE jobs SET lease_expires_at = now() + visibility_timeout * interval '1 second',
+                 progress_percent = $3, progress_message = $4
+             WHERE job_id = $1 AND lease_id = $2
+               AND (status = 'cancelled' OR (status = 'leased' AND lease_expires_at > now()))
+             RETURNING status, visibility_timeout",
+            &[&job_id, &lease_id, &percent, &message],
+        )
+        .await
+        .map_err(|_| {
+            error!(target:"error_logger","Error: Failed to renew lease on job {}",job_id);
+            ApiError::DatabaseError("Error updating database".to_string())
+        })?;
+
+    Ok(row.map(|row| {
+        let status: String = row.get(0);
+        Lease {
+            cancelled: status == "cancelled",
+            visibility_timeout: row.get(1),
+        }
+    }))
+}
+
+/// Marks a job as completed, stores its result and releases any dependent jobs whose parents have
+/// now all completed.
+///
+/// # Arguments
+/// `client`: The database client.
+/// `job_id`: The job that was acked by a consumer.
+/// `lease_id`: The lease of the consumer acking the job, any lease is accepted if not provided.
+/// `result`: The output of the job, if it has any.
+///
+/// # Returns
+/// A Result object that is either Ok(Vec<QueuedJob>) holding every released job, or Err(ApiError)
+pub async fn complete_job(
+    client: &Client,
+    job_id: i64,
+    lease_id: Option<Uuid>,
+    result: Option<&JobResult>,
+) -> Result<Vec<QueuedJob>, ApiError> {
+    let data: Option<&[u8]> = result.map(|r| r.data.as_slice());
+    let content_type: Option<&str> = result.map(|r| r.content_type.as_str());
+
+    let rows = client
+        .execute(
+            "UPDATE jobs SET status = 'completed', lease_id = NULL, result = $3,
+                 result_content_type = $4, finished_at = now(), sla_met = now() <= deadline
+             WHERE job_id = $1 AND status = 'leased' AND ($2::UUID IS NULL OR lease_id = $2)",
+            &[&job_id, &lease_id, &data, &content_type],
+        )
+        .await
+        .map_err(|_| {
+            error!(target:"error_logger","Error: Failed to run UPDATE query on job {}",job_id);
+            ApiError::DatabaseError("Error updating database".to_string())
+        })?;
+
+    if rows == 0 {
+        return Err(ApiError::NotFoundError(format!(
+            "No leased job with job_id={}",
+            job_id
+        )));
+    }
+
+    callbacks::record_event(client, &[job_id], "completed").await?;
+
+    // A child is released once none of its parents are still outstanding
+    let released = client
+        .query(
+            "UPDATE jobs SET status = 'queued'
+             WHERE status = 'waiting'
+               AND job_id IN (SELECT child_id FROM job_dependencies WHERE parent_id = $1)
+               AND NOT EXISTS (
+                   SELECT 1 FROM job_dependencies d JOIN jobs p ON p.job_id = d.parent_id
+                   WHERE d.child_id = jobs.job_id AND p.status <> 'completed'
+               )
+             RETURNING job_id, priority, queue, tenant_id, ordering_key,
+                 (EXTRACT(EPOCH FROM deadline) * 1000)::BIGINT",
+            &[&job_id],
+        )
+        .await
+        .map_err(|_| {
+            error!(target:"error_logger","Error: Failed to release dependents of job {}",job_id);
+            ApiError::DatabaseError("Error releasing dependent jobs".to_string())
+        })?;
+
+    Ok(released
+        .iter()
+        .map(|row| QueuedJob {
+            job_id: row.get(0),
+            priority: row.get(1),
+            queue: row.get(2),
+            tenant_id: row.get(3),
+            ordering_key: row.get(4),
+            deadline: row.get(5),
+        })
+        .collect())
+}
+
+/// Has the leader replicate the dependents released by `complete_job`, the same way it
+/// replicates jobs enqueued with completed parents, so they are queued on every follower rather
+/// than on the follower the parent was acked on.
+///
+/// # Arguments
+/// `leader`: The connection to the leader.
+/// `released`: The dependents released by `complete_job`.
+///
+/// # Returns
+/// The dependents the leader could not replicate, which are left to this follower.
+pub async fn replicate_released(leader: &Leader, released: Vec<QueuedJob>) -> Vec<QueuedJob> {
+    if released.is_empty() {
+        return released;
+    }
+
+    let job_ids: Vec<i64> = released.iter().map(|job| job.job_id).collect();
+    match leader.release_jobs(&job_ids).await {
+        Ok(()) => Vec::new(),
+        Err(_) => {
+            error!(target:"error_logger","Queueing {} released jobs locally, the leader could not replicate them",released.len());
+            released
+        }
+    }
+}
+
+/// Marks an acked job as finished in the local queues and inserts the dependents the leader could
+/// not replicate, so they are not stranded in the queued state. Their enqueue times are recorded
+/// once the lock on the local queues is released.
+///
+/// # Arguments
+/// `client`: The database client.
+/// `node_id`: The id of this node.
+/// `queues`: The lock guarding the local queues.
+/// `queues_of`: Picks the local queues out of the state guarded by the lock.
+/// `job_id`: The job that was acked.
+/// `released`: The dependents returned by `replicate_released`.
+/// `timestamp`: The current logical time.
+///
+/// # Returns
+/// A Result object that is either Ok(()) or Err(ApiError)
+pub async fn release_dependents<Q>(
+    client: &Client,
+    node_id: NodeId,
+    queues: &Mutex<Q>,
+    queues_of: fn(&mut Q) -> &mut QueueSet,
+    job_id: i64,
+    released: &[QueuedJob],
+    timestamp: u64,
+) -> Result<(), ApiError> {
+    let enqueue_times: Vec<(i64, u64)> = {
+        let mut guard = queues.lock().await;
+        let local: &mut QueueSet = queues_of(&mut guard);
+        local.finish(job_id);
+
+        released
+            .iter()
+            .map(|child| (child.job_id, local.insert_job(child, timestamp)))
+            .collect()
+    };
+
+    record_enqueue_times(client, node_id, &enqueue_times).await
+}
+
+/// Records the enqueue time each job was given in the local queues of this node, so that its aging
+/// can be restored by `recover_queues` after a restart. Every node keeps its own enqueue times, as
+/// each gives a replicated job the time on its own clock.
+///
+/// # Arguments
+/// `client`: The database client.
+/// `node_id`: The id of this node.
+/// `enqueue_times`: The id of every job that was queued and the enqueue time it was given.
+///
+/// # Returns
+/// A Result object that is either Ok(()) or Err(ApiError)
+pub async fn record_enqueue_times(
+    client: &Client,
+    node_id: NodeId,
+    enqueue_times: &[(i64, u64)],
+) -> Result<(), ApiError> {
+    if enqueue_times.is_empty() {
+        return Ok(());
+    }
+
+    let job_ids: Vec<i64> = enqueue_times.iter().map(|(job_id, _)| *job_id).collect();
+    let times: Vec<i64> = enqueue_times.iter().map(|(_, time)| *time as i64).collect();
+
+    client
+        .execute(
+            "INSERT INTO job_enqueue_times (job_id, node_id, enqueue_time)
+             SELECT job_id, $1, enqueue_time
+             FROM UNNEST($2::BIGINT[], $3::BIGINT[]) AS queued(job_id, enqueue_time)
+             ON CONFLICT (job_id, node_id) DO UPDATE SET enqueue_time = EXCLUDED.enqueue_time",
+            &[&node_id.0, &job_ids, &times],
+        )
+        .await
+        .map_err(|_| {
+            error!(target:"error_logger","Error: Failed to record the enqueue time of {} jobs",job_ids.len());
+            ApiError::DatabaseError("Error updating database".to_string())
+        })?;
+
+    Ok(())
+}
+
+/// Rebuilds the local queues from the jobs table after a restart. Only the jobs this node had
+/// queued are recovered, each with the enqueue time it was given on this node before the restart,
+/// so its aging carries on where it left off. Leased jobs are not queued, they return through
+/// lease expiry, but they still hold their ordering keys so the jobs behind them keep waiting.
+///
+/// # Arguments
+/// `client`: The database client.
+/// `node_id`: The id of this node.
+/// `queues`: The local queues, expected to be empty.
+/// `timestamp`: The current logical time.
+///
+/// # Returns
+/// A Result object that is either Ok(u64) holding the logical time to resume the clock from,
+/// later than every recovered enqueue time, or Err(ApiError)
+pub async fn recover_queues(
+    client: &Client,
+    node_id: NodeId,
+    queues: &mut QueueSet,
+    timestamp: u64,
+) -> Result<u64, ApiError> {
+    // Jobs are replayed in the order they were created so each ordering key is rebuilt in FIFO
+    // order, a requeued job being older than any job waiting behind it
+    let rows = client
+        .query(
+            "SELECT jobs.job_id, priority, queue, tenant_id, ordering_key,
+                 (EXTRACT(EPOCH FROM deadline) * 1000)::BIGINT, status = 'leased', t.enqueue_time
+             FROM jobs JOIN job_enqueue_times t ON t.job_id = jobs.job_id AND t.node_id = $1
+             WHERE status IN ('queued', 'leased')
+             ORDER BY created_at, jobs.job_id",
+            &[&node_id.0],
+        )
+        .await
+        .map_err(|_| {
+            error!(target:"error_logger","Error: Failed to load queued jobs for recovery");
+            ApiError::DatabaseError("Error database SELECT query failed.".to_string())
+        })?;
+
+    let recovered: Vec<(QueuedJob, bool, i64)> = rows
+        .iter()
+        .map(|row| {
+            (
+                QueuedJob {
+                    job_id: row.get(0),
+                    priority: row.get(1),
+                    queue: row.get(2),
+                    tenant_id: row.get(3),
+                    ordering_key: row.get(4),
+                    deadline: row.get(5),
+                },
+                row.get(6),
+                row.get(7),
+            )
+        })
+        .collect();
+
+    // Wall clock enqueue times are not on the logical clock, so they are left out
+    let resume: u64 = recovered
+        .iter()
+        .filter(|(job, _, _)| !queues.get(&job.queue).is_some_and(|q| q.uses_wall_clock()))
+        .map(|(_, _, enqueue_time)| (*enqueue_time).max(0) as u64 + 1)
+        .fold(timestamp, u64::max);
+
+    for (job, leased, enqueue_time) in &recovered {
+        if *leased {
+            queues.restore_lease(job);
+            continue;
+        }
+
+        queues.restore(job, (*enqueue_time).max(0) as u64);
+    }
+
+    Ok(resume)
+}
+
+/// Records a failed attempt at processing a job and takes it back from the consumer it was leased
+/// to.
+///
+/// # Arguments
+/// `client`: The database client.
+/// `job_id`: The job that was nacked by a consumer.
+/// `lease_id`: The lease of the consumer nacking the job, any lease is accepted if not provided.
+///
+/// # Returns
+/// A Result object that is either Ok((i32, QueuedJob)) holding the number of attempts made so far
+/// and the job, or Err(ApiError)
+pub async fn record_failure(
+    client: &Client,
+    job_id: i64,
+    lease_id: Option<Uuid>,
+) -> Result<(i32, QueuedJob), ApiError> {
+    let row = client
+        .query_opt(
+            "UPDATE jobs SET attempts = attempts + 1, status = 'queued', lease_id = NULL
+             WHERE job_id = $1 AND status = 'leased' AND ($2::UUID IS NULL OR lease_id = $2)
+             RETURNING attempts, priority, queue, tenant_id, ordering_key,
+                 (EXTRACT(EPOCH FROM deadline) * 1000)::BIGINT",
+            &[&job_id, &lease_id],
+        )
+        .await
+        .map_err(|_| {
+            error!(target:"error_logger","Error: Failed to run UPDATE query on job {}",job_id);
+            ApiError::DatabaseError("Error updating database".to_string())
+        })?
+        .ok_or_else(|| ApiError::NotFoundError(format!("No leased job with job_id={}", job_id)))?;
+
+    Ok((
+        row.get(0),
+        QueuedJob {
+            job_id,
+            priority: row.get(1),
+            queue: row.get(2),
+            tenant_id: row.get(3),
+            ordering_key: row.get(4),
+            deadline: row.get(5),
+        },
+    ))
+}
+
+/// Takes back a job a consumer leased but never started on. Unlike a failure, the release does
+/// not count as an attempt.
+///
+/// # Arguments
+/// `client`: The database client.
+/// `job_id`: The job that was released by a consumer.
+/// `lease_id`: The lease of the consumer releasing the job.
+///
+/// # Returns
+/// A Result object that is either Ok(QueuedJob) holding the released job, or Err(ApiError)
+pub async fn release_job(
+    client: &Client,
+    job_id: i64,
+    lease_id: Uuid,
+) -> Result<QueuedJob, ApiError> {
+    let row = client
+        .query_opt(
+            "UPDATE jobs SET status = 'queued', lease_id = NULL
+             WHERE job_id = $1 AND status = 'leased' AND lease_id = $2
+             RETURNING priority, queue, tenant_id, ordering_key,
+                 (EXTRACT(EPOCH FROM deadline) * 1000)::BIGINT",
+            &[&job_id, &lease_id],
+        )
+        .await
+        .map_err(|_| {
+            error!(target:"error_logger","Error: Failed to run UPDATE query on job {}",job_id);
+            ApiError::DatabaseError("Error updating database".to_string())
+        })?
+        .ok_or_else(|| ApiError::NotFoundError(format!("No leased job with job_id={}", job_id)))?;
+
+    Ok(QueuedJob {
+        job_id,
+        priority: row.get(0),
+        queue: row.get(1),
+        tenant_id: row.get(2),
+        ordering_key: row.get(3),
+        deadline: row.get(4),
+    })
+}
+
+/// Moves a job to the dead letter state and cascades the failure to every job that depends on
+/// it, directly or transitively. Producers of every dead lettered job are notified.
+///
+/// # Arguments
+/// `client`: The database client.
+/// `job_id`: The job that failed.
+///
+/// # Returns
+/// A Result object that is either Ok(u64) holding the number of dependents that were dead
+/// lettered, or Err(ApiError)
+pub async fn dead_letter_job(client: &Client, job_id: i64) -> Result<u64, ApiError> {
+    let rows = client
+        .execute(
+            "UPDATE jobs SET status = 'dead_lettered', finished_at = now(),
+                 sla_met = CASE WHEN deadline IS NOT NULL THEN FALSE END
+             WHERE job_id = $1 AND status = 'queued'",
+            &[&job_id],
+        )
+        .await
+        .map_err(|_| {
+            error!(target:"error_logger","Error: Failed to run UPDATE query on job {}",job_id);
+            ApiError::DatabaseError("Error updating database".to_string())
+        })?;
+
+    if rows == 0 {
+        return Err(ApiError::NotFoundError(format!(
+            "No queued job with job_id={}",
+            job_id
+        )));
+    }
+
+    let cascaded = client
+        .query(
+            "WITH RECURSIVE descendants AS (
+                 SELECT child_id FROM job_dependencies WHERE parent_id = $1
+                 UNION
+                 SELECT d.child_id FROM job_dependencies d
+                 JOIN descendants ON d.parent_id = descendants.child_id
+             )
+             UPDATE jobs SET status = 'dead_lettered', finished_at = now(),
+                 sla_met = CASE WHEN deadline IS NOT NULL THEN FALSE END
+             WHERE status = 'waiting' AND job_id IN (SELECT child_id FROM descendants)
+             RETURNING job_id",
+            &[&job_id],
+        )
+        .await
+        .map_err(|_| {
+            error!(target:"error_logger","Error: Failed to cascade dead letter of job {}",job_id);
+            ApiError::DatabaseError("Error dead lettering dependent jobs".to_string())
+        })?;
+
+    let mut dead_lettered: Vec<i64> = cascaded.iter().map(|row| row.get(0)).collect();
+    dead_lettered.push(job_id);
+    callbacks::record_event(client, &dead_lettered, "dead_lettered").await?;
+
+    Ok(cascaded.len() as u64)
+}
+
+/// Requeues a job after a failed attempt, or dead letters it once it has used up the attempts
+/// allowed by its queue's retry policy. The outcome is recorded in the database once the lock on
+/// the local queues is released.
+///
+/// # Arguments
+/// `client`: The database client.
+/// `node_id`: The id of this node.
+/// `queues`: The lock guarding the local queues.
+/// `queues_of`: Picks the local queues out of the state guarded by the lock.
+/// `attempts`: The number of attempts made so far.
+/// `job`: The job that failed.
+/// `timestamp`: The current logical time.
+///
+/// # Returns
+/// A Result object that is either Ok(Option<u64>), None if the job was requeued or the number of
+/// dependents that were dead lettered, or Err(ApiError)
+pub async fn retry_or_dead_letter<Q>(
+    client: &Client,
+    node_id: NodeId,
+    queues: &Mutex<Q>,
+    queues_of: fn(&mut Q) -> &mut QueueSet,
+    attempts: i32,
+    job: &QueuedJob,
+    timestamp: u64,
+) -> Result<Option<u64>, ApiError> {
+    let requeued: Option<u64> = {
+        let mut guard = queues.lock().await;
+        let local: &mut QueueSet = queues_of(&mut guard);
+        if attempts < local.queue_mut(&job.queue).config.max_attempts {
+            Some(local.requeue(job, timestamp))
+        } else {
+            local.finish(job.job_id);
+            None
+        }
+    };
+
+    match requeued {
+        Some(enqueue_time) => {
+            record_enqueue_times(client, node_id, &[(job.job_id, enqueue_time)]).await?;
+            callbacks::record_event(client, &[job.job_id], "failed").await?;
+            Ok(None)
+        }
+        None => dead_letter_job(client, job.job_id).await.map(Some),
+    }
+}
+
+/// Takes back every job whose lease expired without a heartbeat. Each expiry counts as a failed
+/// attempt.
+///
+/// # Arguments
+/// `client`: The database client.
+/// `node_id`: The id of this node.
+/// `queues`: The lock guarding the local queues the jobs are returned to.
+/// `queues_of`: Picks the local queues out of the state guarded by the lock.
+/// `timestamp`: The current logical time.
+///
+/// # Returns
+/// A Result object that is either Ok(usize) holding the number of expired leases, or Err(ApiError)
+pub async fn expire_leases<Q>(
+    client: &Client,
+    node_id: NodeId,
+    queues: &Mutex<Q>,
+    queues_of: fn(&mut Q) -> &mut QueueSet,
+    timestamp: u64,
+) -> Result<usize, ApiError> {
+    let rows = client
+        .query(
+            "UPDATE jobs SET attempts = attempts + 1, status = 'queued', lease_id = NULL
+             WHERE status = 'leased' AND lease_expires_at <= now()
+             RETURNING attempts, job_id, priority, queue, tenant_id, ordering_key,
+                 (EXTRACT(EPOCH FROM deadline) * 1000)::BIGINT",
+            &[],
+        )
+        .await
+        .map_err(|_| {
+            error!(target:"error_logger","Error: Failed to expire leases");
+            ApiError::DatabaseError("Error updating database".to_string())
+        })?;
+
+    for row in &rows {
+        let job: QueuedJob = QueuedJob {
+            job_id: row.get(1),
+            priority: row.get(2),
+            queue: row.get(3),
+            tenant_id: row.get(4),
+            ordering_key: row.get(5),
+            deadline: row.get(6),
+        };
+
+        retry_or_dead_letter(
+            client,
+            node_id,
+            queues,
+            queues_of,
+            row.get(0),
+            &job,
+            timestamp,
+        )
+        .await?;
+    }
+
+    Ok(rows.len())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{lease_next, LeasedJob, QueuedJob};
+    use crate::error::ApiError;
+    use crate::queues::QueueSet;
+    use std::cell::RefCell;
+    use std::collections::{HashMap, HashSet};
+    use tokio::sync::Mutex;
+    use uuid::Uuid;
+
+    #[tokio::test]
+    async fn test_jobs_leased_by_another_follower_are_skipped() {
+        let job = |job_id: i64, priority: i32, ordering_key: &str| QueuedJob {
+            job_id,
+            priority,
+            queue: String::new(),
+            tenant_id: String::new(),
+            ordering_key: ordering_key.to_string(),
+            deadline: None,
+        };
+        let first: QueuedJob = job(1, 1, "account-1");
+        let second: QueuedJob = job(2, 1, "account-1");
+        let other: QueuedJob = job(3, 5, "");
+        let keys: HashMap<i64, String> = [&first, &second, &other]
+            .iter()
+            .map(|job| (job.job_id, job.ordering_key.clone()))
+            .collect();
+
+        // The jobs table both followers lease from: the jobs that are no longer queued and the
+        // ordering keys held by a leased job
+        let taken: RefCell<HashSet<i64>> = RefCell::new(HashSet::new());
+        let held: RefCell<HashSet<String>> = RefCell::new(HashSet::new());
+        let lease = |job_id: i64, _: i32| {
+            let key: &str = &keys[&job_id];
+            let free: bool = !taken.borrow().contains(&job_id)
+                && (key.is_empty() || !held.borrow().contains(key));
+            if free {
+                taken.borrow_mut().insert(job_id);
+                held.borrow_mut().insert(key.to_string());
+            }
+
+            async move {
+                match free {
+                    true => Ok(LeasedJob {
+                        job_id,
+                        priority: 1,
+                        payload: Vec::new(),
+                        queue: String::new(),
+                        tenant_id: String::new(),
+                        ordering_key: String::new(),
+                        lease_id: Uuid::new_v4(),
+                        deadline: None,
+                    }),
+                    false => Err(ApiError::NotFoundError(job_id.to_string())),
+                }
+            }
+        };
+
+        // Both followers accept every job the leader replicates
+        let mut a: QueueSet = QueueSet::new(HashMap::new(), HashMap::new());
+        let mut b: QueueSet = QueueSet::new(HashMap::new(), HashMap::new());
+        for queues in [&mut a, &mut b] {
+            queues.insert_job(&first, 0);
+            queues.insert_job(&second, 1);
+            queues.insert_job(&other, 2);
+        }
+        let (a, b): (Mutex<QueueSet>, Mutex<QueueSet>) = (Mutex::new(a), Mutex::new(b));
+
+        let (job, _) = lease_next(&a, |queues| queues, &[], 3, lease)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(job.job_id, first.job_id);
+
+        // The second follower drops the job leased through the first and the job behind it,
+        // rather than failing the poll or blocking the key
+        let (job, _) = lease_next(&b, |queues| queues, &[], 3, lease)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(job.job_id, other.job_id);
+        assert!(lease_next(&b, |queues| queues, &[], 4, lease)
+            .await
+            .unwrap()
+            .is_none());
+        assert!(b.lock().await.is_empty());
+
+        // Once the first job is acked the first follower hands out the job behind it
+        held.borrow_mut().remove("account-1");
+        a.lock().await.finish(first.job_id);
+        let (job, _) = lease_next(&a, |queues| queues, &[], 5, lease)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(job.job_id, second.job_id);
+        assert!(lease_next(&a, |queues| queues, &[], 5, lease)
+            .await
+            .unwrap()
+            .is_none());
+        assert!(a.lock().await.is_empty());
+    }
+}
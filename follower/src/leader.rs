@@ -0,0 +1,62 @@
+use crate::error::ApiError;
+use crate::job_management::job_service_client::JobServiceClient;
+use crate::job_management::ReleaseJobsRequest;
+use log::{error, info};
+use tonic::transport::{Channel, Endpoint};
+
+/// The address of the leader when no `LEADER_URL` is set.
+const DEFAULT_LEADER_URL: &str = "http://[::1]:50051";
+
+/// Leader is this follower's connection to the leader, through which jobs released on this
+/// follower are replicated to every follower.
+/// `channel`: A lazily connected channel, reused by every request.
+#[derive(Debug, Clone)]
+pub struct Leader {
+    channel: Channel,
+}
+
+impl Leader {
+    pub fn new(address: &str) -> Result<Self, String> {
+        let endpoint: Endpoint = Endpoint::from_shared(address.to_string()).map_err(|_| {
+            error!(target:"error_logger","Invalid leader address {}",address);
+            format!("Invalid leader address {}", address)
+        })?;
+
+        Ok(Leader {
+            channel: endpoint.connect_lazy(),
+        })
+    }
+
+    /// Creates a connection to the leader at the `LEADER_URL` environment variable.
+    pub fn from_env() -> Result<Self, String> {
+        let address: String =
+            std::env::var("LEADER_URL").unwrap_or_else(|_| DEFAULT_LEADER_URL.to_string());
+
+        Leader::new(&address)
+    }
+
+    /// Has the leader replicate jobs this follower released when their last parent was acked,
+    /// so they are queued on every follower like any other job.
+    ///
+    /// # Arguments
+    /// `job_ids`: The ids of the released jobs.
+    ///
+    /// # Returns
+    /// A Result object that is either Ok(()) once the jobs are replicated, or Err(ApiError)
+    pub async fn release_jobs(&self, job_ids: &[i64]) -> Result<(), ApiError> {
+        let mut client: JobServiceClient<Channel> = JobServiceClient::new(self.channel.clone());
+
+        let response = client
+            .release_jobs(ReleaseJobsRequest {
+                job_ids: job_ids.iter().map(|job_id| job_id.to_string()).collect(),
+            })
+            .await
+            .map_err(|status| {
+                error!(target:"error_logger","Error: Leader failed to release {} jobs: {}",job_ids.len(),status.message());
+                ApiError::InternalServerError("Error releasing jobs through the leader".to_string())
+            })?;
+
+        info!(target:"request_logger","{}",response.into_inner().message);
+        Ok(())
+    }
+}
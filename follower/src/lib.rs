@@ -1,7 +1,11 @@
 pub mod api;
+pub mod callbacks;
 pub mod error;
 pub mod grpc;
+pub mod jobs;
 pub mod job_management {
     include!("proto/job_management.rs");
 }
-pub mod min_heap;
+pub mod leader;
+pub mod queues;
+pub mod tenants;
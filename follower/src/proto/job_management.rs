@@ -8,6 +8,15 @@ pub struct Job {
     pub priority: i32,
     #[prost(bytes = "vec", tag = "3")]
     pub payload: ::prost::alloc::vec::Vec<u8>,
+    #[prost(string, tag = "4")]
+    pub queue: ::prost::alloc::string::String,
+    #[prost(string, tag = "5")]
+    pub tenant_id: ::prost::alloc::string::String,
+    #[prost(string, tag = "6")]
+    pub ordering_key: ::prost::alloc::string::String,
+    /// Unix time in milliseconds by which the job should be completed, 0 for none
+    #[prost(int64, tag = "7")]
+    pub deadline: i64,
 }
 /// Enqueue Request (job not created yet)
 #[derive(Clone, PartialEq, ::prost::Message)]
@@ -16,6 +25,54 @@ pub struct EnqueueRequest {
     pub priority: i32,
     #[prost(bytes = "vec", tag = "2")]
     pub payload: ::prost::alloc::vec::Vec<u8>,
+    /// Jobs that must be acked before this job is queued
+    #[prost(string, repeated, tag = "3")]
+    pub depends_on: ::prost::alloc::vec::Vec<::prost::alloc::string::String>,
+    /// Named queue the job is added to, empty for the default queue
+    #[prost(string, tag = "4")]
+    pub queue: ::prost::alloc::string::String,
+    /// Tenant that owns the job, empty for the default tenant
+    #[prost(string, tag = "5")]
+    pub tenant_id: ::prost::alloc::string::String,
+    /// Jobs sharing a key are delivered one at a time in FIFO order
+    #[prost(string, tag = "6")]
+    pub ordering_key: ::prost::alloc::string::String,
+    /// Notified when the job completes, fails or is dead lettered
+    #[prost(string, tag = "7")]
+    pub callback_url: ::prost::alloc::string::String,
+    /// Token handed to the producer before the job is assigned an id
+    #[prost(string, tag = "8")]
+    pub tracking_id: ::prost::alloc::string::String,
+    /// Unix time in milliseconds by which the job should be completed, 0 for none
+    #[prost(int64, tag = "9")]
+    pub deadline: i64,
+}
+/// Request to enqueue many jobs at once
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct EnqueueBatchRequest {
+    #[prost(message, repeated, tag = "1")]
+    pub jobs: ::prost::alloc::vec::Vec<EnqueueRequest>,
+}
+/// Outcome of enqueueing a single job of a batch
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct EnqueueResult {
+    #[prost(bool, tag = "1")]
+    pub success: bool,
+    /// The created job, set on success
+    #[prost(message, optional, tag = "2")]
+    pub job: ::core::option::Option<Job>,
+    /// Why the job was rejected, set on failure
+    #[prost(string, tag = "3")]
+    pub error: ::prost::alloc::string::String,
+    /// The tracking id the job was submitted with
+    #[prost(string, tag = "4")]
+    pub tracking_id: ::prost::alloc::string::String,
+}
+/// Response to a batch enqueue, with one result per job in the order they were submitted
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct EnqueueBatchResponse {
+    #[prost(message, repeated, tag = "1")]
+    pub results: ::prost::alloc::vec::Vec<EnqueueResult>,
 }
 /// Request for getting a task
 #[derive(Clone, PartialEq, ::prost::Message)]
@@ -52,6 +109,9 @@ pub struct PaxosAccept {
     pub proposal_number: i32,
     #[prost(message, optional, tag = "2")]
     pub proposed_job: ::core::option::Option<Job>,
+    /// Jobs of a batch, accepted together as one entry
+    #[prost(message, repeated, tag = "3")]
+    pub proposed_jobs: ::prost::alloc::vec::Vec<Job>,
 }
 /// Sent from the follower to the leader to acknowledge the accept message
 #[derive(Clone, Copy, PartialEq, ::prost::Message)]
@@ -60,12 +120,15 @@ pub struct PaxosAck {
     pub proposal_number: i32,
 }
 /// Message to represent a request to get a job (long-polling)
-#[derive(Clone, Copy, PartialEq, ::prost::Message)]
+#[derive(Clone, PartialEq, ::prost::Message)]
 pub struct PollJobRequest {
     #[prost(int32, tag = "1")]
     pub consumer_id: i32,
     #[prost(int32, tag = "2")]
     pub timeout_seconds: i32,
+    /// Queues to poll, empty to poll every queue
+    #[prost(string, repeated, tag = "3")]
+    pub queues: ::prost::alloc::vec::Vec<::prost::alloc::string::String>,
 }
 /// Mesage representing a response from the long-polling request
 #[derive(Clone, PartialEq, ::prost::Message)]
@@ -76,6 +139,162 @@ pub struct PollJobResponse {
     /// The job assigned to the consumer
     #[prost(message, optional, tag = "2")]
     pub job: ::core::option::Option<Job>,
+    /// Identifies the consumer's lease on the job in heartbeats
+    #[prost(string, tag = "3")]
+    pub lease_id: ::prost::alloc::string::String,
+    /// Seconds until the job is requeued unless a heartbeat is sent
+    #[prost(int32, tag = "4")]
+    pub visibility_timeout_seconds: i32,
+}
+/// Progress reported by a consumer while it processes a job
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct JobProgress {
+    #[prost(int32, tag = "1")]
+    pub percent: i32,
+    #[prost(string, tag = "2")]
+    pub message: ::prost::alloc::string::String,
+}
+/// Sent by a consumer to keep its lease on a job alive and report progress
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct HeartbeatRequest {
+    #[prost(string, tag = "1")]
+    pub job_id: ::prost::alloc::string::String,
+    #[prost(string, tag = "2")]
+    pub lease_id: ::prost::alloc::string::String,
+    #[prost(message, optional, tag = "3")]
+    pub progress: ::core::option::Option<JobProgress>,
+}
+#[derive(Clone, Copy, PartialEq, ::prost::Message)]
+pub struct HeartbeatResponse {
+    /// false if the lease has expired or belongs to another consumer
+    #[prost(bool, tag = "1")]
+    pub success: bool,
+    /// the job was cancelled and should be abandoned
+    #[prost(bool, tag = "2")]
+    pub cancelled: bool,
+    /// Seconds until the renewed lease expires
+    #[prost(int32, tag = "3")]
+    pub visibility_timeout_seconds: i32,
+}
+/// Output attached to a job by the consumer that processed it
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct JobResult {
+    #[prost(bytes = "vec", tag = "1")]
+    pub data: ::prost::alloc::vec::Vec<u8>,
+    #[prost(string, tag = "2")]
+    pub content_type: ::prost::alloc::string::String,
+}
+/// Sent by a consumer once it has processed a job
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct AckRequest {
+    #[prost(string, tag = "1")]
+    pub job_id: ::prost::alloc::string::String,
+    #[prost(string, tag = "2")]
+    pub lease_id: ::prost::alloc::string::String,
+    /// Optional output of the job
+    #[prost(message, optional, tag = "3")]
+    pub result: ::core::option::Option<JobResult>,
+}
+/// Sent by a consumer when it failed to process a job
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct NackRequest {
+    #[prost(string, tag = "1")]
+    pub job_id: ::prost::alloc::string::String,
+    #[prost(string, tag = "2")]
+    pub lease_id: ::prost::alloc::string::String,
+}
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct AckResponse {
+    #[prost(bool, tag = "1")]
+    pub success: bool,
+    #[prost(string, tag = "2")]
+    pub message: ::prost::alloc::string::String,
+}
+/// Request for the state of a job
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct JobStatusRequest {
+    /// The job id or the tracking id the job was enqueued with
+    #[prost(string, tag = "1")]
+    pub id: ::prost::alloc::string::String,
+}
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct JobStatusResponse {
+    #[prost(string, tag = "1")]
+    pub job_id: ::prost::alloc::string::String,
+    #[prost(string, tag = "2")]
+    pub tracking_id: ::prost::alloc::string::String,
+    /// waiting, queued, leased, failed, completed, dead_lettered or cancelled
+    #[prost(string, tag = "3")]
+    pub status: ::prost::alloc::string::String,
+    #[prost(int32, tag = "4")]
+    pub priority: i32,
+    #[prost(int32, tag = "5")]
+    pub attempts: i32,
+    #[prost(string, tag = "6")]
+    pub queue: ::prost::alloc::string::String,
+    #[prost(string, tag = "7")]
+    pub tenant_id: ::prost::alloc::string::String,
+    #[prost(message, optional, tag = "8")]
+    pub progress: ::core::option::Option<JobProgress>,
+    /// Unix time the job was created
+    #[prost(int64, tag = "9")]
+    pub created_at: i64,
+    /// Unix time the job finished, 0 if it has not
+    #[prost(int64, tag = "10")]
+    pub finished_at: i64,
+}
+/// Request for the result of a job, waiting up to timeout_seconds for the job to finish
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct ResultRequest {
+    #[prost(string, tag = "1")]
+    pub job_id: ::prost::alloc::string::String,
+    #[prost(int32, tag = "2")]
+    pub timeout_seconds: i32,
+}
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct ResultResponse {
+    /// Lifecycle state of the job
+    #[prost(string, tag = "1")]
+    pub status: ::prost::alloc::string::String,
+    /// Set once the job has completed with a result
+    #[prost(message, optional, tag = "2")]
+    pub result: ::core::option::Option<JobResult>,
+}
+/// Request to cancel a job that has already been enqueued
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct CancelJobRequest {
+    #[prost(string, tag = "1")]
+    pub job_id: ::prost::alloc::string::String,
+}
+/// Response indicating whether the job was cancelled
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct CancelJobResponse {
+    #[prost(bool, tag = "1")]
+    pub success: bool,
+    #[prost(string, tag = "2")]
+    pub message: ::prost::alloc::string::String,
+}
+/// Sent from the leader to the follower to remove a cancelled job from the local queue
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct PaxosCancel {
+    #[prost(int32, tag = "1")]
+    pub proposal_number: i32,
+    #[prost(string, tag = "2")]
+    pub job_id: ::prost::alloc::string::String,
+}
+/// Sent by a follower to have the leader replicate the jobs released when their last parent was acked
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct ReleaseJobsRequest {
+    #[prost(string, repeated, tag = "1")]
+    pub job_ids: ::prost::alloc::vec::Vec<::prost::alloc::string::String>,
+}
+/// Response indicating how many of the released jobs were replicated
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct ReleaseJobsResponse {
+    #[prost(bool, tag = "1")]
+    pub success: bool,
+    #[prost(string, tag = "2")]
+    pub message: ::prost::alloc::string::String,
 }
 #[derive(Clone, Copy, PartialEq, ::prost::Message)]
 pub struct NodeHealthRequest {}
@@ -90,6 +309,40 @@ pub struct NodeHealthResponse {
     #[prost(float, tag = "4")]
     pub response_time: f32,
 }
+/// Sent by a consumer to join a consumer group, and periodically to stay in it
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct JoinGroupRequest {
+    #[prost(string, tag = "1")]
+    pub group: ::prost::alloc::string::String,
+    #[prost(string, tag = "2")]
+    pub member_id: ::prost::alloc::string::String,
+}
+/// Sent by a consumer leaving a consumer group on shutdown
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct LeaveGroupRequest {
+    #[prost(string, tag = "1")]
+    pub group: ::prost::alloc::string::String,
+    #[prost(string, tag = "2")]
+    pub member_id: ::prost::alloc::string::String,
+}
+/// The followers assigned to a member of a consumer group
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct GroupAssignment {
+    #[prost(bool, tag = "1")]
+    pub success: bool,
+    /// Incremented every time the group is rebalanced
+    #[prost(int64, tag = "2")]
+    pub generation: i64,
+    /// Url addresses of the followers the member polls
+    #[prost(string, repeated, tag = "3")]
+    pub followers: ::prost::alloc::vec::Vec<::prost::alloc::string::String>,
+    /// Number of members in the group
+    #[prost(int32, tag = "4")]
+    pub members: i32,
+    /// How long the member stays in the group without joining again
+    #[prost(int32, tag = "5")]
+    pub session_timeout_seconds: i32,
+}
 /// Generated client implementations.
 pub mod job_service_client {
     #![allow(
@@ -203,6 +456,55 @@ pub mod job_service_client {
                 .insert(GrpcMethod::new("job_management.JobService", "EnqueueJob"));
             self.inner.unary(req, path, codec).await
         }
+        pub async fn enqueue_batch(
+            &mut self,
+            request: impl tonic::IntoRequest<super::EnqueueBatchRequest>,
+        ) -> std::result::Result<
+            tonic::Response<super::EnqueueBatchResponse>,
+            tonic::Status,
+        > {
+            self.inner
+                .ready()
+                .await
+                .map_err(|e| {
+                    tonic::Status::unknown(
+                        format!("Service was not ready: {}", e.into()),
+                    )
+                })?;
+            let codec = tonic::codec::ProstCodec::default();
+            let path = http::uri::PathAndQuery::from_static(
+                "/job_management.JobService/EnqueueBatch",
+            );
+            let mut req = request.into_request();
+            req.extensions_mut()
+                .insert(GrpcMethod::new("job_management.JobService", "EnqueueBatch"));
+            self.inner.unary(req, path, codec).await
+        }
+        /// Jobs pushed over the stream are enqueued in batches and a result is streamed back for each
+        pub async fn enqueue_stream(
+            &mut self,
+            request: impl tonic::IntoStreamingRequest<Message = super::EnqueueRequest>,
+        ) -> std::result::Result<
+            tonic::Response<tonic::codec::Streaming<super::EnqueueResult>>,
+            tonic::Status,
+        > {
+            self.inner
+                .ready()
+                .await
+                .map_err(|e| {
+                    tonic::Status::unknown(
+                        format!("Service was not ready: {}", e.into()),
+                    )
+                })?;
+            let codec = tonic::codec::ProstCodec::default();
+            let path = http::uri::PathAndQuery::from_static(
+                "/job_management.JobService/EnqueueStream",
+            );
+            let mut req = request.into_streaming_request();
+            req.extensions_mut()
+                .insert(GrpcMethod::new("job_management.JobService", "EnqueueStream"));
+            self.inner.streaming(req, path, codec).await
+        }
         pub async fn get_task(
             &mut self,
             request: impl tonic::IntoRequest<super::JobRequest>,
@@ -224,6 +526,99 @@ pub mod job_service_client {
                 .insert(GrpcMethod::new("job_management.JobService", "GetTask"));
             self.inner.unary(req, path, codec).await
         }
+        pub async fn cancel_job(
+            &mut self,
+            request: impl tonic::IntoRequest<super::CancelJobRequest>,
+        ) -> std::result::Result<
+            tonic::Response<super::CancelJobResponse>,
+            tonic::Status,
+        > {
+            self.inner
+                .ready()
+                .await
+                .map_err(|e| {
+                    tonic::Status::unknown(
+                        format!("Service was not ready: {}", e.into()),
+                    )
+                })?;
+            let codec = tonic::codec::ProstCodec::default();
+            let path = http::uri::PathAndQuery::from_static(
+                "/job_management.JobService/CancelJob",
+            );
+            let mut req = request.into_request();
+            req.extensions_mut()
+                .insert(GrpcMethod::new("job_management.JobService", "CancelJob"));
+            self.inner.unary(req, path, codec).await
+        }
+        pub async fn get_result(
+            &mut self,
+            request: impl tonic::IntoRequest<super::ResultRequest>,
+        ) -> std::result::Result<tonic::Response<super::ResultResponse>, tonic::Status> {
+            self.inner
+                .ready()
+                .await
+                .map_err(|e| {
+                    tonic::Status::unknown(
+                        format!("Service was not ready: {}", e.into()),
+                    )
+                })?;
+            let codec = tonic::codec::ProstCodec::default();
+            let path = http::uri::PathAndQuery::from_static(
+                "/job_management.JobService/GetResult",
+            );
+            let mut req = request.into_request();
+            req.extensions_mut()
+                .insert(GrpcMethod::new("job_management.JobService", "GetResult"));
+            self.inner.unary(req, path, codec).await
+        }
+        pub async fn get_job_status(
+            &mut self,
+            request: impl tonic::IntoRequest<super::JobStatusRequest>,
+        ) -> std::result::Result<
+            tonic::Response<super::JobStatusResponse>,
+            tonic::Status,
+        > {
+            self.inner
+                .ready()
+                .await
+                .map_err(|e| {
+                    tonic::Status::unknown(
+                        format!("Service was not ready: {}", e.into()),
+                    )
+                })?;
+            let codec = tonic::codec::ProstCodec::default();
+            let path = http::uri::PathAndQuery::from_static(
+                "/job_management.JobService/GetJobStatus",
+            );
+            let mut req = request.into_request();
+            req.extensions_mut()
+                .insert(GrpcMethod::new("job_management.JobService", "GetJobStatus"));
+            self.inner.unary(req, path, codec).await
+        }
+        pub async fn release_jobs(
+            &mut self,
+            request: impl tonic::IntoRequest<super::ReleaseJobsRequest>,
+        ) -> std::result::Result<
+            tonic::Response<super::ReleaseJobsResponse>,
+            tonic::Status,
+        > {
+            self.inner
+                .ready()
+                .await
+                .map_err(|e| {
+                    tonic::Status::unknown(
+                        format!("Service was not ready: {}", e.into()),
+                    )
+                })?;
+            let codec = tonic::codec::ProstCodec::default();
+            let path = http::uri::PathAndQuery::from_static(
+                "/job_management.JobService/ReleaseJobs",
+            );
+            let mut req = request.into_request();
+            req.extensions_mut()
+                .insert(GrpcMethod::new("job_management.JobService", "ReleaseJobs"));
+            self.inner.unary(req, path, codec).await
+        }
     }
 }
 /// Generated client implementations.
@@ -341,48 +736,137 @@ pub mod long_polling_service_client {
                 .insert(GrpcMethod::new("job_management.LongPollingService", "Poll"));
             self.inner.unary(req, path, codec).await
         }
-    }
-}
-/// Generated client implementations.
-pub mod paxos_service_client {
-    #![allow(
-        unused_variables,
-        dead_code,
-        missing_docs,
-        clippy::wildcard_imports,
-        clippy::let_unit_value,
-    )]
-    use tonic::codegen::*;
-    use tonic::codegen::http::Uri;
-    #[derive(Debug, Clone)]
-    pub struct PaxosServiceClient<T> {
-        inner: tonic::client::Grpc<T>,
-    }
-    impl PaxosServiceClient<tonic::transport::Channel> {
-        /// Attempt to create a new client by connecting to a given endpoint.
-        pub async fn connect<D>(dst: D) -> Result<Self, tonic::transport::Error>
-        where
-            D: TryInto<tonic::transport::Endpoint>,
-            D::Error: Into<StdError>,
-        {
-            let conn = tonic::transport::Endpoint::new(dst)?.connect().await?;
-            Ok(Self::new(conn))
-        }
-    }
-    impl<T> PaxosServiceClient<T>
-    where
-        T: tonic::client::GrpcService<tonic::body::BoxBody>,
-        T::Error: Into<StdError>,
-        T::ResponseBody: Body<Data = Bytes> + std::marker::Send + 'static,
-        <T::ResponseBody as Body>::Error: Into<StdError> + std::marker::Send,
-    {
-        pub fn new(inner: T) -> Self {
-            let inner = tonic::client::Grpc::new(inner);
-            Self { inner }
-        }
-        pub fn with_origin(inner: T, origin: Uri) -> Self {
-            let inner = tonic::client::Grpc::with_origin(inner, origin);
-            Self { inner }
+        pub async fn heartbeat(
+            &mut self,
+            request: impl tonic::IntoRequest<super::HeartbeatRequest>,
+        ) -> std::result::Result<
+            tonic::Response<super::HeartbeatResponse>,
+            tonic::Status,
+        > {
+            self.inner
+                .ready()
+                .await
+                .map_err(|e| {
+                    tonic::Status::unknown(
+                        format!("Service was not ready: {}", e.into()),
+                    )
+                })?;
+            let codec = tonic::codec::ProstCodec::default();
+            let path = http::uri::PathAndQuery::from_static(
+                "/job_management.LongPollingService/Heartbeat",
+            );
+            let mut req = request.into_request();
+            req.extensions_mut()
+                .insert(
+                    GrpcMethod::new("job_management.LongPollingService", "Heartbeat"),
+                );
+            self.inner.unary(req, path, codec).await
+        }
+        pub async fn ack(
+            &mut self,
+            request: impl tonic::IntoRequest<super::AckRequest>,
+        ) -> std::result::Result<tonic::Response<super::AckResponse>, tonic::Status> {
+            self.inner
+                .ready()
+                .await
+                .map_err(|e| {
+                    tonic::Status::unknown(
+                        format!("Service was not ready: {}", e.into()),
+                    )
+                })?;
+            let codec = tonic::codec::ProstCodec::default();
+            let path = http::uri::PathAndQuery::from_static(
+                "/job_management.LongPollingService/Ack",
+            );
+            let mut req = request.into_request();
+            req.extensions_mut()
+                .insert(GrpcMethod::new("job_management.LongPollingService", "Ack"));
+            self.inner.unary(req, path, codec).await
+        }
+        pub async fn nack(
+            &mut self,
+            request: impl tonic::IntoRequest<super::NackRequest>,
+        ) -> std::result::Result<tonic::Response<super::AckResponse>, tonic::Status> {
+            self.inner
+                .ready()
+                .await
+                .map_err(|e| {
+                    tonic::Status::unknown(
+                        format!("Service was not ready: {}", e.into()),
+                    )
+                })?;
+            let codec = tonic::codec::ProstCodec::default();
+            let path = http::uri::PathAndQuery::from_static(
+                "/job_management.LongPollingService/Nack",
+            );
+            let mut req = request.into_request();
+            req.extensions_mut()
+                .insert(GrpcMethod::new("job_management.LongPollingService", "Nack"));
+            self.inner.unary(req, path, codec).await
+        }
+        pub async fn release(
+            &mut self,
+            request: impl tonic::IntoRequest<super::NackRequest>,
+        ) -> std::result::Result<tonic::Response<super::AckResponse>, tonic::Status> {
+            self.inner
+                .ready()
+                .await
+                .map_err(|e| {
+                    tonic::Status::unknown(
+                        format!("Service was not ready: {}", e.into()),
+                    )
+                })?;
+            let codec = tonic::codec::ProstCodec::default();
+            let path = http::uri::PathAndQuery::from_static(
+                "/job_management.LongPollingService/Release",
+            );
+            let mut req = request.into_request();
+            req.extensions_mut()
+                .insert(GrpcMethod::new("job_management.LongPollingService", "Release"));
+            self.inner.unary(req, path, codec).await
+        }
+    }
+}
+/// Generated client implementations.
+pub mod paxos_service_client {
+    #![allow(
+        unused_variables,
+        dead_code,
+        missing_docs,
+        clippy::wildcard_imports,
+        clippy::let_unit_value,
+    )]
+    use tonic::codegen::*;
+    use tonic::codegen::http::Uri;
+    #[derive(Debug, Clone)]
+    pub struct PaxosServiceClient<T> {
+        inner: tonic::client::Grpc<T>,
+    }
+    impl PaxosServiceClient<tonic::transport::Channel> {
+        /// Attempt to create a new client by connecting to a given endpoint.
+        pub async fn connect<D>(dst: D) -> Result<Self, tonic::transport::Error>
+        where
+            D: TryInto<tonic::transport::Endpoint>,
+            D::Error: Into<StdError>,
+        {
+            let conn = tonic::transport::Endpoint::new(dst)?.connect().await?;
+            Ok(Self::new(conn))
+        }
+    }
+    impl<T> PaxosServiceClient<T>
+    where
+        T: tonic::client::GrpcService<tonic::body::BoxBody>,
+        T::Error: Into<StdError>,
+        T::ResponseBody: Body<Data = Bytes> + std::marker::Send + 'static,
+        <T::ResponseBody as Body>::Error: Into<StdError> + std::marker::Send,
+    {
+        pub fn new(inner: T) -> Self {
+            let inner = tonic::client::Grpc::new(inner);
+            Self { inner }
+        }
+        pub fn with_origin(inner: T, origin: Uri) -> Self {
+            let inner = tonic::client::Grpc::with_origin(inner, origin);
+            Self { inner }
         }
         pub fn with_interceptor<F>(
             inner: T,
@@ -476,10 +960,31 @@ pub mod paxos_service_client {
                 .insert(GrpcMethod::new("job_management.PaxosService", "Accept"));
             self.inner.unary(req, path, codec).await
         }
+        pub async fn cancel(
+            &mut self,
+            request: impl tonic::IntoRequest<super::PaxosCancel>,
+        ) -> std::result::Result<tonic::Response<super::PaxosAck>, tonic::Status> {
+            self.inner
+                .ready()
+                .await
+                .map_err(|e| {
+                    tonic::Status::unknown(
+                        format!("Service was not ready: {}", e.into()),
+                    )
+                })?;
+            let codec = tonic::codec::ProstCodec::default();
+            let path = http::uri::PathAndQuery::from_static(
+                "/job_management.PaxosService/Cancel",
+            );
+            let mut req = request.into_request();
+            req.extensions_mut()
+                .insert(GrpcMethod::new("job_management.PaxosService", "Cancel"));
+            self.inner.unary(req, path, codec).await
+        }
     }
 }
 /// Generated client implementations.
-pub mod node_health_service_client {
+pub mod consumer_group_service_client {
     #![allow(
         unused_variables,
         dead_code,
@@ -489,11 +994,12 @@ pub mod node_health_service_client {
     )]
     use tonic::codegen::*;
     use tonic::codegen::http::Uri;
+    /// Tracks consumer group membership on the leader and splits the followers between the members
     #[derive(Debug, Clone)]
-    pub struct NodeHealthServiceClient<T> {
+    pub struct ConsumerGroupServiceClient<T> {
         inner: tonic::client::Grpc<T>,
     }
-    impl NodeHealthServiceClient<tonic::transport::Channel> {
+    impl ConsumerGroupServiceClient<tonic::transport::Channel> {
         /// Attempt to create a new client by connecting to a given endpoint.
         pub async fn connect<D>(dst: D) -> Result<Self, tonic::transport::Error>
         where
@@ -504,7 +1010,7 @@ pub mod node_health_service_client {
             Ok(Self::new(conn))
         }
     }
-    impl<T> NodeHealthServiceClient<T>
+    impl<T> ConsumerGroupServiceClient<T>
     where
         T: tonic::client::GrpcService<tonic::body::BoxBody>,
         T::Error: Into<StdError>,
@@ -522,7 +1028,7 @@ pub mod node_health_service_client {
         pub fn with_interceptor<F>(
             inner: T,
             interceptor: F,
-        ) -> NodeHealthServiceClient<InterceptedService<T, F>>
+        ) -> ConsumerGroupServiceClient<InterceptedService<T, F>>
         where
             F: tonic::service::Interceptor,
             T::ResponseBody: Default,
@@ -536,7 +1042,7 @@ pub mod node_health_service_client {
                 http::Request<tonic::body::BoxBody>,
             >>::Error: Into<StdError> + std::marker::Send + std::marker::Sync,
         {
-            NodeHealthServiceClient::new(InterceptedService::new(inner, interceptor))
+            ConsumerGroupServiceClient::new(InterceptedService::new(inner, interceptor))
         }
         /// Compress requests with the given encoding.
         ///
@@ -569,11 +1075,11 @@ pub mod node_health_service_client {
             self.inner = self.inner.max_encoding_message_size(limit);
             self
         }
-        pub async fn get_node_health(
+        pub async fn join_group(
             &mut self,
-            request: impl tonic::IntoRequest<super::NodeHealthRequest>,
+            request: impl tonic::IntoRequest<super::JoinGroupRequest>,
         ) -> std::result::Result<
-            tonic::Response<super::NodeHealthResponse>,
+            tonic::Response<super::GroupAssignment>,
             tonic::Status,
         > {
             self.inner
@@ -586,19 +1092,45 @@ pub mod node_health_service_client {
                 })?;
             let codec = tonic::codec::ProstCodec::default();
             let path = http::uri::PathAndQuery::from_static(
-                "/job_management.NodeHealthService/GetNodeHealth",
+                "/job_management.ConsumerGroupService/JoinGroup",
             );
             let mut req = request.into_request();
             req.extensions_mut()
                 .insert(
-                    GrpcMethod::new("job_management.NodeHealthService", "GetNodeHealth"),
+                    GrpcMethod::new("job_management.ConsumerGroupService", "JoinGroup"),
+                );
+            self.inner.unary(req, path, codec).await
+        }
+        pub async fn leave_group(
+            &mut self,
+            request: impl tonic::IntoRequest<super::LeaveGroupRequest>,
+        ) -> std::result::Result<
+            tonic::Response<super::GroupAssignment>,
+            tonic::Status,
+        > {
+            self.inner
+                .ready()
+                .await
+                .map_err(|e| {
+                    tonic::Status::unknown(
+                        format!("Service was not ready: {}", e.into()),
+                    )
+                })?;
+            let codec = tonic::codec::ProstCodec::default();
+            let path = http::uri::PathAndQuery::from_static(
+                "/job_management.ConsumerGroupService/LeaveGroup",
+            );
+            let mut req = request.into_request();
+            req.extensions_mut()
+                .insert(
+                    GrpcMethod::new("job_management.ConsumerGroupService", "LeaveGroup"),
                 );
             self.inner.unary(req, path, codec).await
         }
     }
 }
-/// Generated server implementations.
-pub mod job_service_server {
+/// Generated client implementations.
+pub mod node_health_service_client {
     #![allow(
         unused_variables,
         dead_code,
@@ -607,59 +1139,69 @@ pub mod job_service_server {
         clippy::let_unit_value,
     )]
     use tonic::codegen::*;
-    /// Generated trait containing gRPC methods that should be implemented for use with JobServiceServer.
-    #[async_trait]
-    pub trait JobService: std::marker::Send + std::marker::Sync + 'static {
-        async fn enqueue_job(
-            &self,
-            request: tonic::Request<super::EnqueueRequest>,
-        ) -> std::result::Result<tonic::Response<super::Job>, tonic::Status>;
-        async fn get_task(
-            &self,
-            request: tonic::Request<super::JobRequest>,
-        ) -> std::result::Result<tonic::Response<super::JobResponse>, tonic::Status>;
+    use tonic::codegen::http::Uri;
+    #[derive(Debug, Clone)]
+    pub struct NodeHealthServiceClient<T> {
+        inner: tonic::client::Grpc<T>,
     }
-    /// Service definition for Job management and Paxos protocol
-    #[derive(Debug)]
-    pub struct JobServiceServer<T> {
-        inner: Arc<T>,
-        accept_compression_encodings: EnabledCompressionEncodings,
-        send_compression_encodings: EnabledCompressionEncodings,
-        max_decoding_message_size: Option<usize>,
-        max_encoding_message_size: Option<usize>,
+    impl NodeHealthServiceClient<tonic::transport::Channel> {
+        /// Attempt to create a new client by connecting to a given endpoint.
+        pub async fn connect<D>(dst: D) -> Result<Self, tonic::transport::Error>
+        where
+            D: TryInto<tonic::transport::Endpoint>,
+            D::Error: Into<StdError>,
+        {
+            let conn = tonic::transport::Endpoint::new(dst)?.connect().await?;
+            Ok(Self::new(conn))
+        }
     }
-    impl<T> JobServiceServer<T> {
+    impl<T> NodeHealthServiceClient<T>
+    where
+        T: tonic::client::GrpcService<tonic::body::BoxBody>,
+        T::Error: Into<StdError>,
+        T::ResponseBody: Body<Data = Bytes> + std::marker::Send + 'static,
+        <T::ResponseBody as Body>::Error: Into<StdError> + std::marker::Send,
+    {
         pub fn new(inner: T) -> Self {
-            Self::from_arc(Arc::new(inner))
+            let inner = tonic::client::Grpc::new(inner);
+            Self { inner }
         }
-        pub fn from_arc(inner: Arc<T>) -> Self {
-            Self {
-                inner,
-                accept_compression_encodings: Default::default(),
-                send_compression_encodings: Default::default(),
-                max_decoding_message_size: None,
-                max_encoding_message_size: None,
-            }
+        pub fn with_origin(inner: T, origin: Uri) -> Self {
+            let inner = tonic::client::Grpc::with_origin(inner, origin);
+            Self { inner }
         }
         pub fn with_interceptor<F>(
             inner: T,
             interceptor: F,
-        ) -> InterceptedService<Self, F>
+        ) -> NodeHealthServiceClient<InterceptedService<T, F>>
         where
             F: tonic::service::Interceptor,
+            T::ResponseBody: Default,
+            T: tonic::codegen::Service<
+                http::Request<tonic::body::BoxBody>,
+                Response = http::Response<
+                    <T as tonic::client::GrpcService<tonic::body::BoxBody>>::ResponseBody,
+                >,
+            >,
+            <T as tonic::codegen::Service<
+                http::Request<tonic::body::BoxBody>,
+            >>::Error: Into<StdError> + std::marker::Send + std::marker::Sync,
         {
-            InterceptedService::new(Self::new(inner), interceptor)
+            NodeHealthServiceClient::new(InterceptedService::new(inner, interceptor))
         }
-        /// Enable decompressing requests with the given encoding.
+        /// Compress requests with the given encoding.
+        ///
+        /// This requires the server to support it otherwise it might respond with an
+        /// error.
         #[must_use]
-        pub fn accept_compressed(mut self, encoding: CompressionEncoding) -> Self {
-            self.accept_compression_encodings.enable(encoding);
+        pub fn send_compressed(mut self, encoding: CompressionEncoding) -> Self {
+            self.inner = self.inner.send_compressed(encoding);
             self
         }
-        /// Compress responses with the given encoding, if the client supports it.
+        /// Enable decompressing responses.
         #[must_use]
-        pub fn send_compressed(mut self, encoding: CompressionEncoding) -> Self {
-            self.send_compression_encodings.enable(encoding);
+        pub fn accept_compressed(mut self, encoding: CompressionEncoding) -> Self {
+            self.inner = self.inner.accept_compressed(encoding);
             self
         }
         /// Limits the maximum size of a decoded message.
@@ -667,7 +1209,7 @@ pub mod job_service_server {
         /// Default: `4MB`
         #[must_use]
         pub fn max_decoding_message_size(mut self, limit: usize) -> Self {
-            self.max_decoding_message_size = Some(limit);
+            self.inner = self.inner.max_decoding_message_size(limit);
             self
         }
         /// Limits the maximum size of an encoded message.
@@ -675,46 +1217,799 @@ pub mod job_service_server {
         /// Default: `usize::MAX`
         #[must_use]
         pub fn max_encoding_message_size(mut self, limit: usize) -> Self {
-            self.max_encoding_message_size = Some(limit);
+            self.inner = self.inner.max_encoding_message_size(limit);
             self
         }
-    }
-    impl<T, B> tonic::codegen::Service<http::Request<B>> for JobServiceServer<T>
-    where
-        T: JobService,
-        B: Body + std::marker::Send + 'static,
-        B::Error: Into<StdError> + std::marker::Send + 'static,
-    {
-        type Response = http::Response<tonic::body::BoxBody>;
-        type Error = std::convert::Infallible;
-        type Future = BoxFuture<Self::Response, Self::Error>;
-        fn poll_ready(
+        pub async fn get_node_health(
             &mut self,
-            _cx: &mut Context<'_>,
-        ) -> Poll<std::result::Result<(), Self::Error>> {
-            Poll::Ready(Ok(()))
-        }
-        fn call(&mut self, req: http::Request<B>) -> Self::Future {
-            match req.uri().path() {
-                "/job_management.JobService/EnqueueJob" => {
+            request: impl tonic::IntoRequest<super::NodeHealthRequest>,
+        ) -> std::result::Result<
+            tonic::Response<super::NodeHealthResponse>,
+            tonic::Status,
+        > {
+            self.inner
+                .ready()
+                .await
+                .map_err(|e| {
+                    tonic::Status::unknown(
+                        format!("Service was not ready: {}", e.into()),
+                    )
+                })?;
+            let codec = tonic::codec::ProstCodec::default();
+            let path = http::uri::PathAndQuery::from_static(
+                "/job_management.NodeHealthService/GetNodeHealth",
+            );
+            let mut req = request.into_request();
+            req.extensions_mut()
+                .insert(
+                    GrpcMethod::new("job_management.NodeHealthService", "GetNodeHealth"),
+                );
+            self.inner.unary(req, path, codec).await
+        }
+    }
+}
+/// Generated server implementations.
+pub mod job_service_server {
+    #![allow(
+        unused_variables,
+        dead_code,
+        missing_docs,
+        clippy::wildcard_imports,
+        clippy::let_unit_value,
+    )]
+    use tonic::codegen::*;
+    /// Generated trait containing gRPC methods that should be implemented for use with JobServiceServer.
+    #[async_trait]
+    pub trait JobService: std::marker::Send + std::marker::Sync + 'static {
+        async fn enqueue_job(
+            &self,
+            request: tonic::Request<super::EnqueueRequest>,
+        ) -> std::result::Result<tonic::Response<super::Job>, tonic::Status>;
+        async fn enqueue_batch(
+            &self,
+            request: tonic::Request<super::EnqueueBatchRequest>,
+        ) -> std::result::Result<
+            tonic::Response<super::EnqueueBatchResponse>,
+            tonic::Status,
+        >;
+        /// Server streaming response type for the EnqueueStream method.
+        type EnqueueStreamStream: tonic::codegen::tokio_stream::Stream<
+                Item = std::result::Result<super::EnqueueResult, tonic::Status>,
+            >
+            + std::marker::Send
+            + 'static;
+        /// Jobs pushed over the stream are enqueued in batches and a result is streamed back for each
+        async fn enqueue_stream(
+            &self,
+            request: tonic::Request<tonic::Streaming<super::EnqueueRequest>>,
+        ) -> std::result::Result<
+            tonic::Response<Self::EnqueueStreamStream>,
+            tonic::Status,
+        >;
+        async fn get_task(
+            &self,
+            request: tonic::Request<super::JobRequest>,
+        ) -> std::result::Result<tonic::Response<super::JobResponse>, tonic::Status>;
+        async fn cancel_job(
+            &self,
+            request: tonic::Request<super::CancelJobRequest>,
+        ) -> std::result::Result<
+            tonic::Response<super::CancelJobResponse>,
+            tonic::Status,
+        >;
+        async fn get_result(
+            &self,
+            request: tonic::Request<super::ResultRequest>,
+        ) -> std::result::Result<tonic::Response<super::ResultResponse>, tonic::Status>;
+        async fn get_job_status(
+            &self,
+            request: tonic::Request<super::JobStatusRequest>,
+        ) -> std::result::Result<
+            tonic::Response<super::JobStatusResponse>,
+            tonic::Status,
+        >;
+        async fn release_jobs(
+            &self,
+            request: tonic::Request<super::ReleaseJobsRequest>,
+        ) -> std::result::Result<
+            tonic::Response<super::ReleaseJobsResponse>,
+            tonic::Status,
+        >;
+    }
+    /// Service definition for Job management and Paxos protocol
+    #[derive(Debug)]
+    pub struct JobServiceServer<T> {
+        inner: Arc<T>,
+        accept_compression_encodings: EnabledCompressionEncodings,
+        send_compression_encodings: EnabledCompressionEncodings,
+        max_decoding_message_size: Option<usize>,
+        max_encoding_message_size: Option<usize>,
+    }
+    impl<T> JobServiceServer<T> {
+        pub fn new(inner: T) -> Self {
+            Self::from_arc(Arc::new(inner))
+        }
+        pub fn from_arc(inner: Arc<T>) -> Self {
+            Self {
+                inner,
+                accept_compression_encodings: Default::default(),
+                send_compression_encodings: Default::default(),
+                max_decoding_message_size: None,
+                max_encoding_message_size: None,
+            }
+        }
+        pub fn with_interceptor<F>(
+            inner: T,
+            interceptor: F,
+        ) -> InterceptedService<Self, F>
+        where
+            F: tonic::service::Interceptor,
+        {
+            InterceptedService::new(Self::new(inner), interceptor)
+        }
+        /// Enable decompressing requests with the given encoding.
+        #[must_use]
+        pub fn accept_compressed(mut self, encoding: CompressionEncoding) -> Self {
+            self.accept_compression_encodings.enable(encoding);
+            self
+        }
+        /// Compress responses with the given encoding, if the client supports it.
+        #[must_use]
+        pub fn send_compressed(mut self, encoding: CompressionEncoding) -> Self {
+            self.send_compression_encodings.enable(encoding);
+            self
+        }
+        /// Limits the maximum size of a decoded message.
+        ///
+        /// Default: `4MB`
+        #[must_use]
+        pub fn max_decoding_message_size(mut self, limit: usize) -> Self {
+            self.max_decoding_message_size = Some(limit);
+            self
+        }
+        /// Limits the maximum size of an encoded message.
+        ///
+        /// Default: `usize::MAX`
+        #[must_use]
+        pub fn max_encoding_message_size(mut self, limit: usize) -> Self {
+            self.max_encoding_message_size = Some(limit);
+            self
+        }
+    }
+    impl<T, B> tonic::codegen::Service<http::Request<B>> for JobServiceServer<T>
+    where
+        T: JobService,
+        B: Body + std::marker::Send + 'static,
+        B::Error: Into<StdError> + std::marker::Send + 'static,
+    {
+        type Response = http::Response<tonic::body::BoxBody>;
+        type Error = std::convert::Infallible;
+        type Future = BoxFuture<Self::Response, Self::Error>;
+        fn poll_ready(
+            &mut self,
+            _cx: &mut Context<'_>,
+        ) -> Poll<std::result::Result<(), Self::Error>> {
+            Poll::Ready(Ok(()))
+        }
+        fn call(&mut self, req: http::Request<B>) -> Self::Future {
+            match req.uri().path() {
+                "/job_management.JobService/EnqueueJob" => {
+                    #[allow(non_camel_case_types)]
+                    struct EnqueueJobSvc<T: JobService>(pub Arc<T>);
+                    impl<
+                        T: JobService,
+                    > tonic::server::UnaryService<super::EnqueueRequest>
+                    for EnqueueJobSvc<T> {
+                        type Response = super::Job;
+                        type Future = BoxFuture<
+                            tonic::Response<Self::Response>,
+                            tonic::Status,
+                        >;
+                        fn call(
+                            &mut self,
+                            request: tonic::Request<super::EnqueueRequest>,
+                        ) -> Self::Future {
+                            let inner = Arc::clone(&self.0);
+                            let fut = async move {
+                                <T as JobService>::enqueue_job(&inner, request).await
+                            };
+                            Box::pin(fut)
+                        }
+                    }
+                    let accept_compression_encodings = self.accept_compression_encodings;
+                    let send_compression_encodings = self.send_compression_encodings;
+                    let max_decoding_message_size = self.max_decoding_message_size;
+                    let max_encoding_message_size = self.max_encoding_message_size;
+                    let inner = self.inner.clone();
+                    let fut = async move {
+                        let method = EnqueueJobSvc(inner);
+                        let codec = tonic::codec::ProstCodec::default();
+                        let mut grpc = tonic::server::Grpc::new(codec)
+                            .apply_compression_config(
+                                accept_compression_encodings,
+                                send_compression_encodings,
+                            )
+                            .apply_max_message_size_config(
+                                max_decoding_message_size,
+                                max_encoding_message_size,
+                            );
+                        let res = grpc.unary(method, req).await;
+                        Ok(res)
+                    };
+                    Box::pin(fut)
+                }
+                "/job_management.JobService/EnqueueBatch" => {
+                    #[allow(non_camel_case_types)]
+                    struct EnqueueBatchSvc<T: JobService>(pub Arc<T>);
+                    impl<
+                        T: JobService,
+                    > tonic::server::UnaryService<super::EnqueueBatchRequest>
+                    for EnqueueBatchSvc<T> {
+                        type Response = super::EnqueueBatchResponse;
+                        type Future = BoxFuture<
+                            tonic::Response<Self::Response>,
+                            tonic::Status,
+                        >;
+                        fn call(
+                            &mut self,
+                            request: tonic::Request<super::EnqueueBatchRequest>,
+                        ) -> Self::Future {
+                            let inner = Arc::clone(&self.0);
+                            let fut = async move {
+                                <T as JobService>::enqueue_batch(&inner, request).await
+                            };
+                            Box::pin(fut)
+                        }
+                    }
+                    let accept_compression_encodings = self.accept_compression_encodings;
+                    let send_compression_encodings = self.send_compression_encodings;
+                    let max_decoding_message_size = self.max_decoding_message_size;
+                    let max_encoding_message_size = self.max_encoding_message_size;
+                    let inner = self.inner.clone();
+                    let fut = async move {
+                        let method = EnqueueBatchSvc(inner);
+                        let codec = tonic::codec::ProstCodec::default();
+                        let mut grpc = tonic::server::Grpc::new(codec)
+                            .apply_compression_config(
+                                accept_compression_encodings,
+                                send_compression_encodings,
+                            )
+                            .apply_max_message_size_config(
+                                max_decoding_message_size,
+                                max_encoding_message_size,
+                            );
+                        let res = grpc.unary(method, req).await;
+                        Ok(res)
+                    };
+                    Box::pin(fut)
+                }
+                "/job_management.JobService/EnqueueStream" => {
+                    #[allow(non_camel_case_types)]
+                    struct EnqueueStreamSvc<T: JobService>(pub Arc<T>);
+                    impl<
+                        T: JobService,
+                    > tonic::server::StreamingService<super::EnqueueRequest>
+                    for EnqueueStreamSvc<T> {
+                        type Response = super::EnqueueResult;
+                        type ResponseStream = T::EnqueueStreamStream;
+                        type Future = BoxFuture<
+                            tonic::Response<Self::ResponseStream>,
+                            tonic::Status,
+                        >;
+                        fn call(
+                            &mut self,
+                            request: tonic::Request<
+                                tonic::Streaming<super::EnqueueRequest>,
+                            >,
+                        ) -> Self::Future {
+                            let inner = Arc::clone(&self.0);
+                            let fut = async move {
+                                <T as JobService>::enqueue_stream(&inner, request).await
+                            };
+                            Box::pin(fut)
+                        }
+                    }
+                    let accept_compression_encodings = self.accept_compression_encodings;
+                    let send_compression_encodings = self.send_compression_encodings;
+                    let max_decoding_message_size = self.max_decoding_message_size;
+                    let max_encoding_message_size = self.max_encoding_message_size;
+                    let inner = self.inner.clone();
+                    let fut = async move {
+                        let method = EnqueueStreamSvc(inner);
+                        let codec = tonic::codec::ProstCodec::default();
+                        let mut grpc = tonic::server::Grpc::new(codec)
+                            .apply_compression_config(
+                                accept_compression_encodings,
+                                send_compression_encodings,
+                            )
+                            .apply_max_message_size_config(
+                                max_decoding_message_size,
+                                max_encoding_message_size,
+                            );
+                        let res = grpc.streaming(method, req).await;
+                        Ok(res)
+                    };
+                    Box::pin(fut)
+                }
+                "/job_management.JobService/GetTask" => {
+                    #[allow(non_camel_case_types)]
+                    struct GetTaskSvc<T: JobService>(pub Arc<T>);
+                    impl<T: JobService> tonic::server::UnaryService<super::JobRequest>
+                    for GetTaskSvc<T> {
+                        type Response = super::JobResponse;
+                        type Future = BoxFuture<
+                            tonic::Response<Self::Response>,
+                            tonic::Status,
+                        >;
+                        fn call(
+                            &mut self,
+                            request: tonic::Request<super::JobRequest>,
+                        ) -> Self::Future {
+                            let inner = Arc::clone(&self.0);
+                            let fut = async move {
+                                <T as JobService>::get_task(&inner, request).await
+                            };
+                            Box::pin(fut)
+                        }
+                    }
+                    let accept_compression_encodings = self.accept_compression_encodings;
+                    let send_compression_encodings = self.send_compression_encodings;
+                    let max_decoding_message_size = self.max_decoding_message_size;
+                    let max_encoding_message_size = self.max_encoding_message_size;
+                    let inner = self.inner.clone();
+                    let fut = async move {
+                        let method = GetTaskSvc(inner);
+                        let codec = tonic::codec::ProstCodec::default();
+                        let mut grpc = tonic::server::Grpc::new(codec)
+                            .apply_compression_config(
+                                accept_compression_encodings,
+                                send_compression_encodings,
+                            )
+                            .apply_max_message_size_config(
+                                max_decoding_message_size,
+                                max_encoding_message_size,
+                            );
+                        let res = grpc.unary(method, req).await;
+                        Ok(res)
+                    };
+                    Box::pin(fut)
+                }
+                "/job_management.JobService/CancelJob" => {
+                    #[allow(non_camel_case_types)]
+                    struct CancelJobSvc<T: JobService>(pub Arc<T>);
+                    impl<
+                        T: JobService,
+                    > tonic::server::UnaryService<super::CancelJobRequest>
+                    for CancelJobSvc<T> {
+                        type Response = super::CancelJobResponse;
+                        type Future = BoxFuture<
+                            tonic::Response<Self::Response>,
+                            tonic::Status,
+                        >;
+                        fn call(
+                            &mut self,
+                            request: tonic::Request<super::CancelJobRequest>,
+                        ) -> Self::Future {
+                            let inner = Arc::clone(&self.0);
+                            let fut = async move {
+                                <T as JobService>::cancel_job(&inner, request).await
+                            };
+                            Box::pin(fut)
+                        }
+                    }
+                    let accept_compression_encodings = self.accept_compression_encodings;
+                    let send_compression_encodings = self.send_compression_encodings;
+                    let max_decoding_message_size = self.max_decoding_message_size;
+                    let max_encoding_message_size = self.max_encoding_message_size;
+                    let inner = self.inner.clone();
+                    let fut = async move {
+                        let method = CancelJobSvc(inner);
+                        let codec = tonic::codec::ProstCodec::default();
+                        let mut grpc = tonic::server::Grpc::new(codec)
+                            .apply_compression_config(
+                                accept_compression_encodings,
+                                send_compression_encodings,
+                            )
+                            .apply_max_message_size_config(
+                                max_decoding_message_size,
+                                max_encoding_message_size,
+                            );
+                        let res = grpc.unary(method, req).await;
+                        Ok(res)
+                    };
+                    Box::pin(fut)
+                }
+                "/job_management.JobService/GetResult" => {
+                    #[allow(non_camel_case_types)]
+                    struct GetResultSvc<T: JobService>(pub Arc<T>);
+                    impl<T: JobService> tonic::server::UnaryService<super::ResultRequest>
+                    for GetResultSvc<T> {
+                        type Response = super::ResultResponse;
+                        type Future = BoxFuture<
+                            tonic::Response<Self::Response>,
+                            tonic::Status,
+                        >;
+                        fn call(
+                            &mut self,
+                            request: tonic::Request<super::ResultRequest>,
+                        ) -> Self::Future {
+                            let inner = Arc::clone(&self.0);
+                            let fut = async move {
+                                <T as JobService>::get_result(&inner, request).await
+                            };
+                            Box::pin(fut)
+                        }
+                    }
+                    let accept_compression_encodings = self.accept_compression_encodings;
+                    let send_compression_encodings = self.send_compression_encodings;
+                    let max_decoding_message_size = self.max_decoding_message_size;
+                    let max_encoding_message_size = self.max_encoding_message_size;
+                    let inner = self.inner.clone();
+                    let fut = async move {
+                        let method = GetResultSvc(inner);
+                        let codec = tonic::codec::ProstCodec::default();
+                        let mut grpc = tonic::server::Grpc::new(codec)
+                            .apply_compression_config(
+                                accept_compression_encodings,
+                                send_compression_encodings,
+                            )
+                            .apply_max_message_size_config(
+                                max_decoding_message_size,
+                                max_encoding_message_size,
+                            );
+                        let res = grpc.unary(method, req).await;
+                        Ok(res)
+                    };
+                    Box::pin(fut)
+                }
+                "/job_management.JobService/GetJobStatus" => {
+                    #[allow(non_camel_case_types)]
+                    struct GetJobStatusSvc<T: JobService>(pub Arc<T>);
+                    impl<
+                        T: JobService,
+                    > tonic::server::UnaryService<super::JobStatusRequest>
+                    for GetJobStatusSvc<T> {
+                        type Response = super::JobStatusResponse;
+                        type Future = BoxFuture<
+                            tonic::Response<Self::Response>,
+                            tonic::Status,
+                        >;
+                        fn call(
+                            &mut self,
+                            request: tonic::Request<super::JobStatusRequest>,
+                        ) -> Self::Future {
+                            let inner = Arc::clone(&self.0);
+                            let fut = async move {
+                                <T as JobService>::get_job_status(&inner, request).await
+                            };
+                            Box::pin(fut)
+                        }
+                    }
+                    let accept_compression_encodings = self.accept_compression_encodings;
+                    let send_compression_encodings = self.send_compression_encodings;
+                    let max_decoding_message_size = self.max_decoding_message_size;
+                    let max_encoding_message_size = self.max_encoding_message_size;
+                    let inner = self.inner.clone();
+                    let fut = async move {
+                        let method = GetJobStatusSvc(inner);
+                        let codec = tonic::codec::ProstCodec::default();
+                        let mut grpc = tonic::server::Grpc::new(codec)
+                            .apply_compression_config(
+                                accept_compression_encodings,
+                                send_compression_encodings,
+                            )
+                            .apply_max_message_size_config(
+                                max_decoding_message_size,
+                                max_encoding_message_size,
+                            );
+                        let res = grpc.unary(method, req).await;
+                        Ok(res)
+                    };
+                    Box::pin(fut)
+                }
+                "/job_management.JobService/ReleaseJobs" => {
+                    #[allow(non_camel_case_types)]
+                    struct ReleaseJobsSvc<T: JobService>(pub Arc<T>);
+                    impl<
+                        T: JobService,
+                    > tonic::server::UnaryService<super::ReleaseJobsRequest>
+                    for ReleaseJobsSvc<T> {
+                        type Response = super::ReleaseJobsResponse;
+                        type Future = BoxFuture<
+                            tonic::Response<Self::Response>,
+                            tonic::Status,
+                        >;
+                        fn call(
+                            &mut self,
+                            request: tonic::Request<super::ReleaseJobsRequest>,
+                        ) -> Self::Future {
+                            let inner = Arc::clone(&self.0);
+                            let fut = async move {
+                                <T as JobService>::release_jobs(&inner, request).await
+                            };
+                            Box::pin(fut)
+                        }
+                    }
+                    let accept_compression_encodings = self.accept_compression_encodings;
+                    let send_compression_encodings = self.send_compression_encodings;
+                    let max_decoding_message_size = self.max_decoding_message_size;
+                    let max_encoding_message_size = self.max_encoding_message_size;
+                    let inner = self.inner.clone();
+                    let fut = async move {
+                        let method = ReleaseJobsSvc(inner);
+                        let codec = tonic::codec::ProstCodec::default();
+                        let mut grpc = tonic::server::Grpc::new(codec)
+                            .apply_compression_config(
+                                accept_compression_encodings,
+                                send_compression_encodings,
+                            )
+                            .apply_max_message_size_config(
+                                max_decoding_message_size,
+                                max_encoding_message_size,
+                            );
+                        let res = grpc.unary(method, req).await;
+                        Ok(res)
+                    };
+                    Box::pin(fut)
+                }
+                _ => {
+                    Box::pin(async move {
+                        let mut response = http::Response::new(empty_body());
+                        let headers = response.headers_mut();
+                        headers
+                            .insert(
+                                tonic::Status::GRPC_STATUS,
+                                (tonic::Code::Unimplemented as i32).into(),
+                            );
+                        headers
+                            .insert(
+                                http::header::CONTENT_TYPE,
+                                tonic::metadata::GRPC_CONTENT_TYPE,
+                            );
+                        Ok(response)
+                    })
+                }
+            }
+        }
+    }
+    impl<T> Clone for JobServiceServer<T> {
+        fn clone(&self) -> Self {
+            let inner = self.inner.clone();
+            Self {
+                inner,
+                accept_compression_encodings: self.accept_compression_encodings,
+                send_compression_encodings: self.send_compression_encodings,
+                max_decoding_message_size: self.max_decoding_message_size,
+                max_encoding_message_size: self.max_encoding_message_size,
+            }
+        }
+    }
+    /// Generated gRPC service name
+    pub const SERVICE_NAME: &str = "job_management.JobService";
+    impl<T> tonic::server::NamedService for JobServiceServer<T> {
+        const NAME: &'static str = SERVICE_NAME;
+    }
+}
+/// Generated server implementations.
+pub mod long_polling_service_server {
+    #![allow(
+        unused_variables,
+        dead_code,
+        missing_docs,
+        clippy::wildcard_imports,
+        clippy::let_unit_value,
+    )]
+    use tonic::codegen::*;
+    /// Generated trait containing gRPC methods that should be implemented for use with LongPollingServiceServer.
+    #[async_trait]
+    pub trait LongPollingService: std::marker::Send + std::marker::Sync + 'static {
+        async fn poll(
+            &self,
+            request: tonic::Request<super::PollJobRequest>,
+        ) -> std::result::Result<tonic::Response<super::PollJobResponse>, tonic::Status>;
+        async fn heartbeat(
+            &self,
+            request: tonic::Request<super::HeartbeatRequest>,
+        ) -> std::result::Result<
+            tonic::Response<super::HeartbeatResponse>,
+            tonic::Status,
+        >;
+        async fn ack(
+            &self,
+            request: tonic::Request<super::AckRequest>,
+        ) -> std::result::Result<tonic::Response<super::AckResponse>, tonic::Status>;
+        async fn nack(
+            &self,
+            request: tonic::Request<super::NackRequest>,
+        ) -> std::result::Result<tonic::Response<super::AckResponse>, tonic::Status>;
+        async fn release(
+            &self,
+            request: tonic::Request<super::NackRequest>,
+        ) -> std::result::Result<tonic::Response<super::AckResponse>, tonic::Status>;
+    }
+    #[derive(Debug)]
+    pub struct LongPollingServiceServer<T> {
+        inner: Arc<T>,
+        accept_compression_encodings: EnabledCompressionEncodings,
+        send_compression_encodings: EnabledCompressionEncodings,
+        max_decoding_message_size: Option<usize>,
+        max_encoding_message_size: Option<usize>,
+    }
+    impl<T> LongPollingServiceServer<T> {
+        pub fn new(inner: T) -> Self {
+            Self::from_arc(Arc::new(inner))
+        }
+        pub fn from_arc(inner: Arc<T>) -> Self {
+            Self {
+                inner,
+                accept_compression_encodings: Default::default(),
+                send_compression_encodings: Default::default(),
+                max_decoding_message_size: None,
+                max_encoding_message_size: None,
+            }
+        }
+        pub fn with_interceptor<F>(
+            inner: T,
+            interceptor: F,
+        ) -> InterceptedService<Self, F>
+        where
+            F: tonic::service::Interceptor,
+        {
+            InterceptedService::new(Self::new(inner), interceptor)
+        }
+        /// Enable decompressing requests with the given encoding.
+        #[must_use]
+        pub fn accept_compressed(mut self, encoding: CompressionEncoding) -> Self {
+            self.accept_compression_encodings.enable(encoding);
+            self
+        }
+        /// Compress responses with the given encoding, if the client supports it.
+        #[must_use]
+        pub fn send_compressed(mut self, encoding: CompressionEncoding) -> Self {
+            self.send_compression_encodings.enable(encoding);
+            self
+        }
+        /// Limits the maximum size of a decoded message.
+        ///
+        /// Default: `4MB`
+        #[must_use]
+        pub fn max_decoding_message_size(mut self, limit: usize) -> Self {
+            self.max_decoding_message_size = Some(limit);
+            self
+        }
+        /// Limits the maximum size of an encoded message.
+        ///
+        /// Default: `usize::MAX`
+        #[must_use]
+        pub fn max_encoding_message_size(mut self, limit: usize) -> Self {
+            self.max_encoding_message_size = Some(limit);
+            self
+        }
+    }
+    impl<T, B> tonic::codegen::Service<http::Request<B>> for LongPollingServiceServer<T>
+    where
+        T: LongPollingService,
+        B: Body + std::marker::Send + 'static,
+        B::Error: Into<StdError> + std::marker::Send + 'static,
+    {
+        type Response = http::Response<tonic::body::BoxBody>;
+        type Error = std::convert::Infallible;
+        type Future = BoxFuture<Self::Response, Self::Error>;
+        fn poll_ready(
+            &mut self,
+            _cx: &mut Context<'_>,
+        ) -> Poll<std::result::Result<(), Self::Error>> {
+            Poll::Ready(Ok(()))
+        }
+        fn call(&mut self, req: http::Request<B>) -> Self::Future {
+            match req.uri().path() {
+                "/job_management.LongPollingService/Poll" => {
+                    #[allow(non_camel_case_types)]
+                    struct PollSvc<T: LongPollingService>(pub Arc<T>);
+                    impl<
+                        T: LongPollingService,
+                    > tonic::server::UnaryService<super::PollJobRequest> for PollSvc<T> {
+                        type Response = super::PollJobResponse;
+                        type Future = BoxFuture<
+                            tonic::Response<Self::Response>,
+                            tonic::Status,
+                        >;
+                        fn call(
+                            &mut self,
+                            request: tonic::Request<super::PollJobRequest>,
+                        ) -> Self::Future {
+                            let inner = Arc::clone(&self.0);
+                            let fut = async move {
+                                <T as LongPollingService>::poll(&inner, request).await
+                            };
+                            Box::pin(fut)
+                        }
+                    }
+                    let accept_compression_encodings = self.accept_compression_encodings;
+                    let send_compression_encodings = self.send_compression_encodings;
+                    let max_decoding_message_size = self.max_decoding_message_size;
+                    let max_encoding_message_size = self.max_encoding_message_size;
+                    let inner = self.inner.clone();
+                    let fut = async move {
+                        let method = PollSvc(inner);
+                        let codec = tonic::codec::ProstCodec::default();
+                        let mut grpc = tonic::server::Grpc::new(codec)
+                            .apply_compression_config(
+                                accept_compression_encodings,
+                                send_compression_encodings,
+                            )
+                            .apply_max_message_size_config(
+                                max_decoding_message_size,
+                                max_encoding_message_size,
+                            );
+                        let res = grpc.unary(method, req).await;
+                        Ok(res)
+                    };
+                    Box::pin(fut)
+                }
+                "/job_management.LongPollingService/Heartbeat" => {
+                    #[allow(non_camel_case_types)]
+                    struct HeartbeatSvc<T: LongPollingService>(pub Arc<T>);
+                    impl<
+                        T: LongPollingService,
+                    > tonic::server::UnaryService<super::HeartbeatRequest>
+                    for HeartbeatSvc<T> {
+                        type Response = super::HeartbeatResponse;
+                        type Future = BoxFuture<
+                            tonic::Response<Self::Response>,
+                            tonic::Status,
+                        >;
+                        fn call(
+                            &mut self,
+                            request: tonic::Request<super::HeartbeatRequest>,
+                        ) -> Self::Future {
+                            let inner = Arc::clone(&self.0);
+                            let fut = async move {
+                                <T as LongPollingService>::heartbeat(&inner, request).await
+                            };
+                            Box::pin(fut)
+                        }
+                    }
+                    let accept_compression_encodings = self.accept_compression_encodings;
+                    let send_compression_encodings = self.send_compression_encodings;
+                    let max_decoding_message_size = self.max_decoding_message_size;
+                    let max_encoding_message_size = self.max_encoding_message_size;
+                    let inner = self.inner.clone();
+                    let fut = async move {
+                        let method = HeartbeatSvc(inner);
+                        let codec = tonic::codec::ProstCodec::default();
+                        let mut grpc = tonic::server::Grpc::new(codec)
+                            .apply_compression_config(
+                                accept_compression_encodings,
+                                send_compression_encodings,
+                            )
+                            .apply_max_message_size_config(
+                                max_decoding_message_size,
+                                max_encoding_message_size,
+                            );
+                        let res = grpc.unary(method, req).await;
+                        Ok(res)
+                    };
+                    Box::pin(fut)
+                }
+                "/job_management.LongPollingService/Ack" => {
                     #[allow(non_camel_case_types)]
-                    struct EnqueueJobSvc<T: JobService>(pub Arc<T>);
+                    struct AckSvc<T: LongPollingService>(pub Arc<T>);
                     impl<
-                        T: JobService,
-                    > tonic::server::UnaryService<super::EnqueueRequest>
-                    for EnqueueJobSvc<T> {
-                        type Response = super::Job;
+                        T: LongPollingService,
+                    > tonic::server::UnaryService<super::AckRequest> for AckSvc<T> {
+                        type Response = super::AckResponse;
                         type Future = BoxFuture<
                             tonic::Response<Self::Response>,
                             tonic::Status,
                         >;
                         fn call(
                             &mut self,
-                            request: tonic::Request<super::EnqueueRequest>,
+                            request: tonic::Request<super::AckRequest>,
                         ) -> Self::Future {
                             let inner = Arc::clone(&self.0);
                             let fut = async move {
-                                <T as JobService>::enqueue_job(&inner, request).await
+                                <T as LongPollingService>::ack(&inner, request).await
                             };
                             Box::pin(fut)
                         }
@@ -725,7 +2020,7 @@ pub mod job_service_server {
                     let max_encoding_message_size = self.max_encoding_message_size;
                     let inner = self.inner.clone();
                     let fut = async move {
-                        let method = EnqueueJobSvc(inner);
+                        let method = AckSvc(inner);
                         let codec = tonic::codec::ProstCodec::default();
                         let mut grpc = tonic::server::Grpc::new(codec)
                             .apply_compression_config(
@@ -741,23 +2036,24 @@ pub mod job_service_server {
                     };
                     Box::pin(fut)
                 }
-                "/job_management.JobService/GetTask" => {
+                "/job_management.LongPollingService/Nack" => {
                     #[allow(non_camel_case_types)]
-                    struct GetTaskSvc<T: JobService>(pub Arc<T>);
-                    impl<T: JobService> tonic::server::UnaryService<super::JobRequest>
-                    for GetTaskSvc<T> {
-                        type Response = super::JobResponse;
+                    struct NackSvc<T: LongPollingService>(pub Arc<T>);
+                    impl<
+                        T: LongPollingService,
+                    > tonic::server::UnaryService<super::NackRequest> for NackSvc<T> {
+                        type Response = super::AckResponse;
                         type Future = BoxFuture<
                             tonic::Response<Self::Response>,
                             tonic::Status,
                         >;
                         fn call(
                             &mut self,
-                            request: tonic::Request<super::JobRequest>,
+                            request: tonic::Request<super::NackRequest>,
                         ) -> Self::Future {
                             let inner = Arc::clone(&self.0);
                             let fut = async move {
-                                <T as JobService>::get_task(&inner, request).await
+                                <T as LongPollingService>::nack(&inner, request).await
                             };
                             Box::pin(fut)
                         }
@@ -768,7 +2064,51 @@ pub mod job_service_server {
                     let max_encoding_message_size = self.max_encoding_message_size;
                     let inner = self.inner.clone();
                     let fut = async move {
-                        let method = GetTaskSvc(inner);
+                        let method = NackSvc(inner);
+                        let codec = tonic::codec::ProstCodec::default();
+                        let mut grpc = tonic::server::Grpc::new(codec)
+                            .apply_compression_config(
+                                accept_compression_encodings,
+                                send_compression_encodings,
+                            )
+                            .apply_max_message_size_config(
+                                max_decoding_message_size,
+                                max_encoding_message_size,
+                            );
+                        let res = grpc.unary(method, req).await;
+                        Ok(res)
+                    };
+                    Box::pin(fut)
+                }
+                "/job_management.LongPollingService/Release" => {
+                    #[allow(non_camel_case_types)]
+                    struct ReleaseSvc<T: LongPollingService>(pub Arc<T>);
+                    impl<
+                        T: LongPollingService,
+                    > tonic::server::UnaryService<super::NackRequest> for ReleaseSvc<T> {
+                        type Response = super::AckResponse;
+                        type Future = BoxFuture<
+                            tonic::Response<Self::Response>,
+                            tonic::Status,
+                        >;
+                        fn call(
+                            &mut self,
+                            request: tonic::Request<super::NackRequest>,
+                        ) -> Self::Future {
+                            let inner = Arc::clone(&self.0);
+                            let fut = async move {
+                                <T as LongPollingService>::release(&inner, request).await
+                            };
+                            Box::pin(fut)
+                        }
+                    }
+                    let accept_compression_encodings = self.accept_compression_encodings;
+                    let send_compression_encodings = self.send_compression_encodings;
+                    let max_decoding_message_size = self.max_decoding_message_size;
+                    let max_encoding_message_size = self.max_encoding_message_size;
+                    let inner = self.inner.clone();
+                    let fut = async move {
+                        let method = ReleaseSvc(inner);
                         let codec = tonic::codec::ProstCodec::default();
                         let mut grpc = tonic::server::Grpc::new(codec)
                             .apply_compression_config(
@@ -804,7 +2144,7 @@ pub mod job_service_server {
             }
         }
     }
-    impl<T> Clone for JobServiceServer<T> {
+    impl<T> Clone for LongPollingServiceServer<T> {
         fn clone(&self) -> Self {
             let inner = self.inner.clone();
             Self {
@@ -817,13 +2157,13 @@ pub mod job_service_server {
         }
     }
     /// Generated gRPC service name
-    pub const SERVICE_NAME: &str = "job_management.JobService";
-    impl<T> tonic::server::NamedService for JobServiceServer<T> {
+    pub const SERVICE_NAME: &str = "job_management.LongPollingService";
+    impl<T> tonic::server::NamedService for LongPollingServiceServer<T> {
         const NAME: &'static str = SERVICE_NAME;
     }
 }
 /// Generated server implementations.
-pub mod long_polling_service_server {
+pub mod paxos_service_server {
     #![allow(
         unused_variables,
         dead_code,
@@ -832,23 +2172,31 @@ pub mod long_polling_service_server {
         clippy::let_unit_value,
     )]
     use tonic::codegen::*;
-    /// Generated trait containing gRPC methods that should be implemented for use with LongPollingServiceServer.
+    /// Generated trait containing gRPC methods that should be implemented for use with PaxosServiceServer.
     #[async_trait]
-    pub trait LongPollingService: std::marker::Send + std::marker::Sync + 'static {
-        async fn poll(
+    pub trait PaxosService: std::marker::Send + std::marker::Sync + 'static {
+        async fn prepare(
             &self,
-            request: tonic::Request<super::PollJobRequest>,
-        ) -> std::result::Result<tonic::Response<super::PollJobResponse>, tonic::Status>;
+            request: tonic::Request<super::PaxosPrepare>,
+        ) -> std::result::Result<tonic::Response<super::PaxosPromise>, tonic::Status>;
+        async fn accept(
+            &self,
+            request: tonic::Request<super::PaxosAccept>,
+        ) -> std::result::Result<tonic::Response<super::PaxosAck>, tonic::Status>;
+        async fn cancel(
+            &self,
+            request: tonic::Request<super::PaxosCancel>,
+        ) -> std::result::Result<tonic::Response<super::PaxosAck>, tonic::Status>;
     }
     #[derive(Debug)]
-    pub struct LongPollingServiceServer<T> {
+    pub struct PaxosServiceServer<T> {
         inner: Arc<T>,
         accept_compression_encodings: EnabledCompressionEncodings,
         send_compression_encodings: EnabledCompressionEncodings,
         max_decoding_message_size: Option<usize>,
         max_encoding_message_size: Option<usize>,
     }
-    impl<T> LongPollingServiceServer<T> {
+    impl<T> PaxosServiceServer<T> {
         pub fn new(inner: T) -> Self {
             Self::from_arc(Arc::new(inner))
         }
@@ -899,9 +2247,9 @@ pub mod long_polling_service_server {
             self
         }
     }
-    impl<T, B> tonic::codegen::Service<http::Request<B>> for LongPollingServiceServer<T>
+    impl<T, B> tonic::codegen::Service<http::Request<B>> for PaxosServiceServer<T>
     where
-        T: LongPollingService,
+        T: PaxosService,
         B: Body + std::marker::Send + 'static,
         B::Error: Into<StdError> + std::marker::Send + 'static,
     {
@@ -916,24 +2264,25 @@ pub mod long_polling_service_server {
         }
         fn call(&mut self, req: http::Request<B>) -> Self::Future {
             match req.uri().path() {
-                "/job_management.LongPollingService/Poll" => {
+                "/job_management.PaxosService/Prepare" => {
                     #[allow(non_camel_case_types)]
-                    struct PollSvc<T: LongPollingService>(pub Arc<T>);
+                    struct PrepareSvc<T: PaxosService>(pub Arc<T>);
                     impl<
-                        T: LongPollingService,
-                    > tonic::server::UnaryService<super::PollJobRequest> for PollSvc<T> {
-                        type Response = super::PollJobResponse;
+                        T: PaxosService,
+                    > tonic::server::UnaryService<super::PaxosPrepare>
+                    for PrepareSvc<T> {
+                        type Response = super::PaxosPromise;
                         type Future = BoxFuture<
                             tonic::Response<Self::Response>,
                             tonic::Status,
                         >;
                         fn call(
                             &mut self,
-                            request: tonic::Request<super::PollJobRequest>,
+                            request: tonic::Request<super::PaxosPrepare>,
                         ) -> Self::Future {
                             let inner = Arc::clone(&self.0);
                             let fut = async move {
-                                <T as LongPollingService>::poll(&inner, request).await
+                                <T as PaxosService>::prepare(&inner, request).await
                             };
                             Box::pin(fut)
                         }
@@ -944,7 +2293,93 @@ pub mod long_polling_service_server {
                     let max_encoding_message_size = self.max_encoding_message_size;
                     let inner = self.inner.clone();
                     let fut = async move {
-                        let method = PollSvc(inner);
+                        let method = PrepareSvc(inner);
+                        let codec = tonic::codec::ProstCodec::default();
+                        let mut grpc = tonic::server::Grpc::new(codec)
+                            .apply_compression_config(
+                                accept_compression_encodings,
+                                send_compression_encodings,
+                            )
+                            .apply_max_message_size_config(
+                                max_decoding_message_size,
+                                max_encoding_message_size,
+                            );
+                        let res = grpc.unary(method, req).await;
+                        Ok(res)
+                    };
+                    Box::pin(fut)
+                }
+                "/job_management.PaxosService/Accept" => {
+                    #[allow(non_camel_case_types)]
+                    struct AcceptSvc<T: PaxosService>(pub Arc<T>);
+                    impl<T: PaxosService> tonic::server::UnaryService<super::PaxosAccept>
+                    for AcceptSvc<T> {
+                        type Response = super::PaxosAck;
+                        type Future = BoxFuture<
+                            tonic::Response<Self::Response>,
+                            tonic::Status,
+                        >;
+                        fn call(
+                            &mut self,
+                            request: tonic::Request<super::PaxosAccept>,
+                        ) -> Self::Future {
+                            let inner = Arc::clone(&self.0);
+                            let fut = async move {
+                                <T as PaxosService>::accept(&inner, request).await
+                            };
+                            Box::pin(fut)
+                        }
+                    }
+                    let accept_compression_encodings = self.accept_compression_encodings;
+                    let send_compression_encodings = self.send_compression_encodings;
+                    let max_decoding_message_size = self.max_decoding_message_size;
+                    let max_encoding_message_size = self.max_encoding_message_size;
+                    let inner = self.inner.clone();
+                    let fut = async move {
+                        let method = AcceptSvc(inner);
+                        let codec = tonic::codec::ProstCodec::default();
+                        let mut grpc = tonic::server::Grpc::new(codec)
+                            .apply_compression_config(
+                                accept_compression_encodings,
+                                send_compression_encodings,
+                            )
+                            .apply_max_message_size_config(
+                                max_decoding_message_size,
+                                max_encoding_message_size,
+                            );
+                        let res = grpc.unary(method, req).await;
+                        Ok(res)
+                    };
+                    Box::pin(fut)
+                }
+                "/job_management.PaxosService/Cancel" => {
+                    #[allow(non_camel_case_types)]
+                    struct CancelSvc<T: PaxosService>(pub Arc<T>);
+                    impl<T: PaxosService> tonic::server::UnaryService<super::PaxosCancel>
+                    for CancelSvc<T> {
+                        type Response = super::PaxosAck;
+                        type Future = BoxFuture<
+                            tonic::Response<Self::Response>,
+                            tonic::Status,
+                        >;
+                        fn call(
+                            &mut self,
+                            request: tonic::Request<super::PaxosCancel>,
+                        ) -> Self::Future {
+                            let inner = Arc::clone(&self.0);
+                            let fut = async move {
+                                <T as PaxosService>::cancel(&inner, request).await
+                            };
+                            Box::pin(fut)
+                        }
+                    }
+                    let accept_compression_encodings = self.accept_compression_encodings;
+                    let send_compression_encodings = self.send_compression_encodings;
+                    let max_decoding_message_size = self.max_decoding_message_size;
+                    let max_encoding_message_size = self.max_encoding_message_size;
+                    let inner = self.inner.clone();
+                    let fut = async move {
+                        let method = CancelSvc(inner);
                         let codec = tonic::codec::ProstCodec::default();
                         let mut grpc = tonic::server::Grpc::new(codec)
                             .apply_compression_config(
@@ -980,7 +2415,7 @@ pub mod long_polling_service_server {
             }
         }
     }
-    impl<T> Clone for LongPollingServiceServer<T> {
+    impl<T> Clone for PaxosServiceServer<T> {
         fn clone(&self) -> Self {
             let inner = self.inner.clone();
             Self {
@@ -993,13 +2428,13 @@ pub mod long_polling_service_server {
         }
     }
     /// Generated gRPC service name
-    pub const SERVICE_NAME: &str = "job_management.LongPollingService";
-    impl<T> tonic::server::NamedService for LongPollingServiceServer<T> {
+    pub const SERVICE_NAME: &str = "job_management.PaxosService";
+    impl<T> tonic::server::NamedService for PaxosServiceServer<T> {
         const NAME: &'static str = SERVICE_NAME;
     }
 }
 /// Generated server implementations.
-pub mod paxos_service_server {
+pub mod consumer_group_service_server {
     #![allow(
         unused_variables,
         dead_code,
@@ -1008,27 +2443,28 @@ pub mod paxos_service_server {
         clippy::let_unit_value,
     )]
     use tonic::codegen::*;
-    /// Generated trait containing gRPC methods that should be implemented for use with PaxosServiceServer.
+    /// Generated trait containing gRPC methods that should be implemented for use with ConsumerGroupServiceServer.
     #[async_trait]
-    pub trait PaxosService: std::marker::Send + std::marker::Sync + 'static {
-        async fn prepare(
+    pub trait ConsumerGroupService: std::marker::Send + std::marker::Sync + 'static {
+        async fn join_group(
             &self,
-            request: tonic::Request<super::PaxosPrepare>,
-        ) -> std::result::Result<tonic::Response<super::PaxosPromise>, tonic::Status>;
-        async fn accept(
+            request: tonic::Request<super::JoinGroupRequest>,
+        ) -> std::result::Result<tonic::Response<super::GroupAssignment>, tonic::Status>;
+        async fn leave_group(
             &self,
-            request: tonic::Request<super::PaxosAccept>,
-        ) -> std::result::Result<tonic::Response<super::PaxosAck>, tonic::Status>;
+            request: tonic::Request<super::LeaveGroupRequest>,
+        ) -> std::result::Result<tonic::Response<super::GroupAssignment>, tonic::Status>;
     }
+    /// Tracks consumer group membership on the leader and splits the followers between the members
     #[derive(Debug)]
-    pub struct PaxosServiceServer<T> {
+    pub struct ConsumerGroupServiceServer<T> {
         inner: Arc<T>,
         accept_compression_encodings: EnabledCompressionEncodings,
         send_compression_encodings: EnabledCompressionEncodings,
         max_decoding_message_size: Option<usize>,
         max_encoding_message_size: Option<usize>,
     }
-    impl<T> PaxosServiceServer<T> {
+    impl<T> ConsumerGroupServiceServer<T> {
         pub fn new(inner: T) -> Self {
             Self::from_arc(Arc::new(inner))
         }
@@ -1079,9 +2515,10 @@ pub mod paxos_service_server {
             self
         }
     }
-    impl<T, B> tonic::codegen::Service<http::Request<B>> for PaxosServiceServer<T>
+    impl<T, B> tonic::codegen::Service<http::Request<B>>
+    for ConsumerGroupServiceServer<T>
     where
-        T: PaxosService,
+        T: ConsumerGroupService,
         B: Body + std::marker::Send + 'static,
         B::Error: Into<StdError> + std::marker::Send + 'static,
     {
@@ -1096,25 +2533,26 @@ pub mod paxos_service_server {
         }
         fn call(&mut self, req: http::Request<B>) -> Self::Future {
             match req.uri().path() {
-                "/job_management.PaxosService/Prepare" => {
+                "/job_management.ConsumerGroupService/JoinGroup" => {
                     #[allow(non_camel_case_types)]
-                    struct PrepareSvc<T: PaxosService>(pub Arc<T>);
+                    struct JoinGroupSvc<T: ConsumerGroupService>(pub Arc<T>);
                     impl<
-                        T: PaxosService,
-                    > tonic::server::UnaryService<super::PaxosPrepare>
-                    for PrepareSvc<T> {
-                        type Response = super::PaxosPromise;
+                        T: ConsumerGroupService,
+                    > tonic::server::UnaryService<super::JoinGroupRequest>
+                    for JoinGroupSvc<T> {
+                        type Response = super::GroupAssignment;
                         type Future = BoxFuture<
                             tonic::Response<Self::Response>,
                             tonic::Status,
                         >;
                         fn call(
                             &mut self,
-                            request: tonic::Request<super::PaxosPrepare>,
+                            request: tonic::Request<super::JoinGroupRequest>,
                         ) -> Self::Future {
                             let inner = Arc::clone(&self.0);
                             let fut = async move {
-                                <T as PaxosService>::prepare(&inner, request).await
+                                <T as ConsumerGroupService>::join_group(&inner, request)
+                                    .await
                             };
                             Box::pin(fut)
                         }
@@ -1125,7 +2563,7 @@ pub mod paxos_service_server {
                     let max_encoding_message_size = self.max_encoding_message_size;
                     let inner = self.inner.clone();
                     let fut = async move {
-                        let method = PrepareSvc(inner);
+                        let method = JoinGroupSvc(inner);
                         let codec = tonic::codec::ProstCodec::default();
                         let mut grpc = tonic::server::Grpc::new(codec)
                             .apply_compression_config(
@@ -1141,23 +2579,26 @@ pub mod paxos_service_server {
                     };
                     Box::pin(fut)
                 }
-                "/job_management.PaxosService/Accept" => {
+                "/job_management.ConsumerGroupService/LeaveGroup" => {
                     #[allow(non_camel_case_types)]
-                    struct AcceptSvc<T: PaxosService>(pub Arc<T>);
-                    impl<T: PaxosService> tonic::server::UnaryService<super::PaxosAccept>
-                    for AcceptSvc<T> {
-                        type Response = super::PaxosAck;
+                    struct LeaveGroupSvc<T: ConsumerGroupService>(pub Arc<T>);
+                    impl<
+                        T: ConsumerGroupService,
+                    > tonic::server::UnaryService<super::LeaveGroupRequest>
+                    for LeaveGroupSvc<T> {
+                        type Response = super::GroupAssignment;
                         type Future = BoxFuture<
                             tonic::Response<Self::Response>,
                             tonic::Status,
                         >;
                         fn call(
                             &mut self,
-                            request: tonic::Request<super::PaxosAccept>,
+                            request: tonic::Request<super::LeaveGroupRequest>,
                         ) -> Self::Future {
                             let inner = Arc::clone(&self.0);
                             let fut = async move {
-                                <T as PaxosService>::accept(&inner, request).await
+                                <T as ConsumerGroupService>::leave_group(&inner, request)
+                                    .await
                             };
                             Box::pin(fut)
                         }
@@ -1168,7 +2609,7 @@ pub mod paxos_service_server {
                     let max_encoding_message_size = self.max_encoding_message_size;
                     let inner = self.inner.clone();
                     let fut = async move {
-                        let method = AcceptSvc(inner);
+                        let method = LeaveGroupSvc(inner);
                         let codec = tonic::codec::ProstCodec::default();
                         let mut grpc = tonic::server::Grpc::new(codec)
                             .apply_compression_config(
@@ -1204,7 +2645,7 @@ pub mod paxos_service_server {
             }
         }
     }
-    impl<T> Clone for PaxosServiceServer<T> {
+    impl<T> Clone for ConsumerGroupServiceServer<T> {
         fn clone(&self) -> Self {
             let inner = self.inner.clone();
             Self {
@@ -1217,8 +2658,8 @@ pub mod paxos_service_server {
         }
     }
     /// Generated gRPC service name
-    pub const SERVICE_NAME: &str = "job_management.PaxosService";
-    impl<T> tonic::server::NamedService for PaxosServiceServer<T> {
+    pub const SERVICE_NAME: &str = "job_management.ConsumerGroupService";
+    impl<T> tonic::server::NamedService for ConsumerGroupServiceServer<T> {
         const NAME: &'static str = SERVICE_NAME;
     }
 }
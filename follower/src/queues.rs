@@ -0,0 +1,1051 @@
+use crate::jobs::QueuedJob;
+use crate::tenants::{self, tenant_name, TenantConfig};
+use chrono::Utc;
+use log::error;
+use priority_queue::{
+    EarliestDeadline, ExponentialAging, HeapNode, LinearAging, PriorityBand, PriorityQueue,
+    SchedulingPolicy, StrictPriority, WeightedBands,
+};
+use serde::{Deserialize, Serialize};
+use std::cmp::Ordering;
+use std::collections::{HashMap, VecDeque};
+use std::sync::Arc;
+
+/// The queue jobs are added to when no queue name is provided.
+pub const DEFAULT_QUEUE: &str = "default";
+
+/// Resolves the queue name of a request, mapping an empty name to the default queue.
+pub fn queue_name(name: &str) -> &str {
+    if name.is_empty() {
+        DEFAULT_QUEUE
+    } else {
+        name
+    }
+}
+
+/// QueueConfig holds the settings of a single named queue.
+/// `aging_factor`: The aging factor used when calculating the effective priority of its jobs
+/// under the linear policy.
+/// `policy`: The scheduling policy deciding the order its jobs are handed out in.
+/// `max_attempts`: The number of attempts a job gets before it is dead lettered.
+/// `max_size`: The maximum number of jobs the queue may hold on this node.
+/// `visibility_timeout`: The number of seconds a consumer may hold a job without sending a
+/// heartbeat before it is requeued.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct QueueConfig {
+    pub aging_factor: f32,
+    pub policy: PolicyConfig,
+    pub max_attempts: i32,
+    pub max_size: usize,
+    pub visibility_timeout: i32,
+}
+
+impl Default for QueueConfig {
+    fn default() -> Self {
+        QueueConfig {
+            aging_factor: 0.5,
+            policy: PolicyConfig::Linear,
+            max_attempts: 3,
+            max_size: 100_000,
+            visibility_timeout: 30,
+        }
+    }
+}
+
+/// PolicyConfig selects the scheduling policy of a queue, tagged by `type` in `queues.json`.
+/// `Linear`: Lowers the effective priority by `aging_factor` per tick.
+/// `Exponential`: Halves the effective priority every `half_life` ticks.
+/// `Strict`: Strict priority order, except that jobs which waited `max_wait` ticks go first.
+/// `WeightedBands`: Picks between priority bands at random in proportion to their weights.
+/// `EarliestDeadline`: Earliest deadline first, jobs without one are due `slack` milliseconds per
+/// unit of priority after they are enqueued.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum PolicyConfig {
+    Linear,
+    Exponential { half_life: f64 },
+    Strict { max_wait: u64 },
+    WeightedBands { bands: Vec<BandConfig> },
+    EarliestDeadline { slack: u64 },
+}
+
+/// BandConfig is a band of priorities under the weighted bands policy.
+/// `max_priority`: The largest priority in the band, the band starts after the previous one.
+/// `weight`: The share of dequeues the band receives while it has jobs.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct BandConfig {
+    pub max_priority: u32,
+    pub weight: f32,
+}
+
+impl QueueConfig {
+    /// Checks that the settings of the scheduling policy describe a usable order. A half life
+    /// that is not positive would make every rank infinite or NaN, and bands that all have a
+    /// weight of 0 or overlap would never serve some priorities.
+    ///
+    /// # Returns
+    /// A Result object that is either Ok(()) or Err(String) explaining what is wrong
+    pub fn validate(&self) -> Result<(), String> {
+        match &self.policy {
+            PolicyConfig::Exponential { half_life }
+                if !(half_life.is_finite() && *half_life > 0.0) =>
+            {
+                Err(format!(
+                    "half_life must be greater than 0, got {}",
+                    half_life
+                ))
+            }
+            PolicyConfig::WeightedBands { bands } => {
+                if bands.is_empty() {
+                    return Err("weighted_bands needs at least one band".to_string());
+                }
+                if let Some(band) = bands
+                    .iter()
+                    .find(|band| !(band.weight.is_finite() && band.weight >= 0.0))
+                {
+                    return Err(format!(
+                        "band weights must be 0 or more, got {} for the band up to priority {}",
+                        band.weight, band.max_priority
+                    ));
+                }
+                if bands.iter().all(|band| band.weight == 0.0) {
+                    return Err("at least one band must have a weight greater than 0".to_string());
+                }
+                if let Some(pair) = bands
+                    .windows(2)
+                    .find(|pair| pair[0].max_priority >= pair[1].max_priority)
+                {
+                    return Err(format!(
+                        "band max_priority must be increasing, got {} after {}",
+                        pair[1].max_priority, pair[0].max_priority
+                    ));
+                }
+                Ok(())
+            }
+            _ => Ok(()),
+        }
+    }
+
+    /// Builds the scheduling policy the queue's jobs are ordered by.
+    pub fn scheduling_policy(&self) -> Arc<dyn SchedulingPolicy> {
+        match &self.policy {
+            PolicyConfig::Linear => Arc::new(LinearAging::new(self.aging_factor)),
+            PolicyConfig::Exponential { half_life } => Arc::new(ExponentialAging::new(*half_life)),
+            PolicyConfig::Strict { max_wait } => Arc::new(StrictPriority::new(*max_wait)),
+            PolicyConfig::WeightedBands { bands } => Arc::new(WeightedBands::new(
+                bands
+                    .iter()
+                    .map(|band| PriorityBand {
+                        max_priority: band.max_priority,
+                        weight: band.weight,
+                    })
+                    .collect(),
+            )),
+            PolicyConfig::EarliestDeadline { slack } => Arc::new(EarliestDeadline::new(*slack)),
+        }
+    }
+}
+
+/// A tenant's place in a replayed stride schedule: its name, pass and stride, its top jobs and
+/// the index of the next one.
+type ScheduledTenant<'a> = (&'a String, f64, f64, Vec<&'a HeapNode<i64>>, usize);
+
+/// A tenant's share of a queue with its own min heap.
+/// `pass`: The tenant's position in the stride schedule, advanced by the inverse of its weight on
+/// every dequeue so tenants are served in proportion to their weights.
+#[derive(Debug)]
+struct TenantHeap {
+    heap: PriorityQueue<i64>,
+    weight: f32,
+    pass: f64,
+}
+
+/// QueueEntry is a job to be placed in its tenant's heap, or held back behind an earlier job with
+/// the same ordering key.
+/// `weight`: The weight of the tenant that owns the job.
+/// `timestamp`: The time at which the job was enqueued, as given by `Queue::enqueue_time`.
+/// `deadline`: Unix time in milliseconds by which the job should be completed, if it has one.
+#[derive(Debug, Clone)]
+pub struct QueueEntry {
+    pub tenant_id: String,
+    pub weight: f32,
+    pub priority: u32,
+    pub job_id: i64,
+    pub timestamp: u64,
+    pub deadline: Option<u64>,
+}
+
+/// A named queue with its own configuration and a min heap per tenant.
+/// Jobs are ordered by effective priority within a tenant, while tenants are scheduled against
+/// each other with weighted fair queuing so one tenant cannot starve the others.
+/// Only the oldest unfinished job of each ordering key is ever in a heap, the rest wait in FIFO
+/// order until it is finished.
+#[derive(Debug)]
+pub struct Queue {
+    pub config: QueueConfig,
+    /// The scheduling policy built from the config, shared by every tenant heap.
+    policy: Arc<dyn SchedulingPolicy>,
+    tenants: HashMap<String, TenantHeap>,
+    /// Pass of the most recently scheduled tenant, tenants that become active start from here so
+    /// they cannot bank credit while idle.
+    pass: f64,
+    /// Jobs waiting behind the active job of their ordering key. A key has an entry for as long
+    /// as one of its jobs is queued or leased.
+    pending: HashMap<String, VecDeque<QueueEntry>>,
+    /// The ordering key of every unfinished job that has one.
+    job_keys: HashMap<i64, String>,
+}
+
+impl Queue {
+    pub fn new(config: QueueConfig) -> Self {
+        Queue {
+            policy: config.scheduling_policy(),
+            config,
+            tenants: HashMap::new(),
+            pass: 0.0,
+            pending: HashMap::new(),
+            job_keys: HashMap::new(),
+        }
+    }
+
+    /// Inserts a job into the heap of the tenant that owns it. A job with an ordering key is held
+    /// back instead if an earlier job with the same key has not finished yet.
+    pub fn insert(&mut self, job: QueueEntry, ordering_key: &str) {
+        if !ordering_key.is_empty() {
+            self.job_keys.insert(job.job_id, ordering_key.to_string());
+
+            if let Some(waiting) = self.pending.get_mut(ordering_key) {
+                waiting.push_back(job);
+                return;
+            }
+
+            self.pending
+                .insert(ordering_key.to_string(), VecDeque::new());
+        }
+
+        self.push_heap(job);
+    }
+
+    /// Puts a job that was handed out back into its tenant heap, ahead of any job waiting behind
+    /// it on its ordering key.
+    pub fn requeue(&mut self, job: QueueEntry) {
+        self.push_heap(job);
+    }
+
+    /// Records that a job holds its ordering key without placing it in a heap, so jobs with the
+    /// same key wait for it. Used for jobs that were leased when the queue was rebuilt.
+    pub fn hold(&mut self, job_id: i64, ordering_key: &str) {
+        if ordering_key.is_empty() {
+            return;
+        }
+
+        self.job_keys.insert(job_id, ordering_key.to_string());
+        self.pending.entry(ordering_key.to_string()).or_default();
+    }
+
+    /// Returns true if jobs in this queue are timed on the wall clock rather than the logical
+    /// clock, which is the case wherever they are ordered against deadlines.
+    pub fn uses_wall_clock(&self) -> bool {
+        matches!(self.config.policy, PolicyConfig::EarliestDeadline { .. })
+    }
+
+    /// The enqueue time given to a job queued at the provided logical time: unix time in
+    /// milliseconds if the queue uses the wall clock, the logical time otherwise.
+    pub fn enqueue_time(&self, timestamp: u64) -> u64 {
+        if self.uses_wall_clock() {
+            Utc::now().timestamp_millis().max(0) as u64
+        } else {
+            timestamp
+        }
+    }
+
+    /// Marks a job as finished, releasing the next job waiting on its ordering key.
+    pub fn finish(&mut self, job_id: i64) {
+        let key: String = match self.job_keys.remove(&job_id) {
+            Some(k) => k,
+            None => return,
+        };
+
+        let next: Option<QueueEntry> = match self.pending.get_mut(&key) {
+            Some(waiting) => waiting.pop_front(),
+            None => None,
+        };
+
+        match next {
+            Some(job) => self.push_heap(job),
+            None => {
+                self.pending.remove(&key);
+            }
+        }
+    }
+
+    fn push_heap(&mut self, job: QueueEntry) {
+        let policy: Arc<dyn SchedulingPolicy> = self.policy.clone();
+        let pass: f64 = self.pass;
+
+        let tenant: &mut TenantHeap = self
+            .tenants
+            .entry(tenant_name(&job.tenant_id).to_string())
+            .or_insert_with(|| TenantHeap {
+                heap: PriorityQueue::with_policy(policy),
+                weight: job.weight,
+                pass,
+            });
+
+        if tenant.heap.is_empty() {
+            tenant.weight = job.weight;
+            tenant.pass = tenant.pass.max(pass);
+        }
+
+        tenant
+            .heap
+            .push_with_deadline(job.priority, job.job_id, job.timestamp, job.deadline, ());
+    }
+
+    /// The tenant that should be served next: the non-empty tenant with the lowest pass.
+    fn next_tenant(&self) -> Option<String> {
+        self.tenants
+            .iter()
+            .filter(|(_, tenant)| !tenant.heap.is_empty())
+            .min_by(|(a_name, a), (b_name, b)| {
+                a.pass
+                    .partial_cmp(&b.pass)
+                    .unwrap_or(Ordering::Equal)
+                    .then_with(|| a_name.cmp(b_name))
+            })
+            .map(|(name, _)| name.clone())
+    }
+
+    /// Extracts the next job, taken from the top of the heap of the next tenant in the schedule.
+    pub fn get_top(&mut self) -> Option<HeapNode<i64>> {
+        let name: String = self.next_tenant()?;
+        let tenant: &mut TenantHeap = self.tenants.get_mut(&name)?;
+
+        self.pass = tenant.pass;
+        let stride: f64 = if tenant.weight > 0.0 {
+            1.0 / tenant.weight as f64
+        } else {
+            1.0
+        };
+        tenant.pass += stride;
+
+        tenant.heap.get_top()
+    }
+
+    /// Retrieves the job that the next call to `get_top` would return.
+    pub fn peek(&self) -> Option<&HeapNode<i64>> {
+        let name: String = self.next_tenant()?;
+        self.tenants.get(&name)?.heap.peek()
+    }
+
+    /// Retrieves the jobs that the next `n` calls to `get_top` would return, in order, by replaying
+    /// the stride schedule over the top of every tenant heap. Nothing is removed.
+    pub fn peek_n(&self, n: usize) -> Vec<&HeapNode<i64>> {
+        let mut schedule: Vec<ScheduledTenant> = self
+            .tenants
+            .iter()
+            .filter(|(_, tenant)| !tenant.heap.is_empty())
+            .map(|(name, tenant)| {
+                let stride: f64 = if tenant.weight > 0.0 {
+                    1.0 / tenant.weight as f64
+                } else {
+                    1.0
+                };
+                (name, tenant.pass, stride, tenant.heap.peek_n(n), 0)
+            })
+            .collect();
+
+        let mut top: Vec<&HeapNode<i64>> = Vec::with_capacity(n);
+        while top.len() < n {
+            let next = schedule
+                .iter_mut()
+                .filter(|(_, _, _, jobs, i)| *i < jobs.len())
+                .min_by(|(a_name, a_pass, ..), (b_name, b_pass, ..)| {
+                    a_pass
+                        .partial_cmp(b_pass)
+                        .unwrap_or(Ordering::Equal)
+                        .then_with(|| a_name.cmp(b_name))
+                });
+
+            match next {
+                Some((_, pass, stride, jobs, i)) => {
+                    top.push(jobs[*i]);
+                    *pass += *stride;
+                    *i += 1;
+                }
+                None => break,
+            }
+        }
+
+        top
+    }
+
+    /// Removes the job from whichever tenant heap holds it, or from behind its ordering key.
+    /// Removing the active job of a key releases the next one.
+    pub fn remove(&mut self, job_id: i64) -> Option<HeapNode<i64>> {
+        if let Some(node) = self
+            .tenants
+            .values_mut()
+            .find_map(|tenant| tenant.heap.remove(&job_id))
+        {
+            self.finish(job_id);
+            return Some(node);
+        }
+
+        let key: String = self.job_keys.get(&job_id)?.clone();
+        let waiting: &mut VecDeque<QueueEntry> = self.pending.get_mut(&key)?;
+
+        match waiting.iter().position(|job| job.job_id == job_id) {
+            Some(i) => {
+                let job: QueueEntry = waiting.remove(i)?;
+                self.job_keys.remove(&job_id);
+                let mut node: HeapNode<i64> =
+                    HeapNode::new(job.job_id, job.priority, job.timestamp);
+                node.deadline = job.deadline;
+                Some(node)
+            }
+            // The job has been handed out, cancelling it frees its key
+            None => {
+                self.finish(job_id);
+                None
+            }
+        }
+    }
+
+    /// Changes the priority of the job in whichever tenant heap holds it, or of a job waiting on
+    /// its ordering key.
+    pub fn change_priority(&mut self, job_id: i64, new_priority: u32) -> bool {
+        if self
+            .tenants
+            .values_mut()
+            .any(|tenant| tenant.heap.change_priority(&job_id, new_priority))
+        {
+            return true;
+        }
+
+        match self
+            .pending
+            .values_mut()
+            .flat_map(|waiting| waiting.iter_mut())
+            .find(|job| job.job_id == job_id)
+        {
+            Some(job) => {
+                job.priority = new_priority;
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Computes the effective priority of a job in the queue at the given time, using the
+    /// queue's scheduling policy. The time waited is measured on the clock the queue times its
+    /// jobs with, so queues on the wall clock are compared against the current unix time.
+    ///
+    /// # Arguments
+    /// `node`: The job, as returned by `peek` or `peek_n`.
+    /// `timestamp`: The current logical time.
+    pub fn effective_priority(&self, node: &HeapNode<i64>, timestamp: u64) -> u32 {
+        let now: u64 = self.enqueue_time(timestamp);
+        self.policy
+            .effective_priority(node.priority, now.saturating_sub(node.enqueue_time))
+    }
+
+    /// The number of jobs in the queue across every tenant, including those waiting on an
+    /// ordering key.
+    pub fn len(&self) -> usize {
+        let waiting: usize = self.pending.values().map(|w| w.len()).sum();
+        self.tenants.values().map(|t| t.heap.len()).sum::<usize>() + waiting
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// The number of jobs the tenant has in the queue.
+    pub fn tenant_depth(&self, tenant_id: &str) -> usize {
+        let tenant_id: &str = tenant_name(tenant_id);
+        let waiting: usize = self
+            .pending
+            .values()
+            .flat_map(|w| w.iter())
+            .filter(|job| tenant_name(&job.tenant_id) == tenant_id)
+            .count();
+
+        self.tenants.get(tenant_id).map_or(0, |t| t.heap.len()) + waiting
+    }
+
+    /// Returns true if the queue cannot accept another job.
+    pub fn is_full(&self) -> bool {
+        self.len() >= self.config.max_size
+    }
+}
+
+/// QueueSet holds every named queue on a node, each backed by an independent heap.
+/// Queues that are not configured are created with the default configuration the first time a
+/// job is added to them.
+#[derive(Debug)]
+pub struct QueueSet {
+    queues: HashMap<String, Queue>,
+    tenants: HashMap<String, TenantConfig>,
+    default_tenant: TenantConfig,
+}
+
+impl QueueSet {
+    /// Creates a queue set from the provided queue and tenant configurations, the default queue
+    /// always exists.
+    pub fn new(
+        configs: HashMap<String, QueueConfig>,
+        tenants: HashMap<String, TenantConfig>,
+    ) -> Self {
+        let mut queues: HashMap<String, Queue> = configs
+            .into_iter()
+            .map(|(name, config)| (name, Queue::new(config)))
+            .collect();
+
+        queues
+            .entry(DEFAULT_QUEUE.to_string())
+            .or_insert_with(|| Queue::new(QueueConfig::default()));
+
+        QueueSet {
+            queues,
+            tenants,
+            default_tenant: TenantConfig::default(),
+        }
+    }
+
+    /// Loads the queue configuration from a JSON file mapping queue names to their config, and
+    /// the tenant configuration from a second file.
+    /// If the queue file does not exist only the default queue is created. A queue whose policy
+    /// settings are invalid is an error.
+    ///
+    /// # Arguments
+    /// `path`: The path to the queue configuration file.
+    /// `tenant_path`: The path to the tenant configuration file.
+    ///
+    /// # Returns
+    /// A Result object that is either Ok(QueueSet) or Err(String)
+    pub fn from_files(path: &str, tenant_path: &str) -> Result<Self, String> {
+        let tenants: HashMap<String, TenantConfig> = tenants::load_tenants(tenant_path)?;
+
+        let contents: String = match std::fs::read_to_string(path) {
+            Ok(c) => c,
+            Err(_) => return Ok(QueueSet::new(HashMap::new(), tenants)),
+        };
+
+        let configs: HashMap<String, QueueConfig> =
+            serde_json::from_str(&contents).map_err(|_| {
+                error!(target:"error_logger","Failed to parse queue configuration file {}",path);
+                format!("Failed to parse queue configuration file {}", path)
+            })?;
+
+        for (name, config) in &configs {
+            config.validate().map_err(|e| {
+                error!(target:"error_logger","Invalid configuration for queue {}: {}",name,e);
+                format!("Invalid configuration for queue {}: {}", name, e)
+            })?;
+        }
+
+        Ok(QueueSet::new(configs, tenants))
+    }
+
+    /// Returns the queue with the given name, if it exists.
+    pub fn get(&self, name: &str) -> Option<&Queue> {
+        self.queues.get(queue_name(name))
+    }
+
+    /// Returns a mutable reference to the queue with the given name, if it exists.
+    pub fn get_mut(&mut self, name: &str) -> Option<&mut Queue> {
+        self.queues.get_mut(queue_name(name))
+    }
+
+    /// Returns the queue with the given name, creating it with the default configuration if it
+    /// does not exist yet.
+    pub fn queue_mut(&mut self, name: &str) -> &mut Queue {
+        self.queues
+            .entry(queue_name(name).to_string())
+            .or_insert_with(|| Queue::new(QueueConfig::default()))
+    }
+
+    /// Returns the configuration of the tenant, the default configuration if it is not configured.
+    pub fn tenant_config(&self, tenant_id: &str) -> &TenantConfig {
+        self.tenants
+            .get(tenant_name(tenant_id))
+            .unwrap_or(&self.default_tenant)
+    }
+
+    /// Inserts a job without a deadline into the named queue, scheduled with the weight of the
+    /// tenant that owns it. An empty ordering key means the job is not ordered against any other
+    /// job.
+    pub fn insert(
+        &mut self,
+        queue: &str,
+        tenant_id: &str,
+        priority: u32,
+        job_id: i64,
+        timestamp: u64,
+        ordering_key: &str,
+    ) {
+        let entry: QueueEntry = QueueEntry {
+            tenant_id: tenant_id.to_string(),
+            weight: self.tenant_config(tenant_id).weight,
+            priority,
+            job_id,
+            timestamp: self.queue_mut(queue).enqueue_time(timestamp),
+            deadline: None,
+        };
+        self.queue_mut(queue).insert(entry, ordering_key);
+    }
+
+    /// Inserts a job into its queue along with its deadline, scheduled with the weight of the
+    /// tenant that owns it.
+    ///
+    /// # Arguments
+    /// `job`: The job to insert.
+    /// `timestamp`: The current logical time.
+    ///
+    /// # Returns
+    /// The enqueue time the job was given, to be recorded so it survives a restart.
+    pub fn insert_job(&mut self, job: &QueuedJob, timestamp: u64) -> u64 {
+        let enqueue_time: u64 = self.queue_mut(&job.queue).enqueue_time(timestamp);
+        self.restore(job, enqueue_time);
+        enqueue_time
+    }
+
+    /// Puts a job that failed back into its queue so it is retried before any job queued behind
+    /// it on its ordering key.
+    ///
+    /// # Arguments
+    /// `job`: The job to requeue.
+    /// `timestamp`: The current logical time.
+    ///
+    /// # Returns
+    /// The enqueue time the job was given, to be recorded so it survives a restart.
+    pub fn requeue(&mut self, job: &QueuedJob, timestamp: u64) -> u64 {
+        let enqueue_time: u64 = self.queue_mut(&job.queue).enqueue_time(timestamp);
+        let entry: QueueEntry = self.entry(job, enqueue_time);
+        self.queue_mut(&job.queue).requeue(entry);
+        enqueue_time
+    }
+
+    /// Inserts a job recovered from storage into its queue with the enqueue time it was given
+    /// before the restart, so it keeps the aging it had already earned.
+    ///
+    /// # Arguments
+    /// `job`: The job to insert.
+    /// `enqueue_time`: The enqueue time recorded for the job.
+    pub fn restore(&mut self, job: &QueuedJob, enqueue_time: u64) {
+        let entry: QueueEntry = self.entry(job, enqueue_time);
+        self.queue_mut(&job.queue).insert(entry, &job.ordering_key);
+    }
+
+    /// Registers a job recovered from storage that is leased to a consumer, so that jobs with the
+    /// same ordering key keep waiting until it is finished.
+    pub fn restore_lease(&mut self, job: &QueuedJob) {
+        self.queue_mut(&job.queue)
+            .hold(job.job_id, &job.ordering_key);
+    }
+
+    fn entry(&self, job: &QueuedJob, timestamp: u64) -> QueueEntry {
+        QueueEntry {
+            tenant_id: job.tenant_id.clone(),
+            weight: self.tenant_config(&job.tenant_id).weight,
+            priority: job.priority as u32,
+            job_id: job.job_id,
+            timestamp,
+            deadline: job.deadline.map(|deadline| deadline.max(0) as u64),
+        }
+    }
+
+    /// Marks a job as finished so the next job on its ordering key can be handed out.
+    pub fn finish(&mut self, job_id: i64) {
+        for queue in self.queues.values_mut() {
+            queue.finish(job_id);
+        }
+    }
+
+    /// The number of jobs the tenant has queued across every queue on the node.
+    pub fn tenant_depth(&self, tenant_id: &str) -> usize {
+        self.queues
+            .values()
+            .map(|q| q.tenant_depth(tenant_id))
+            .sum()
+    }
+
+    /// Finds a queue that would go over its maximum size if a batch of jobs were added to it,
+    /// counting every job of the batch headed for the same queue.
+    ///
+    /// # Arguments
+    /// `names`: The queue of every job in the batch.
+    ///
+    /// # Returns
+    /// The name of the first queue without room for its jobs, None if every job fits.
+    pub fn overflowing<'a>(&self, names: impl IntoIterator<Item = &'a str>) -> Option<String> {
+        let mut added: HashMap<&str, usize> = HashMap::new();
+        for name in names {
+            *added.entry(queue_name(name)).or_default() += 1;
+        }
+
+        added
+            .into_iter()
+            .find(|(name, count)| match self.get(name) {
+                Some(queue) => queue.len() + count > queue.config.max_size,
+                None => *count > QueueConfig::default().max_size,
+            })
+            .map(|(name, _)| name.to_string())
+    }
+
+    /// The names of every queue on the node.
+    pub fn names(&self) -> Vec<String> {
+        self.queues.keys().cloned().collect()
+    }
+
+    /// Extracts the most urgent job across the given queues, or across every queue if none are
+    /// given. Queues are compared by the effective priority of their next job at `timestamp`,
+    /// each measured on its own queue's clock.
+    ///
+    /// Deadlines only order jobs within an earliest deadline first queue. The effective priority
+    /// of a job in such a queue is its original priority, so across queues a job that is due
+    /// soon is not preferred over a more urgent job in another queue.
+    ///
+    /// # Returns
+    /// The name of the queue the job was taken from along with the job.
+    pub fn get_top_from(
+        &mut self,
+        names: &[String],
+        timestamp: u64,
+    ) -> Option<(String, HeapNode<i64>)> {
+        let name: String = self
+            .queues
+            .iter()
+            .filter(|(name, _)| names.is_empty() || names.iter().any(|n| queue_name(n) == *name))
+            .filter_map(|(name, queue)| {
+                queue
+                    .peek()
+                    .map(|node| (name, queue.effective_priority(node, timestamp)))
+            })
+            .min_by_key(|(_, priority)| *priority)
+            .map(|(name, _)| name.clone())?;
+
+        let node: HeapNode<i64> = self.queues.get_mut(&name)?.get_top()?;
+        Some((name, node))
+    }
+
+    /// The total number of jobs across every queue.
+    pub fn len(&self) -> usize {
+        self.queues.values().map(|q| q.len()).sum()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Removes the job from whichever queue holds it.
+    pub fn remove(&mut self, job_id: i64) -> Option<HeapNode<i64>> {
+        self.queues
+            .values_mut()
+            .find_map(|queue| queue.remove(job_id))
+    }
+
+    /// Changes the priority of the job in whichever queue holds it.
+    pub fn change_priority(&mut self, job_id: i64, new_priority: u32) -> bool {
+        self.queues
+            .values_mut()
+            .any(|queue| queue.change_priority(job_id, new_priority))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{PolicyConfig, QueueConfig, QueueSet, DEFAULT_QUEUE};
+    use crate::jobs::QueuedJob;
+    use crate::tenants::TenantConfig;
+    use chrono::Utc;
+    use std::collections::HashMap;
+
+    #[test]
+    fn test_queues_are_independent() {
+        let mut configs: HashMap<String, QueueConfig> = HashMap::new();
+        configs.insert(
+            "emails".to_string(),
+            QueueConfig {
+                aging_factor: 0.1,
+                max_attempts: 5,
+                max_size: 1,
+                ..QueueConfig::default()
+            },
+        );
+
+        let mut queues: QueueSet = QueueSet::new(configs, HashMap::new());
+        let ids: Vec<i64> = vec![1, 2, 3];
+
+        queues.insert("emails", "", 3, ids[0], 0, "");
+        queues.insert("", "", 1, ids[1], 1, "");
+        queues.insert("reports", "", 2, ids[2], 2, "");
+
+        assert!(queues.get("emails").unwrap().is_full());
+        assert!(!queues.get(DEFAULT_QUEUE).unwrap().is_full());
+        assert_eq!(
+            queues.overflowing(["reports", "emails"]),
+            Some("emails".to_string())
+        );
+        assert!(queues.overflowing(["", "reports", "reports"]).is_none());
+        assert_eq!(queues.get("reports").unwrap().config.max_attempts, 3);
+        assert_eq!(queues.len(), 3);
+
+        assert_eq!(
+            queues.get_mut("emails").unwrap().get_top().unwrap().job_id,
+            ids[0]
+        );
+        assert!(queues.get_mut("emails").unwrap().get_top().is_none());
+
+        assert_eq!(queues.remove(ids[2]).unwrap().job_id, ids[2]);
+        assert!(queues.remove(ids[2]).is_none());
+        assert_eq!(queues.len(), 1);
+    }
+
+    #[test]
+    fn test_policy_is_selected_per_queue() {
+        let configs: HashMap<String, QueueConfig> = serde_json::from_str(
+            r#"{
+                "strict": { "policy": { "type": "strict", "max_wait": 100 } },
+                "bands": { "policy": { "type": "weighted_bands", "bands": [
+                    { "max_priority": 2, "weight": 3.0 },
+                    { "max_priority": 5, "weight": 1.0 }
+                ] } }
+            }"#,
+        )
+        .unwrap();
+        assert_eq!(configs["bands"].aging_factor, 0.5);
+        assert_eq!(
+            configs["strict"].policy,
+            PolicyConfig::Strict { max_wait: 100 }
+        );
+
+        let mut queues: QueueSet = QueueSet::new(configs, HashMap::new());
+        let ids: Vec<i64> = vec![1, 2];
+
+        // Under linear aging the old job would have aged past the urgent one by now
+        queues.insert("strict", "", 5, ids[0], 0, "");
+        queues.insert("strict", "", 1, ids[1], 90, "");
+        queues.insert("", "", 5, 3, 0, "");
+        queues.insert("", "", 1, 4, 90, "");
+
+        let strict = queues.get("strict").unwrap();
+        assert_eq!(strict.peek().unwrap().job_id, ids[1]);
+        assert_eq!(strict.effective_priority(strict.peek().unwrap(), 90), 1);
+        let default = queues.get(DEFAULT_QUEUE).unwrap();
+        assert_eq!(default.peek().unwrap().priority, 5);
+
+        // Once the old job has waited 100 ticks it is no longer held back
+        queues.insert("strict", "", 1, 5, 100, "");
+        assert_eq!(queues.get("strict").unwrap().peek().unwrap().job_id, ids[0]);
+    }
+
+    #[test]
+    fn test_invalid_policies_are_rejected() {
+        let config = |policy: &str| -> QueueConfig {
+            serde_json::from_str(&format!(r#"{{ "policy": {} }}"#, policy)).unwrap()
+        };
+
+        assert!(config(r#"{ "type": "exponential", "half_life": 16 }"#)
+            .validate()
+            .is_ok());
+        assert!(config(r#"{ "type": "exponential", "half_life": 0 }"#)
+            .validate()
+            .is_err());
+        assert!(config(r#"{ "type": "exponential", "half_life": -1 }"#)
+            .validate()
+            .is_err());
+
+        assert!(config(
+            r#"{ "type": "weighted_bands", "bands": [
+                { "max_priority": 2, "weight": 0.0 }, { "max_priority": 5, "weight": 1.0 }
+            ] }"#
+        )
+        .validate()
+        .is_ok());
+        for bands in [
+            "[]",
+            r#"[{ "max_priority": 2, "weight": 0.0 }, { "max_priority": 5, "weight": 0.0 }]"#,
+            r#"[{ "max_priority": 2, "weight": -1.0 }, { "max_priority": 5, "weight": 1.0 }]"#,
+            r#"[{ "max_priority": 5, "weight": 1.0 }, { "max_priority": 5, "weight": 1.0 }]"#,
+            r#"[{ "max_priority": 5, "weight": 1.0 }, { "max_priority": 2, "weight": 1.0 }]"#,
+        ] {
+            let policy: String = format!(r#"{{ "type": "weighted_bands", "bands": {} }}"#, bands);
+            assert!(config(&policy).validate().is_err(), "{}", bands);
+        }
+
+        assert!(QueueConfig::default().validate().is_ok());
+    }
+
+    #[test]
+    fn test_earliest_deadline_first() {
+        let configs: HashMap<String, QueueConfig> = serde_json::from_str(
+            r#"{ "reports": { "policy": { "type": "earliest_deadline", "slack": 60000 } } }"#,
+        )
+        .unwrap();
+        let mut queues: QueueSet = QueueSet::new(configs, HashMap::new());
+        let now: i64 = Utc::now().timestamp_millis();
+
+        let job = |job_id: i64, priority: i32, deadline: Option<i64>| QueuedJob {
+            job_id,
+            priority,
+            queue: "reports".to_string(),
+            tenant_id: String::new(),
+            ordering_key: String::new(),
+            deadline,
+        };
+
+        // Without a deadline a job is due `slack` milliseconds per unit of priority after it was
+        // queued, so the relaxed job is due in five minutes and the urgent one in one minute
+        let jobs: Vec<QueuedJob> = vec![
+            job(1, 5, None),
+            job(2, 1, None),
+            job(3, 5, Some(now + 10_000)),
+        ];
+        for (i, queued) in jobs.iter().enumerate() {
+            queues.insert_job(queued, i as u64);
+        }
+
+        // Across queues the job due first only competes on its priority
+        let aged: i64 = 4;
+        queues.insert("", "", 3, aged, 0, "");
+        assert_eq!(queues.get_top_from(&[], 2).unwrap().1.job_id, aged);
+
+        let reports = queues.get_mut("reports").unwrap();
+        assert_eq!(
+            reports.peek().unwrap().deadline,
+            Some((now + 10_000) as u64)
+        );
+        assert_eq!(reports.effective_priority(reports.peek().unwrap(), 2), 5);
+        let order: Vec<i64> = (0..3).map(|_| reports.get_top().unwrap().job_id).collect();
+        assert_eq!(order, vec![jobs[2].job_id, jobs[1].job_id, jobs[0].job_id]);
+    }
+
+    #[test]
+    fn test_restore_keeps_aging_and_ordering_keys() {
+        let mut queues: QueueSet = QueueSet::new(HashMap::new(), HashMap::new());
+
+        let job = |job_id: i64, priority: i32, ordering_key: &str| QueuedJob {
+            job_id,
+            priority,
+            queue: String::new(),
+            tenant_id: String::new(),
+            ordering_key: ordering_key.to_string(),
+            deadline: None,
+        };
+        let leased: QueuedJob = job(1, 1, "account-1");
+        let behind: QueuedJob = job(2, 1, "account-1");
+        let old: QueuedJob = job(3, 4, "");
+        let new: QueuedJob = job(4, 2, "");
+
+        // The old job was enqueued long before the restart and has aged past the new one
+        queues.restore_lease(&leased);
+        queues.restore(&behind, 0);
+        queues.restore(&old, 0);
+        assert_eq!(queues.insert_job(&new, 10), 10);
+
+        let default = queues.get_mut(DEFAULT_QUEUE).unwrap();
+        assert_eq!(default.len(), 3);
+        assert_eq!(default.get_top().unwrap().job_id, old.job_id);
+        assert_eq!(default.get_top().unwrap().job_id, new.job_id);
+        assert!(default.get_top().is_none());
+
+        // The job behind the leased one is only released once it is finished
+        queues.finish(leased.job_id);
+        let default = queues.get_mut(DEFAULT_QUEUE).unwrap();
+        assert_eq!(default.get_top().unwrap().job_id, behind.job_id);
+    }
+
+    #[test]
+    fn test_tenants_are_scheduled_fairly() {
+        let mut tenants: HashMap<String, TenantConfig> = HashMap::new();
+        tenants.insert(
+            "heavy".to_string(),
+            TenantConfig {
+                weight: 2.0,
+                ..TenantConfig::default()
+            },
+        );
+
+        let mut queues: QueueSet = QueueSet::new(HashMap::new(), tenants);
+
+        // The flooding tenant only submits the most urgent jobs
+        for i in 0..10 {
+            queues.insert("", "flood", 1, i as i64, i, "");
+        }
+        let light: Vec<i64> = vec![10, 11];
+        queues.insert("", "light", 5, light[0], 10, "");
+        queues.insert("", "light", 5, light[1], 11, "");
+        for i in 0..4 {
+            queues.insert("", "heavy", 3, 12 + i as i64, 12 + i, "");
+        }
+
+        let queue = queues.get_mut("").unwrap();
+        let peeked: Vec<u32> = queue.peek_n(8).iter().map(|n| n.priority).collect();
+        let order: Vec<u32> = (0..8).map(|_| queue.get_top().unwrap().priority).collect();
+
+        // Peeking replays the schedule without consuming it
+        assert_eq!(peeked, order);
+
+        // Equal weight tenants alternate, the heavy tenant is served twice per round
+        assert_eq!(order.iter().filter(|p| **p == 5).count(), 2);
+        assert_eq!(order.iter().filter(|p| **p == 3).count(), 4);
+        assert_eq!(order.iter().filter(|p| **p == 1).count(), 2);
+        assert_eq!(queues.tenant_depth("flood"), 8);
+        assert_eq!(queues.tenant_depth("light"), 0);
+    }
+
+    #[test]
+    fn test_ordering_keys_are_fifo() {
+        let mut queues: QueueSet = QueueSet::new(HashMap::new(), HashMap::new());
+        let ids: Vec<i64> = (0..5).collect();
+
+        queues.insert("", "", 5, ids[0], 0, "customer-1");
+        queues.insert("", "", 1, ids[1], 1, "customer-1");
+        queues.insert("", "", 3, ids[2], 2, "");
+        queues.insert("", "", 2, ids[3], 3, "customer-2");
+        queues.insert("", "", 1, ids[4], 4, "customer-1");
+        assert_eq!(queues.len(), 5);
+
+        // Only the oldest job of each key competes on priority
+        let queue = queues.get_mut("").unwrap();
+        assert_eq!(queue.get_top().unwrap().job_id, ids[3]);
+        assert_eq!(queue.get_top().unwrap().job_id, ids[2]);
+        assert_eq!(queue.get_top().unwrap().job_id, ids[0]);
+        assert!(queue.get_top().is_none());
+
+        // A failed job is retried before the jobs queued behind it
+        queues.requeue(
+            &QueuedJob {
+                job_id: ids[0],
+                priority: 5,
+                queue: String::new(),
+                tenant_id: String::new(),
+                ordering_key: "customer-1".to_string(),
+                deadline: None,
+            },
+            0,
+        );
+        assert_eq!(
+            queues.get_mut("").unwrap().get_top().unwrap().job_id,
+            ids[0]
+        );
+
+        queues.finish(ids[0]);
+        assert_eq!(
+            queues.get_mut("").unwrap().get_top().unwrap().job_id,
+            ids[1]
+        );
+        assert!(queues.get_mut("").unwrap().get_top().is_none());
+
+        // Cancelling a leased job releases the key
+        assert!(queues.remove(ids[1]).is_none());
+        assert_eq!(
+            queues.get_mut("").unwrap().get_top().unwrap().job_id,
+            ids[4]
+        );
+        assert_eq!(queues.len(), 0);
+    }
+}
@@ -0,0 +1,60 @@
+use log::error;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+/// The tenant jobs belong to when no tenant id is provided.
+pub const DEFAULT_TENANT: &str = "default";
+
+/// Resolves the tenant id of a request, mapping an empty id to the default tenant.
+pub fn tenant_name(tenant_id: &str) -> &str {
+    if tenant_id.is_empty() {
+        DEFAULT_TENANT
+    } else {
+        tenant_id
+    }
+}
+
+/// TenantConfig holds the scheduling weight and quotas of a single tenant.
+/// `weight`: The tenant's share of dequeues relative to the other tenants of a queue.
+/// `max_depth`: The maximum number of jobs the tenant may have queued on this node.
+/// `max_payload_bytes`: The maximum size of a single job payload.
+/// `max_enqueue_rate`: The maximum number of jobs the tenant may enqueue per second.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct TenantConfig {
+    pub weight: f32,
+    pub max_depth: usize,
+    pub max_payload_bytes: usize,
+    pub max_enqueue_rate: f32,
+}
+
+impl Default for TenantConfig {
+    fn default() -> Self {
+        TenantConfig {
+            weight: 1.0,
+            max_depth: 10_000,
+            max_payload_bytes: 1_048_576,
+            max_enqueue_rate: 100.0,
+        }
+    }
+}
+
+/// Loads the tenant configuration from a JSON file mapping tenant ids to their config.
+/// If the file does not exist every tenant uses the default configuration.
+///
+/// # Arguments
+/// `path`: The path to the configuration file.
+///
+/// # Returns
+/// A Result object that is either Ok(HashMap<String, TenantConfig>) or Err(String)
+pub fn load_tenants(path: &str) -> Result<HashMap<String, TenantConfig>, String> {
+    let contents: String = match std::fs::read_to_string(path) {
+        Ok(c) => c,
+        Err(_) => return Ok(HashMap::new()),
+    };
+
+    serde_json::from_str(&contents).map_err(|_| {
+        error!(target:"error_logger","Failed to parse tenant configuration file {}",path);
+        format!("Failed to parse tenant configuration file {}", path)
+    })
+}
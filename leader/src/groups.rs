@@ -0,0 +1,145 @@
+use std::collections::{BTreeMap, HashMap};
+use std::time::{Duration, Instant};
+
+/// How long a member stays in its group without a heartbeat.
+pub const SESSION_TIMEOUT: Duration = Duration::from_secs(15);
+
+/// Group holds the live members of a consumer group.
+/// `members`: The members of the group keyed by member id, with the time of their last heartbeat.
+/// Kept sorted so every member is assigned the same way for the same membership.
+/// `generation`: Incremented every time the membership changes and the group is rebalanced.
+#[derive(Debug, Default)]
+struct Group {
+    members: BTreeMap<String, Instant>,
+    generation: i64,
+}
+
+/// Assignment is the followers a member of a consumer group polls.
+/// `generation`: The generation of the group the assignment was made for.
+/// `followers`: The url addresses of the followers the member polls.
+/// `members`: The number of members in the group.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Assignment {
+    pub generation: i64,
+    pub followers: Vec<String>,
+    pub members: usize,
+}
+
+/// ConsumerGroups tracks the membership of every consumer group and splits the followers between
+/// the members of each group, so members of a group poll different followers instead of every
+/// member polling every follower.
+/// `groups`: The consumer groups keyed by name.
+/// `session_timeout`: How long a member stays in its group without a heartbeat.
+#[derive(Debug)]
+pub struct ConsumerGroups {
+    groups: HashMap<String, Group>,
+    session_timeout: Duration,
+}
+
+impl ConsumerGroups {
+    pub fn new(session_timeout: Duration) -> Self {
+        ConsumerGroups {
+            groups: HashMap::new(),
+            session_timeout,
+        }
+    }
+
+    /// How long a member stays in its group without a heartbeat.
+    pub fn session_timeout(&self) -> Duration {
+        self.session_timeout
+    }
+
+    /// The generation of a group, 0 if the group has no members.
+    pub fn generation(&self, group: &str) -> i64 {
+        self.groups.get(group).map(|g| g.generation).unwrap_or(0)
+    }
+
+    /// Adds a member to a group, or renews its membership if it is already in the group, and
+    /// returns the followers it is assigned. Members that missed their heartbeats are removed
+    /// first. The group is rebalanced whenever a member joins or is removed.
+    ///
+    /// # Arguments
+    /// `group`: The name of the group.
+    /// `member_id`: The id of the member.
+    /// `followers`: The url addresses of every follower.
+    /// `now`: The current time.
+    pub fn join(
+        &mut self,
+        group: &str,
+        member_id: &str,
+        followers: &[String],
+        now: Instant,
+    ) -> Assignment {
+        let session_timeout: Duration = self.session_timeout;
+        let group: &mut Group = self.groups.entry(group.to_string()).or_default();
+
+        let before: usize = group.members.len();
+        group
+            .members
+            .retain(|_, heartbeat| now.duration_since(*heartbeat) < session_timeout);
+        let expired: bool = group.members.len() != before;
+
+        let joined: bool = group.members.insert(member_id.to_string(), now).is_none();
+        if expired || joined {
+            group.generation += 1;
+        }
+
+        assign(group, member_id, followers)
+    }
+
+    /// Removes a member from a group, rebalancing the members left. A group is forgotten once
+    /// its last member leaves.
+    ///
+    /// # Returns
+    /// Whether the member was in the group.
+    pub fn leave(&mut self, group: &str, member_id: &str) -> bool {
+        let g: &mut Group = match self.groups.get_mut(group) {
+            Some(g) => g,
+            None => return false,
+        };
+
+        let left: bool = g.members.remove(member_id).is_some();
+        if g.members.is_empty() {
+            self.groups.remove(group);
+        } else if left {
+            g.generation += 1;
+        }
+
+        left
+    }
+}
+
+/// Splits the followers between the members of a group round robin, in member id order. If the
+/// group has more members than there are followers, members share followers instead.
+///
+/// # Arguments
+/// `group`: The group the member belongs to.
+/// `member_id`: The member whose followers are returned.
+/// `followers`: The url addresses of every follower.
+fn assign(group: &Group, member_id: &str, followers: &[String]) -> Assignment {
+    let members: usize = group.members.len();
+    let index: usize = group
+        .members
+        .keys()
+        .position(|id| id == member_id)
+        .unwrap_or(0);
+
+    let assigned: Vec<String> = if followers.is_empty() {
+        Vec::new()
+    } else if members > followers.len() {
+        vec![followers[index % followers.len()].clone()]
+    } else {
+        followers
+            .iter()
+            .skip(index)
+            .step_by(members.max(1))
+            .cloned()
+            .collect()
+    };
+
+    Assignment {
+        generation: group.generation,
+        followers: assigned,
+        members,
+    }
+}
@@ -1,17 +1,40 @@
+use crate::groups::Assignment;
+use crate::job_management::consumer_group_service_server::ConsumerGroupService;
 use crate::job_management::job_service_server::JobService;
 use crate::job_management::node_health_service_server::NodeHealthService;
 use crate::job_management::paxos_service_client::PaxosServiceClient;
 use crate::job_management::{
-    EnqueueRequest, Job, JobRequest, JobResponse, NodeHealthRequest, NodeHealthResponse,
-    PaxosCommit, PaxosPrepare, PaxosPropose,
+    CancelJobRequest, CancelJobResponse, EnqueueBatchRequest, EnqueueBatchResponse, EnqueueRequest,
+    EnqueueResult, GroupAssignment, Job, JobRequest, JobResponse, JobResult, JobStatusRequest,
+    JobStatusResponse, JoinGroupRequest, LeaveGroupRequest, NodeHealthRequest, NodeHealthResponse,
+    PaxosCancel, PaxosCommit, PaxosPrepare, PaxosPropose, ReleaseJobsRequest, ReleaseJobsResponse,
+    ResultRequest, ResultResponse,
 };
-use crate::node_state::NodeState;
-use log::error;
+use crate::node_state::{NewJob, NodeState, DEFAULT_QUEUE};
+use crate::tenants::DEFAULT_TENANT;
+use futures::StreamExt;
+use log::{error, info};
+use std::collections::{HashMap, HashSet};
 use std::sync::Arc;
+use std::time::Duration;
 use sysinfo::System;
-use tokio::sync::Mutex;
+use tokio::sync::{mpsc, Mutex};
+use tokio::time::Instant;
+use tokio_stream::wrappers::ReceiverStream;
+use tonic::transport::Channel;
 use tonic::Code;
-use tonic::{transport::Server, Request, Response, Status};
+use tonic::{transport::Server, Request, Response, Status, Streaming};
+
+/// The longest a GetResult request may wait for a job to finish
+const MAX_RESULT_WAIT: Duration = Duration::from_secs(60);
+/// How often a waiting GetResult request checks whether the job has finished
+const RESULT_POLL_INTERVAL: Duration = Duration::from_millis(100);
+/// The largest number of jobs an EnqueueBatch request may hold
+const MAX_BATCH_SIZE: usize = 10_000;
+/// The largest number of jobs an EnqueueStream request enqueues at once
+const STREAM_BATCH_SIZE: usize = 500;
+/// How long jobs received on an EnqueueStream request wait to be enqueued with later jobs
+const STREAM_FLUSH_INTERVAL: Duration = Duration::from_millis(50);
 
 pub struct LocalJobService {
     node_state: Arc<Mutex<NodeState>>,
@@ -23,15 +46,124 @@ impl LocalJobService {
     }
 }
 
-#[tonic::async_trait]
-impl JobService for LocalJobService {
-    // EnqueueJob RPC method
-    async fn enqueue_job(&self, request: Request<EnqueueRequest>) -> Result<Response<Job>, Status> {
-        let enqueue_request = request.into_inner();
-        let priority = enqueue_request.priority;
-        let payload = enqueue_request.payload;
-        let proposal_number: i32 = self.node_state.lock().await.increment_timestamp();
-        let mut job_id: i64 = -1;
+impl LocalJobService {
+    /// Checks that a job may be enqueued: its callback url, its tenant's quotas and its parent
+    /// jobs.
+    ///
+    /// # Arguments
+    /// `enqueue_request`: The job being enqueued.
+    ///
+    /// # Returns
+    /// A Result object that is either Ok((String, String, Vec<i64>)) holding the queue, the tenant
+    /// and the parent job ids of the job, or Err(Status)
+    async fn validate(
+        &self,
+        enqueue_request: &EnqueueRequest,
+    ) -> Result<(String, String, Vec<i64>), Status> {
+        let (queue, tenant_id, depends_on): (String, String, Vec<i64>) =
+            parse_request(enqueue_request)?;
+
+        self.node_state
+            .lock()
+            .await
+            .check_quota(&tenant_id, enqueue_request.payload.len())
+            .await?;
+
+        self.node_state
+            .lock()
+            .await
+            .check_dependencies(&depends_on)
+            .await?;
+
+        return Ok((queue, tenant_id, depends_on));
+    }
+
+    /// Checks the jobs of a batch the way `validate` checks a single job, with one query for the
+    /// parent jobs, one for the tracking ids and one for the tenant depths of the whole batch.
+    /// The jobs of the batch accepted so far count towards the depth of their tenant, and a
+    /// tracking id may only be used once.
+    ///
+    /// # Arguments
+    /// `requests`: The jobs of the batch.
+    /// `results`: The result of every job, holding the reason for every job that is rejected.
+    ///
+    /// # Returns
+    /// A Result object that is either Ok(Vec<(usize, String, String, Vec<i64>)>) holding the index,
+    /// queue, tenant and parent job ids of every accepted job, or Err(Status)
+    async fn check_batch(
+        &self,
+        requests: &[EnqueueRequest],
+        results: &mut [EnqueueResult],
+    ) -> Result<Vec<(usize, String, String, Vec<i64>)>, Status> {
+        let mut parsed: Vec<(usize, String, String, Vec<i64>)> = Vec::new();
+        for (i, enqueue_request) in requests.iter().enumerate() {
+            match parse_request(enqueue_request) {
+                Ok((queue, tenant_id, depends_on)) => {
+                    parsed.push((i, queue, tenant_id, depends_on))
+                }
+                Err(status) => results[i].error = status.message().to_string(),
+            }
+        }
+
+        let parents: Vec<i64> = parsed
+            .iter()
+            .flat_map(|(_, _, _, depends_on)| depends_on.iter().copied())
+            .collect();
+        let tracking_ids: Vec<&str> = parsed
+            .iter()
+            .map(|(i, ..)| requests[*i].tracking_id.as_str())
+            .filter(|tracking_id| !tracking_id.is_empty())
+            .collect();
+        let tenant_ids: Vec<&str> = parsed
+            .iter()
+            .map(|(_, _, tenant_id, _)| tenant_id.as_str())
+            .collect();
+
+        let mut state = self.node_state.lock().await;
+        let statuses: HashMap<i64, String> = state.parent_statuses(&parents).await?;
+        let mut taken: HashSet<String> = state.existing_tracking_ids(&tracking_ids).await?;
+        let mut depths: HashMap<String, i64> = state.tenant_depths(&tenant_ids).await?;
+
+        let mut accepted: Vec<(usize, String, String, Vec<i64>)> = Vec::with_capacity(parsed.len());
+        for (i, queue, tenant_id, depends_on) in parsed {
+            let tracking_id: &str = &requests[i].tracking_id;
+            let depth: &mut i64 = depths.entry(tenant_id.clone()).or_insert(0);
+
+            let checked: Result<(), Status> = NodeState::check_parents(&depends_on, &statuses)
+                .and_then(|_| {
+                    if !tracking_id.is_empty() && taken.contains(tracking_id) {
+                        return Err(Status::already_exists(format!(
+                            "A job with tracking id {} already exists",
+                            tracking_id
+                        )));
+                    }
+                    return Ok(());
+                })
+                .and_then(|_| state.check_limits(&tenant_id, requests[i].payload.len(), *depth));
+
+            match checked {
+                Ok(()) => {
+                    *depth += 1;
+                    if !tracking_id.is_empty() {
+                        taken.insert(tracking_id.to_string());
+                    }
+                    accepted.push((i, queue, tenant_id, depends_on));
+                }
+                Err(status) => results[i].error = status.message().to_string(),
+            }
+        }
+
+        return Ok(accepted);
+    }
+
+    /// Runs the prepare phase of Paxos on the followers.
+    ///
+    /// # Arguments
+    /// `proposal_number`: The number of the proposal.
+    ///
+    /// # Returns
+    /// A Result object that is either Ok(()) once a follower has promised, or Err(Status)
+    async fn prepare(&self, proposal_number: i32) -> Result<(), Status> {
         let paxos_prepare = PaxosPrepare { proposal_number };
 
         let mut responses = Vec::new();
@@ -45,38 +177,35 @@ impl JobService for LocalJobService {
         }
 
         // Check the first successful PaxosPromise response
-        let mut paxos_promise = None;
         for response in responses {
             if let Ok(promise) = response {
                 if promise.get_ref().accepted_value == 1 {
-                    paxos_promise = Some(promise);
-                    break;
+                    return Ok(());
                 }
             }
         }
 
-        if paxos_promise.is_none() {
-            error!(
-                "Paxos prepared failed with proposal number = {}",
-                proposal_number
-            );
-            return Err(Status::internal("Paxos prepared failed"));
-        }
-
-        job_id = self
-            .node_state
-            .lock()
-            .await
-            .insert_job(priority as u32, payload.clone())
-            .await? as i64;
+        error!(
+            "Paxos prepared failed with proposal number = {}",
+            proposal_number
+        );
+        return Err(Status::internal("Paxos prepared failed"));
+    }
 
+    /// Proposes jobs to the followers and commits them, adding them to the followers' queues.
+    /// The jobs are replicated as a single entry.
+    ///
+    /// # Arguments
+    /// `proposal_number`: The number of the proposal, already prepared.
+    /// `jobs`: The jobs to replicate.
+    ///
+    /// # Returns
+    /// A Result object that is either Ok(()) or Err(Status)
+    async fn replicate(&self, proposal_number: i32, jobs: Vec<Job>) -> Result<(), Status> {
         let paxos_propose = PaxosPropose {
             proposal_number,
-            proposed_job: Some(Job {
-                job_id,
-                priority,
-                payload: payload.clone(),
-            }),
+            proposed_job: None,
+            proposed_jobs: jobs,
         };
 
         let mut responses = Vec::new();
@@ -122,11 +251,7 @@ impl JobService for LocalJobService {
 
         for response in responses {
             if let Ok(_) = response {
-                return Ok(Response::new(Job {
-                    job_id: job_id as i64,
-                    priority,
-                    payload,
-                }));
+                return Ok(());
             }
         }
 
@@ -137,6 +262,346 @@ impl JobService for LocalJobService {
         return Err(Status::internal("Paxos commit failed"));
     }
 
+    /// Enqueues many jobs at once. Jobs that fail validation, exceed their tenant's quota or
+    /// reuse a tracking id are rejected individually. The rest are inserted along with their
+    /// dependencies in a single transaction and replicated to the followers as one entry. If the
+    /// batch cannot be replicated its jobs are removed again, so the accepted jobs are either all
+    /// enqueued or none are.
+    ///
+    /// # Arguments
+    /// `requests`: The jobs to enqueue.
+    ///
+    /// # Returns
+    /// A Result object that is either Ok(Vec<EnqueueResult>) holding a result per job in the order
+    /// they were given, or Err(Status) if the accepted jobs could not be inserted or replicated
+    async fn enqueue_many(
+        &self,
+        requests: Vec<EnqueueRequest>,
+    ) -> Result<Vec<EnqueueResult>, Status> {
+        let mut results: Vec<EnqueueResult> = requests
+            .iter()
+            .map(|enqueue_request| EnqueueResult {
+                success: false,
+                job: None,
+                error: String::new(),
+                tracking_id: enqueue_request.tracking_id.clone(),
+            })
+            .collect();
+
+        let accepted: Vec<(usize, String, String, Vec<i64>)> =
+            self.check_batch(&requests, &mut results).await?;
+
+        if accepted.is_empty() {
+            return Ok(results);
+        }
+
+        let proposal_number: i32 = self.node_state.lock().await.increment_timestamp();
+        self.prepare(proposal_number).await?;
+
+        let new_jobs: Vec<NewJob> = accepted
+            .iter()
+            .map(|(i, queue, tenant_id, _)| NewJob {
+                priority: requests[*i].priority as u32,
+                payload: &requests[*i].payload,
+                queue,
+                tenant_id,
+                ordering_key: &requests[*i].ordering_key,
+                callback_url: &requests[*i].callback_url,
+                tracking_id: &requests[*i].tracking_id,
+                deadline: requests[*i].deadline,
+            })
+            .collect();
+        let depends_on: Vec<&[i64]> = accepted
+            .iter()
+            .map(|(_, _, _, depends_on)| depends_on.as_slice())
+            .collect();
+
+        let inserted: Vec<Option<(i64, bool)>> = self
+            .node_state
+            .lock()
+            .await
+            .insert_batch(&new_jobs, &depends_on)
+            .await?;
+
+        let mut job_ids: Vec<i64> = Vec::with_capacity(inserted.len());
+        let mut ready_jobs: Vec<Job> = Vec::with_capacity(inserted.len());
+        for ((i, queue, tenant_id, _), inserted) in accepted.iter().zip(inserted) {
+            // Another job took the tracking id after the batch was checked
+            let (job_id, ready): (i64, bool) = match inserted {
+                Some(job) => job,
+                None => {
+                    results[*i].error = format!(
+                        "A job with tracking id {} already exists",
+                        requests[*i].tracking_id
+                    );
+                    continue;
+                }
+            };
+
+            let job: Job = Job {
+                job_id: job_id.to_string(),
+                priority: requests[*i].priority,
+                payload: requests[*i].payload.clone(),
+                queue: queue.clone(),
+                tenant_id: tenant_id.clone(),
+                ordering_key: requests[*i].ordering_key.clone(),
+                deadline: requests[*i].deadline,
+            };
+
+            // Jobs with outstanding parents are held out of the followers' queues until released
+            if ready {
+                ready_jobs.push(job.clone());
+            }
+
+            job_ids.push(job_id);
+            results[*i].success = true;
+            results[*i].job = Some(job);
+        }
+
+        if !ready_jobs.is_empty() {
+            if let Err(status) = self.replicate(proposal_number, ready_jobs).await {
+                error!(
+                    "Error: Removing {} jobs that could not be replicated",
+                    job_ids.len()
+                );
+                self.node_state.lock().await.remove_jobs(&job_ids).await?;
+                return Err(status);
+            }
+        }
+
+        return Ok(results);
+    }
+
+    /// Enqueues the jobs received on a stream so far and sends back their results.
+    ///
+    /// # Arguments
+    /// `pending`: The jobs received since the last flush, emptied by the flush.
+    /// `results`: The stream of results to the producer.
+    ///
+    /// # Returns
+    /// False if the producer is no longer listening to the results.
+    async fn flush_stream(
+        &self,
+        pending: &mut Vec<EnqueueRequest>,
+        results: &mpsc::Sender<Result<EnqueueResult, Status>>,
+    ) -> bool {
+        if pending.is_empty() {
+            return true;
+        }
+
+        let requests: Vec<EnqueueRequest> = std::mem::take(pending);
+        let tracking_ids: Vec<String> = requests.iter().map(|r| r.tracking_id.clone()).collect();
+
+        // A failed batch is reported job by job so the producer can resubmit it without
+        // reopening the stream
+        let batch: Vec<EnqueueResult> = match self.enqueue_many(requests).await {
+            Ok(batch) => batch,
+            Err(status) => tracking_ids
+                .into_iter()
+                .map(|tracking_id| EnqueueResult {
+                    success: false,
+                    job: None,
+                    error: status.message().to_string(),
+                    tracking_id,
+                })
+                .collect(),
+        };
+
+        for result in batch {
+            if results.send(Ok(result)).await.is_err() {
+                return false;
+            }
+        }
+
+        return true;
+    }
+}
+
+/// Checks the fields of a job that can be checked without the database: its callback url, its
+/// deadline and its parent job ids.
+///
+/// # Arguments
+/// `enqueue_request`: The job being enqueued.
+///
+/// # Returns
+/// A Result object that is either Ok((String, String, Vec<i64>)) holding the queue, the tenant and
+/// the parent job ids of the job, or Err(Status)
+fn parse_request(enqueue_request: &EnqueueRequest) -> Result<(String, String, Vec<i64>), Status> {
+    let queue: String = if enqueue_request.queue.is_empty() {
+        DEFAULT_QUEUE.to_string()
+    } else {
+        enqueue_request.queue.clone()
+    };
+    let tenant_id: String = if enqueue_request.tenant_id.is_empty() {
+        DEFAULT_TENANT.to_string()
+    } else {
+        enqueue_request.tenant_id.clone()
+    };
+    let callback_url: &str = &enqueue_request.callback_url;
+
+    if !callback_url.is_empty()
+        && !callback_url.starts_with("http://")
+        && !callback_url.starts_with("https://")
+    {
+        error!(
+            "Error: Invalid callback url {} provided to EnqueueJob",
+            callback_url
+        );
+        return Err(Status::invalid_argument(
+            "Callback url must be an http or https url",
+        ));
+    }
+
+    if enqueue_request.deadline < 0 {
+        error!(
+            "Error: Invalid deadline {} provided to EnqueueJob",
+            enqueue_request.deadline
+        );
+        return Err(Status::invalid_argument(
+            "Deadline must be a unix time in milliseconds",
+        ));
+    }
+
+    let mut depends_on: Vec<i64> = Vec::with_capacity(enqueue_request.depends_on.len());
+    for parent in &enqueue_request.depends_on {
+        depends_on.push(parent.parse::<i64>().map_err(|_| {
+            error!(
+                "Error: Invalid parent job id {} provided to EnqueueJob",
+                parent
+            );
+            Status::invalid_argument(format!("Invalid parent job id {}", parent))
+        })?);
+    }
+
+    return Ok((queue, tenant_id, depends_on));
+}
+
+#[tonic::async_trait]
+impl JobService for LocalJobService {
+    // EnqueueJob RPC method
+    async fn enqueue_job(&self, request: Request<EnqueueRequest>) -> Result<Response<Job>, Status> {
+        let enqueue_request = request.into_inner();
+        let priority = enqueue_request.priority;
+        let (queue, tenant_id, depends_on): (String, String, Vec<i64>) =
+            self.validate(&enqueue_request).await?;
+        let payload = enqueue_request.payload;
+        let ordering_key: String = enqueue_request.ordering_key;
+        let callback_url: String = enqueue_request.callback_url;
+
+        let proposal_number: i32 = self.node_state.lock().await.increment_timestamp();
+        self.prepare(proposal_number).await?;
+
+        let job_id: i64 = self
+            .node_state
+            .lock()
+            .await
+            .insert_job(NewJob {
+                priority: priority as u32,
+                payload: &payload,
+                queue: &queue,
+                tenant_id: &tenant_id,
+                ordering_key: &ordering_key,
+                callback_url: &callback_url,
+                tracking_id: &enqueue_request.tracking_id,
+                deadline: enqueue_request.deadline,
+            })
+            .await? as i64;
+
+        let ready: bool = self
+            .node_state
+            .lock()
+            .await
+            .add_dependencies(job_id, &depends_on)
+            .await?;
+
+        let job: Job = Job {
+            job_id: job_id.to_string(),
+            priority,
+            payload,
+            queue,
+            tenant_id,
+            ordering_key,
+            deadline: enqueue_request.deadline,
+        };
+
+        // Jobs with outstanding parents are held out of the followers' queues until released
+        if ready {
+            self.replicate(proposal_number, vec![job.clone()]).await?;
+        }
+
+        return Ok(Response::new(job));
+    }
+
+    /// Enqueues many jobs at once, reporting the outcome of every job.
+    async fn enqueue_batch(
+        &self,
+        request: Request<EnqueueBatchRequest>,
+    ) -> Result<Response<EnqueueBatchResponse>, Status> {
+        let requests: Vec<EnqueueRequest> = request.into_inner().jobs;
+
+        if requests.len() > MAX_BATCH_SIZE {
+            return Err(Status::invalid_argument(format!(
+                "A batch may hold at most {} jobs",
+                MAX_BATCH_SIZE
+            )));
+        }
+
+        let results: Vec<EnqueueResult> = self.enqueue_many(requests).await?;
+
+        return Ok(Response::new(EnqueueBatchResponse { results }));
+    }
+
+    type EnqueueStreamStream = ReceiverStream<Result<EnqueueResult, Status>>;
+
+    /// Enqueues the jobs a producer pushes over a single stream. Jobs are gathered into batches
+    /// of up to `STREAM_BATCH_SIZE` jobs, or whatever arrived within `STREAM_FLUSH_INTERVAL`, and
+    /// the result of every job is streamed back once its batch has been enqueued.
+    async fn enqueue_stream(
+        &self,
+        request: Request<Streaming<EnqueueRequest>>,
+    ) -> Result<Response<Self::EnqueueStreamStream>, Status> {
+        let mut jobs: Streaming<EnqueueRequest> = request.into_inner();
+        let (results, receiver) = mpsc::channel(STREAM_BATCH_SIZE);
+        let service: LocalJobService = LocalJobService {
+            node_state: self.node_state.clone(),
+        };
+
+        tokio::spawn(async move {
+            let mut pending: Vec<EnqueueRequest> = Vec::new();
+            let mut flush = tokio::time::interval(STREAM_FLUSH_INTERVAL);
+
+            loop {
+                tokio::select! {
+                    message = jobs.next() => match message {
+                        Some(Ok(job)) => {
+                            pending.push(job);
+                            if pending.len() >= STREAM_BATCH_SIZE
+                                && !service.flush_stream(&mut pending, &results).await
+                            {
+                                break;
+                            }
+                        }
+                        Some(Err(status)) => {
+                            error!("Error: Enqueue stream failed: {}", status);
+                            break;
+                        }
+                        None => {
+                            service.flush_stream(&mut pending, &results).await;
+                            break;
+                        }
+                    },
+                    _ = flush.tick() => {
+                        if !service.flush_stream(&mut pending, &results).await {
+                            break;
+                        }
+                    }
+                }
+            }
+        });
+
+        return Ok(Response::new(ReceiverStream::new(receiver)));
+    }
+
     async fn get_task(
         &self,
         request: Request<JobRequest>,
@@ -146,7 +611,9 @@ impl JobService for LocalJobService {
 
         let query = client
             .db
-            .prepare("SELECT * FROM jobs WHERE job_id = $1")
+            .prepare(
+                "SELECT job_id, priority, payload, queue, tenant_id, ordering_key, COALESCE((EXTRACT(EPOCH FROM deadline) * 1000)::BIGINT, 0) FROM jobs WHERE job_id = $1",
+            )
             .await
             .map_err(|_| {
                 error!("Error: Failed to create SELECT query");
@@ -164,12 +631,152 @@ impl JobService for LocalJobService {
 
         return Ok(Response::new(JobResponse {
             job: Some(Job {
-                job_id: row.get(0),
+                job_id: row.get::<_, i64>(0).to_string(),
                 priority: row.get(1),
                 payload: row.get(2),
+                queue: row.get(3),
+                tenant_id: row.get(4),
+                ordering_key: row.get(5),
+                deadline: row.get(6),
             }),
         }));
     }
+
+    /// Cancels a job, along with its waiting dependents, by marking it cancelled in the database
+    /// and instructing every follower to drop it from its local queue. Waiting dependents are
+    /// never queued on a follower, so only the job itself is sent to them.
+    async fn cancel_job(
+        &self,
+        request: Request<CancelJobRequest>,
+    ) -> Result<Response<CancelJobResponse>, Status> {
+        let job_id: String = request.into_inner().job_id;
+
+        let id: i64 = job_id.parse::<i64>().map_err(|_| {
+            error!("Error: Invalid job id {} provided to CancelJob", job_id);
+            Status::invalid_argument(format!("Invalid job id {}", job_id))
+        })?;
+
+        let cascaded: u64 = match self.node_state.lock().await.cancel_job(id).await? {
+            Some(cascaded) => cascaded,
+            None => {
+                return Ok(Response::new(CancelJobResponse {
+                    success: false,
+                    message: format!("Job with job_id={} could not be cancelled", job_id),
+                }));
+            }
+        };
+
+        // The lock is only held to take the channels, the followers are sent the cancel
+        // concurrently without it
+        let (proposal_number, channels): (i32, Vec<(String, Channel)>) = {
+            let mut state = self.node_state.lock().await;
+            (state.increment_timestamp(), state.follower_channels())
+        };
+
+        // Followers key their queues by the job id, so it is sent in the same form jobs are
+        // replicated with rather than as the client wrote it
+        let paxos_cancel = PaxosCancel {
+            proposal_number,
+            job_id: id.to_string(),
+        };
+
+        let cancels = channels.into_iter().map(|(follower, channel)| {
+            let paxos_cancel: PaxosCancel = paxos_cancel.clone();
+            async move {
+                let mut client = PaxosServiceClient::new(channel);
+                if client.cancel(paxos_cancel).await.is_err() {
+                    error!(
+                        "Paxos cancel failed on follower {} with proposal number = {}",
+                        follower, proposal_number
+                    );
+                }
+            }
+        });
+        futures::future::join_all(cancels).await;
+
+        return Ok(Response::new(CancelJobResponse {
+            success: true,
+            message: format!(
+                "Job with job_id={} has been cancelled, {} dependent jobs cancelled",
+                job_id, cascaded
+            ),
+        }));
+    }
+
+    /// Replicates the dependents a follower released when their last parent was acked, the same
+    /// way jobs enqueued with completed parents are replicated, so every follower queues them.
+    async fn release_jobs(
+        &self,
+        request: Request<ReleaseJobsRequest>,
+    ) -> Result<Response<ReleaseJobsResponse>, Status> {
+        let mut job_ids: Vec<i64> = Vec::new();
+        for job_id in request.into_inner().job_ids {
+            job_ids.push(job_id.parse::<i64>().map_err(|_| {
+                error!("Error: Invalid job id {} provided to ReleaseJobs", job_id);
+                Status::invalid_argument(format!("Invalid job id {}", job_id))
+            })?);
+        }
+
+        let jobs: Vec<Job> = self.node_state.lock().await.queued_jobs(&job_ids).await?;
+        let released: usize = jobs.len();
+
+        if !jobs.is_empty() {
+            let proposal_number: i32 = self.node_state.lock().await.increment_timestamp();
+            self.prepare(proposal_number).await?;
+            self.replicate(proposal_number, jobs).await?;
+        }
+
+        return Ok(Response::new(ReleaseJobsResponse {
+            success: true,
+            message: format!("{} released jobs replicated", released),
+        }));
+    }
+
+    /// Returns the state of a job, looked up by its id or by its tracking id.
+    async fn get_job_status(
+        &self,
+        request: Request<JobStatusRequest>,
+    ) -> Result<Response<JobStatusResponse>, Status> {
+        let id: String = request.into_inner().id;
+        let status: JobStatusResponse = self.node_state.lock().await.get_job_status(&id).await?;
+
+        return Ok(Response::new(status));
+    }
+
+    /// Returns the result of a job, waiting up to the requested timeout for the job to reach a
+    /// terminal state.
+    async fn get_result(
+        &self,
+        request: Request<ResultRequest>,
+    ) -> Result<Response<ResultResponse>, Status> {
+        let request = request.into_inner();
+
+        let job_id: i64 = request.job_id.parse::<i64>().map_err(|_| {
+            error!(
+                "Error: Invalid job id {} provided to GetResult",
+                request.job_id
+            );
+            Status::invalid_argument(format!("Invalid job id {}", request.job_id))
+        })?;
+
+        let wait: Duration =
+            Duration::from_secs(request.timeout_seconds.max(0) as u64).min(MAX_RESULT_WAIT);
+        let deadline: Instant = Instant::now() + wait;
+
+        loop {
+            let (status, result): (String, Option<JobResult>) =
+                self.node_state.lock().await.get_result(job_id).await?;
+
+            let finished: bool =
+                matches!(status.as_str(), "completed" | "dead_lettered" | "cancelled");
+
+            if finished || Instant::now() >= deadline {
+                return Ok(Response::new(ResultResponse { status, result }));
+            }
+
+            tokio::time::sleep(RESULT_POLL_INTERVAL).await;
+        }
+    }
 }
 
 #[tonic::async_trait]
@@ -189,3 +796,76 @@ impl NodeHealthService for LocalJobService {
         }));
     }
 }
+
+#[tonic::async_trait]
+impl ConsumerGroupService for LocalJobService {
+    /// Adds a consumer to a group, or keeps it in the group, and returns the followers it polls.
+    /// Members are expected to join again well within the session timeout, picking up the new
+    /// assignment whenever the group has been rebalanced.
+    ///
+    /// # Arguments
+    /// `request`: The group and the id of the member joining it.
+    ///
+    /// # Returns
+    /// A Result object that is either an Ok(tonic::Response) or Err(tonic::Status)
+    async fn join_group(
+        &self,
+        request: Request<JoinGroupRequest>,
+    ) -> Result<Response<GroupAssignment>, Status> {
+        let join = request.into_inner();
+        if join.group.is_empty() || join.member_id.is_empty() {
+            return Err(Status::invalid_argument(
+                "A group and a member id must be provided",
+            ));
+        }
+
+        let mut state = self.node_state.lock().await;
+        let followers: Vec<String> = state.followers.clone();
+        let session_timeout: Duration = state.groups.session_timeout();
+        let generation: i64 = state.groups.generation(&join.group);
+
+        let assignment: Assignment = state.groups.join(
+            &join.group,
+            &join.member_id,
+            &followers,
+            Instant::now().into_std(),
+        );
+
+        if assignment.generation != generation {
+            info!(
+                "Consumer group {} rebalanced to generation {} with {} members",
+                join.group, assignment.generation, assignment.members
+            );
+        }
+
+        return Ok(Response::new(GroupAssignment {
+            success: true,
+            generation: assignment.generation,
+            followers: assignment.followers,
+            members: assignment.members as i32,
+            session_timeout_seconds: session_timeout.as_secs() as i32,
+        }));
+    }
+
+    async fn leave_group(
+        &self,
+        request: Request<LeaveGroupRequest>,
+    ) -> Result<Response<GroupAssignment>, Status> {
+        let leave = request.into_inner();
+
+        let mut state = self.node_state.lock().await;
+        let left: bool = state.groups.leave(&leave.group, &leave.member_id);
+        if left {
+            info!(
+                "Consumer {} left group {}, rebalancing",
+                leave.member_id, leave.group
+            );
+        }
+
+        return Ok(Response::new(GroupAssignment {
+            success: left,
+            generation: state.groups.generation(&leave.group),
+            ..GroupAssignment::default()
+        }));
+    }
+}
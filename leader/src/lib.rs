@@ -1,4 +1,6 @@
 pub mod db;
+pub mod groups;
 pub mod grpc;
 pub mod job_management;
 pub mod node_state;
+pub mod tenants;
@@ -1,6 +1,7 @@
 use std::sync::Arc;
 
 use leader::grpc::LocalJobService;
+use leader::job_management::consumer_group_service_server::ConsumerGroupServiceServer;
 use leader::job_management::job_service_server::JobServiceServer;
 use leader::node_state::NodeState;
 use tokio::sync::Mutex;
@@ -14,14 +15,19 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
     let node_state = NodeState::new(followers);
 
     // Initialize the job service with the node_state
-    let job_service = LocalJobService::new(node_state).await;
+    let job_service = LocalJobService::new(node_state.clone()).await;
+    let group_service = LocalJobService::new(node_state).await;
 
     let addr = "[::1]:50051".parse()?;
     let svc = JobServiceServer::new(job_service);
 
     println!("Leader service listening on {:?}", addr);
 
-    Server::builder().add_service(svc).serve(addr).await?;
+    Server::builder()
+        .add_service(svc)
+        .add_service(ConsumerGroupServiceServer::new(group_service))
+        .serve(addr)
+        .await?;
 
     Ok(())
 }
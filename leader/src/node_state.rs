@@ -1,15 +1,40 @@
 use crate::db::connect_to_db;
+use crate::groups::{ConsumerGroups, SESSION_TIMEOUT};
+use crate::job_management::{Job, JobProgress, JobResult, JobStatusResponse};
+use crate::tenants::{TenantConfig, TenantLimits};
 use log::error;
+use std::collections::{HashMap, HashSet};
 use std::sync::Arc;
 use tokio::sync::Mutex;
-use tokio_postgres::Client;
+use tokio_postgres::{Client, Transaction};
+use tonic::transport::{Channel, Endpoint};
 use tonic::{Code, Status};
 
+/// The queue jobs are added to when no queue name is provided.
+pub const DEFAULT_QUEUE: &str = "default";
+
+/// NewJob holds the fields of a job being inserted into the jobs table.
+/// `tracking_id`: The token the enqueue manager handed to the producer before the job had an id.
+/// `deadline`: Unix time in milliseconds by which the job should be completed, 0 for none.
+pub struct NewJob<'a> {
+    pub priority: u32,
+    pub payload: &'a [u8],
+    pub queue: &'a str,
+    pub tenant_id: &'a str,
+    pub ordering_key: &'a str,
+    pub callback_url: &'a str,
+    pub tracking_id: &'a str,
+    pub deadline: i64,
+}
+
 pub struct NodeState {
     pub lamport_timestamp: i32,
     pub node_id: u64,
     pub db: Client,
     pub followers: Vec<String>,
+    pub channels: HashMap<String, Channel>,
+    pub tenants: TenantLimits,
+    pub groups: ConsumerGroups,
 }
 
 impl NodeState {
@@ -33,11 +58,22 @@ impl NodeState {
             }
         };
 
+        let tenants = match TenantLimits::from_file("tenants.json") {
+            Ok(t) => t,
+            Err(_) => {
+                eprintln!("Failed to load the tenant configuration, could not start server");
+                std::process::exit(1);
+            }
+        };
+
         return Arc::new(Mutex::new(NodeState {
             lamport_timestamp: 0,
             node_id,
             db,
             followers,
+            channels: HashMap::new(),
+            tenants,
+            groups: ConsumerGroups::new(SESSION_TIMEOUT),
         }));
     }
 
@@ -48,10 +84,38 @@ impl NodeState {
         return temp as i32;
     }
 
-    pub async fn insert_job(&mut self, priority: u32, payload: Vec<u8>) -> Result<u64, Status> {
+    /// Returns a channel to every follower, creating the ones that do not exist yet. Channels
+    /// connect lazily and are kept for later requests, so this never waits on a follower.
+    ///
+    /// # Returns
+    /// The address of every follower with a valid address, along with its channel.
+    pub fn follower_channels(&mut self) -> Vec<(String, Channel)> {
+        let mut channels: Vec<(String, Channel)> = Vec::with_capacity(self.followers.len());
+        for follower in &self.followers {
+            if let Some(channel) = self.channels.get(follower) {
+                channels.push((follower.clone(), channel.clone()));
+                continue;
+            }
+
+            match Endpoint::from_shared(follower.clone()) {
+                Ok(endpoint) => {
+                    let channel: Channel = endpoint.connect_lazy();
+                    self.channels.insert(follower.clone(), channel.clone());
+                    channels.push((follower.clone(), channel));
+                }
+                Err(_) => error!("Invalid follower address {}", follower),
+            }
+        }
+
+        return channels;
+    }
+
+    pub async fn insert_job(&mut self, job: NewJob<'_>) -> Result<u64, Status> {
         let query = &self
             .db
-            .prepare("INSERT INTO jobs (priority, payload) VALUES ($1,$2) RETURNING job_id")
+            .prepare(
+                "INSERT INTO jobs (priority, payload, queue, tenant_id, ordering_key, callback_url, tracking_id, deadline) VALUES ($1,$2,$3,$4,$5,$6,NULLIF($7, ''),to_timestamp(NULLIF($8::BIGINT, 0) / 1000.0)) RETURNING job_id",
+            )
             .await
             .map_err(|_| {
                 error!("Failed to create INSERT query");
@@ -60,7 +124,19 @@ impl NodeState {
 
         let row = &self
             .db
-            .query_one(&query, &[&priority, &payload])
+            .query_one(
+                &query,
+                &[
+                    &job.priority,
+                    &job.payload,
+                    &job.queue,
+                    &job.tenant_id,
+                    &job.ordering_key,
+                    &job.callback_url,
+                    &job.tracking_id,
+                    &job.deadline,
+                ],
+            )
             .await
             .map_err(|_| {
                 error!("Failed to run INSERT query");
@@ -82,4 +158,651 @@ impl NodeState {
 
         return Ok(job_id);
     }
+
+    /// Inserts the jobs of a batch and records their dependency edges in a single transaction,
+    /// so either every job of the batch is stored or none is. Jobs whose tracking id has already
+    /// been given to another job are skipped rather than failing the batch.
+    ///
+    /// # Arguments
+    /// `jobs`: The jobs to insert.
+    /// `depends_on`: The ids of the parent jobs of every job, in the same order.
+    ///
+    /// # Returns
+    /// A Result object that is either Ok(Vec<Option<(i64, bool)>>) holding, in the order the jobs
+    /// were given, the id of every job and whether it is ready to be queued, None for a job that
+    /// was skipped over its tracking id, or Err(Status)
+    pub async fn insert_batch(
+        &mut self,
+        jobs: &[NewJob<'_>],
+        depends_on: &[&[i64]],
+    ) -> Result<Vec<Option<(i64, bool)>>, Status> {
+        if jobs.is_empty() {
+            return Ok(Vec::new());
+        }
+
+        let transaction: Transaction<'_> = self.db.transaction().await.map_err(|_| {
+            error!("Failed to start batch INSERT transaction");
+            return Status::new(Code::Internal, format!("Failed to start transaction"));
+        })?;
+
+        let job_ids: Vec<Option<i64>> = NodeState::insert_jobs(&transaction, jobs).await?;
+
+        let mut edges: (Vec<i64>, Vec<i64>) = (Vec::new(), Vec::new());
+        for (job_id, parents) in job_ids.iter().zip(depends_on) {
+            if let Some(job_id) = job_id {
+                for parent in parents.iter() {
+                    edges.0.push(*parent);
+                    edges.1.push(*job_id);
+                }
+            }
+        }
+
+        let waiting: HashSet<i64> = NodeState::add_batch_dependencies(&transaction, &edges).await?;
+
+        transaction.commit().await.map_err(|_| {
+            error!("Failed to commit batch INSERT transaction");
+            return Status::new(Code::Internal, format!("Failed to commit transaction"));
+        })?;
+
+        // Increment logical time
+        self.increment_timestamp();
+
+        println!(
+            "Inserted {} jobs into jobs table",
+            job_ids.iter().flatten().count()
+        );
+
+        return Ok(job_ids
+            .into_iter()
+            .map(|job_id| job_id.map(|job_id| (job_id, !waiting.contains(&job_id))))
+            .collect());
+    }
+
+    /// Inserts the jobs of a batch with a single multi-row statement. The ids are drawn from the
+    /// sequence before the rows are inserted, so every id is matched to its job by the job's
+    /// position in the batch.
+    ///
+    /// # Arguments
+    /// `transaction`: The transaction the batch is inserted in.
+    /// `jobs`: The jobs to insert.
+    ///
+    /// # Returns
+    /// A Result object that is either Ok(Vec<Option<i64>>) holding the id of every job in the
+    /// order they were given, None for a job whose tracking id is already taken, or Err(Status)
+    async fn insert_jobs(
+        transaction: &Transaction<'_>,
+        jobs: &[NewJob<'_>],
+    ) -> Result<Vec<Option<i64>>, Status> {
+        let priorities: Vec<i32> = jobs.iter().map(|job| job.priority as i32).collect();
+        let payloads: Vec<&[u8]> = jobs.iter().map(|job| job.payload).collect();
+        let queues: Vec<&str> = jobs.iter().map(|job| job.queue).collect();
+        let tenant_ids: Vec<&str> = jobs.iter().map(|job| job.tenant_id).collect();
+        let ordering_keys: Vec<&str> = jobs.iter().map(|job| job.ordering_key).collect();
+        let callback_urls: Vec<&str> = jobs.iter().map(|job| job.callback_url).collect();
+        let tracking_ids: Vec<&str> = jobs.iter().map(|job| job.tracking_id).collect();
+        let deadlines: Vec<i64> = jobs.iter().map(|job| job.deadline).collect();
+
+        let rows = transaction
+            .query(
+                "WITH batch AS (
+                     SELECT nextval(pg_get_serial_sequence('jobs', 'job_id')) AS job_id, p, d, q, t, o, c, r, l, n
+                     FROM UNNEST($1::INT[], $2::BYTEA[], $3::TEXT[], $4::TEXT[], $5::TEXT[], $6::TEXT[], $7::TEXT[], $8::BIGINT[])
+                         WITH ORDINALITY AS rows(p, d, q, t, o, c, r, l, n)
+                 ), inserted AS (
+                     INSERT INTO jobs (job_id, priority, payload, queue, tenant_id, ordering_key, callback_url, tracking_id, deadline)
+                     SELECT job_id, p, d, q, t, o, c, NULLIF(r, ''), to_timestamp(NULLIF(l, 0) / 1000.0)
+                     FROM batch ORDER BY n
+                     ON CONFLICT (tracking_id) DO NOTHING
+                     RETURNING job_id
+                 )
+                 SELECT batch.n, inserted.job_id FROM batch LEFT JOIN inserted USING (job_id)",
+                &[
+                    &priorities,
+                    &payloads,
+                    &queues,
+                    &tenant_ids,
+                    &ordering_keys,
+                    &callback_urls,
+                    &tracking_ids,
+                    &deadlines,
+                ],
+            )
+            .await
+            .map_err(|_| {
+                error!("Failed to run batch INSERT query");
+                return Status::new(Code::Internal, format!("Failed to run INSERT query"));
+            })?;
+
+        let mut job_ids: Vec<Option<i64>> = vec![None; jobs.len()];
+        for row in rows {
+            let n: i64 = row.get(0);
+            job_ids[n as usize - 1] = row.get(1);
+        }
+
+        return Ok(job_ids);
+    }
+
+    /// Records the dependency edges of the jobs of a batch with a single statement, and moves
+    /// every job with a parent that has not completed yet to the waiting state.
+    ///
+    /// # Arguments
+    /// `transaction`: The transaction the batch is inserted in.
+    /// `edges`: The parent and the child of every edge, as two columns.
+    ///
+    /// # Returns
+    /// A Result object that is either Ok(HashSet<i64>) holding the jobs moved to the waiting
+    /// state, or Err(Status)
+    async fn add_batch_dependencies(
+        transaction: &Transaction<'_>,
+        edges: &(Vec<i64>, Vec<i64>),
+    ) -> Result<HashSet<i64>, Status> {
+        if edges.0.is_empty() {
+            return Ok(HashSet::new());
+        }
+
+        transaction
+            .execute(
+                "INSERT INTO job_dependencies (parent_id, child_id)
+                 SELECT * FROM UNNEST($1::BIGINT[], $2::BIGINT[]) ON CONFLICT DO NOTHING",
+                &[&edges.0, &edges.1],
+            )
+            .await
+            .map_err(|_| {
+                error!("Failed to insert the dependencies of a batch");
+                return Status::new(Code::Internal, format!("Failed to run INSERT query"));
+            })?;
+
+        let rows = transaction
+            .query(
+                "UPDATE jobs SET status = 'waiting' WHERE job_id = ANY($1) AND EXISTS (
+                     SELECT 1 FROM job_dependencies d JOIN jobs p ON p.job_id = d.parent_id
+                     WHERE d.child_id = jobs.job_id AND p.status <> 'completed'
+                 )
+                 RETURNING job_id",
+                &[&edges.1],
+            )
+            .await
+            .map_err(|_| {
+                error!("Failed to run UPDATE query on the jobs of a batch");
+                return Status::new(Code::Internal, format!("Failed to run UPDATE query"));
+            })?;
+
+        return Ok(rows.iter().map(|row| row.get(0)).collect());
+    }
+
+    /// Removes the jobs of a batch that could not be replicated, along with their dependency
+    /// edges, so they are not left in the jobs table without being queued on any follower. Jobs
+    /// a follower has already leased are left in place.
+    ///
+    /// # Arguments
+    /// `job_ids`: The ids of the jobs to remove.
+    ///
+    /// # Returns
+    /// A Result object that is either Ok(()) or Err(Status)
+    pub async fn remove_jobs(&mut self, job_ids: &[i64]) -> Result<(), Status> {
+        let transaction: Transaction<'_> = self.db.transaction().await.map_err(|_| {
+            error!("Failed to start DELETE transaction");
+            return Status::new(Code::Internal, format!("Failed to start transaction"));
+        })?;
+
+        transaction
+            .execute(
+                "DELETE FROM job_dependencies WHERE child_id IN (
+                     SELECT job_id FROM jobs WHERE job_id = ANY($1) AND status IN ('waiting', 'queued')
+                 )",
+                &[&job_ids],
+            )
+            .await
+            .map_err(|_| {
+                error!("Failed to remove the dependencies of an unreplicated batch");
+                return Status::new(Code::Internal, format!("Failed to run DELETE query"));
+            })?;
+
+        transaction
+            .execute(
+                "DELETE FROM jobs WHERE job_id = ANY($1) AND status IN ('waiting', 'queued')",
+                &[&job_ids],
+            )
+            .await
+            .map_err(|_| {
+                error!("Failed to remove the jobs of an unreplicated batch");
+                return Status::new(Code::Internal, format!("Failed to run DELETE query"));
+            })?;
+
+        transaction.commit().await.map_err(|_| {
+            error!("Failed to commit DELETE transaction");
+            return Status::new(Code::Internal, format!("Failed to commit transaction"));
+        })?;
+
+        return Ok(());
+    }
+
+    /// Finds the tracking ids that have already been given to a job.
+    ///
+    /// # Arguments
+    /// `tracking_ids`: The tracking ids to look up.
+    ///
+    /// # Returns
+    /// A Result object that is either Ok(HashSet<String>) holding the tracking ids in use, or
+    /// Err(Status)
+    pub async fn existing_tracking_ids(
+        &self,
+        tracking_ids: &[&str],
+    ) -> Result<HashSet<String>, Status> {
+        if tracking_ids.is_empty() {
+            return Ok(HashSet::new());
+        }
+
+        let rows = self
+            .db
+            .query(
+                "SELECT tracking_id FROM jobs WHERE tracking_id = ANY($1)",
+                &[&tracking_ids],
+            )
+            .await
+            .map_err(|_| {
+                error!("Failed to look up tracking ids");
+                return Status::new(Code::Internal, format!("Failed to run SELECT query"));
+            })?;
+
+        return Ok(rows.iter().map(|row| row.get(0)).collect());
+    }
+
+    /// Checks that a new job stays within the quotas of the tenant that submitted it: the payload
+    /// size, the enqueue rate and the number of unfinished jobs.
+    ///
+    /// # Arguments
+    /// `tenant_id`: The tenant submitting the job.
+    /// `payload_bytes`: The size of the job payload.
+    ///
+    /// # Returns
+    /// A Result object that is either Ok(()) or Err(Status)
+    pub async fn check_quota(
+        &mut self,
+        tenant_id: &str,
+        payload_bytes: usize,
+    ) -> Result<(), Status> {
+        let depths: HashMap<String, i64> = self.tenant_depths(&[tenant_id]).await?;
+        let depth: i64 = depths.get(tenant_id).copied().unwrap_or(0);
+
+        return self.check_limits(tenant_id, payload_bytes, depth);
+    }
+
+    /// Checks a job against the quotas of its tenant, given the number of unfinished jobs the
+    /// tenant already has.
+    ///
+    /// # Arguments
+    /// `tenant_id`: The tenant submitting the job.
+    /// `payload_bytes`: The size of the job payload.
+    /// `depth`: The number of unfinished jobs of the tenant.
+    ///
+    /// # Returns
+    /// A Result object that is either Ok(()) or Err(Status)
+    pub fn check_limits(
+        &mut self,
+        tenant_id: &str,
+        payload_bytes: usize,
+        depth: i64,
+    ) -> Result<(), Status> {
+        let config: TenantConfig = self.tenants.config(tenant_id).clone();
+
+        if payload_bytes > config.max_payload_bytes {
+            return Err(Status::resource_exhausted(format!(
+                "Payload exceeds the {} byte limit of tenant {}",
+                config.max_payload_bytes, tenant_id
+            )));
+        }
+
+        if !self.tenants.try_acquire(tenant_id) {
+            return Err(Status::resource_exhausted(format!(
+                "Tenant {} exceeded its enqueue rate",
+                tenant_id
+            )));
+        }
+
+        if depth >= config.max_depth {
+            return Err(Status::resource_exhausted(format!(
+                "Tenant {} has reached its maximum queue depth",
+                tenant_id
+            )));
+        }
+
+        return Ok(());
+    }
+
+    /// Counts the unfinished jobs of every given tenant with a single query.
+    ///
+    /// # Arguments
+    /// `tenant_ids`: The tenants to count the jobs of.
+    ///
+    /// # Returns
+    /// A Result object that is either Ok(HashMap<String, i64>) holding the number of unfinished
+    /// jobs of every tenant that has any, or Err(Status)
+    pub async fn tenant_depths(&self, tenant_ids: &[&str]) -> Result<HashMap<String, i64>, Status> {
+        let rows = self
+            .db
+            .query(
+                "SELECT tenant_id, COUNT(*) FROM jobs WHERE tenant_id = ANY($1) AND status IN ('waiting', 'queued', 'leased') GROUP BY tenant_id",
+                &[&tenant_ids],
+            )
+            .await
+            .map_err(|_| {
+                error!("Failed to count queued jobs of tenants {:?}", tenant_ids);
+                return Status::new(Code::Internal, format!("Failed to run SELECT query"));
+            })?;
+
+        return Ok(rows.iter().map(|row| (row.get(0), row.get(1))).collect());
+    }
+
+    /// Marks a job as cancelled in the database and cascades the cancellation to every job that
+    /// depends on it, directly or transitively, and is still waiting on its parents. Producers of
+    /// every cancelled job are notified. Both happen in one transaction, so a cancelled job never
+    /// leaves dependents behind that can no longer be released.
+    /// Jobs that have already completed or been cancelled are left untouched. A job that has
+    /// already been handed to a consumer is still marked cancelled so that the consumer can
+    /// observe the cancellation on its next heartbeat.
+    ///
+    /// # Arguments
+    /// `job_id`: The id of the job to cancel.
+    ///
+    /// # Returns
+    /// A Result object that is either Ok(Option<u64>) holding the number of dependents that were
+    /// cancelled, None if the job could not be cancelled, or Err(Status)
+    pub async fn cancel_job(&mut self, job_id: i64) -> Result<Option<u64>, Status> {
+        let transaction: Transaction<'_> = self.db.transaction().await.map_err(|_| {
+            error!("Failed to start cancel transaction for job {}", job_id);
+            return Status::new(Code::Internal, format!("Failed to start transaction"));
+        })?;
+
+        let rows = transaction
+            .execute(
+                "UPDATE jobs SET status = 'cancelled', finished_at = now() WHERE job_id = $1 AND status NOT IN ('completed', 'cancelled')",
+                &[&job_id],
+            )
+            .await
+            .map_err(|_| {
+                error!("Failed to run UPDATE query on job {}", job_id);
+                return Status::new(Code::Internal, format!("Failed to run UPDATE query"));
+            })?;
+
+        if rows == 0 {
+            return Ok(None);
+        }
+
+        let cascaded = transaction
+            .query(
+                "WITH RECURSIVE descendants AS (
+                     SELECT child_id FROM job_dependencies WHERE parent_id = $1
+                     UNION
+                     SELECT d.child_id FROM job_dependencies d
+                     JOIN descendants ON d.parent_id = descendants.child_id
+                 )
+                 UPDATE jobs SET status = 'cancelled', finished_at = now()
+                 WHERE status = 'waiting' AND job_id IN (SELECT child_id FROM descendants)
+                 RETURNING job_id",
+                &[&job_id],
+            )
+            .await
+            .map_err(|_| {
+                error!("Failed to cascade the cancellation of job {}", job_id);
+                return Status::new(Code::Internal, format!("Failed to run UPDATE query"));
+            })?;
+
+        let mut cancelled: Vec<i64> = cascaded.iter().map(|row| row.get(0)).collect();
+        cancelled.push(job_id);
+
+        transaction
+            .execute(
+                "INSERT INTO callback_deliveries (job_id, event, url, status, attempts)
+                 SELECT job_id, 'cancelled', callback_url, 'pending', 0 FROM jobs
+                 WHERE job_id = ANY($1) AND callback_url IS NOT NULL AND callback_url <> ''",
+                &[&cancelled],
+            )
+            .await
+            .map_err(|_| {
+                error!("Failed to record cancelled callbacks of job {}", job_id);
+                return Status::new(Code::Internal, format!("Failed to run INSERT query"));
+            })?;
+
+        transaction.commit().await.map_err(|_| {
+            error!("Failed to commit cancel transaction for job {}", job_id);
+            return Status::new(Code::Internal, format!("Failed to commit transaction"));
+        })?;
+
+        // Increment logical time
+        self.increment_timestamp();
+
+        return Ok(Some(cascaded.len() as u64));
+    }
+
+    /// Loads the released jobs that are still queued, to be replicated to the followers. Jobs
+    /// that have been leased, cancelled or finished since they were released are left out.
+    ///
+    /// # Arguments
+    /// `job_ids`: The ids of the released jobs.
+    ///
+    /// # Returns
+    /// A Result object that is either Ok(Vec<Job>) holding every job that is still queued, or
+    /// Err(Status)
+    pub async fn queued_jobs(&self, job_ids: &[i64]) -> Result<Vec<Job>, Status> {
+        let rows = self
+            .db
+            .query(
+                "SELECT job_id, priority, payload, queue, tenant_id, ordering_key, COALESCE((EXTRACT(EPOCH FROM deadline) * 1000)::BIGINT, 0) FROM jobs WHERE job_id = ANY($1) AND status = 'queued' ORDER BY job_id",
+                &[&job_ids],
+            )
+            .await
+            .map_err(|_| {
+                error!("Failed to run SELECT query on released jobs");
+                return Status::new(Code::Internal, format!("Failed to run SELECT query"));
+            })?;
+
+        return Ok(rows
+            .iter()
+            .map(|row| Job {
+                job_id: row.get::<_, i64>(0).to_string(),
+                priority: row.get(1),
+                payload: row.get(2),
+                queue: row.get(3),
+                tenant_id: row.get(4),
+                ordering_key: row.get(5),
+                deadline: row.get(6),
+            })
+            .collect());
+    }
+
+    /// Looks up the state of a job by its id or by the tracking id it was enqueued with.
+    /// A queued job that has failed at least once is reported as failed until it is retried.
+    ///
+    /// # Arguments
+    /// `id`: The job id or tracking id.
+    ///
+    /// # Returns
+    /// A Result object that is either Ok(JobStatusResponse) or Err(Status)
+    pub async fn get_job_status(&self, id: &str) -> Result<JobStatusResponse, Status> {
+        let row = self
+            .db
+            .query_opt(
+                "SELECT job_id::TEXT, COALESCE(tracking_id, ''),
+                     CASE WHEN status = 'queued' AND attempts > 0 THEN 'failed' ELSE status END,
+                     priority, attempts, queue, tenant_id, progress_percent, progress_message,
+                     EXTRACT(EPOCH FROM created_at)::BIGINT,
+                     COALESCE(EXTRACT(EPOCH FROM finished_at)::BIGINT, 0)
+                 FROM jobs WHERE tracking_id = $1 OR job_id::TEXT = $1",
+                &[&id],
+            )
+            .await
+            .map_err(|_| {
+                error!("Failed to run SELECT query on job {}", id);
+                return Status::new(Code::Internal, format!("Failed to run SELECT query"));
+            })?
+            .ok_or_else(|| Status::not_found(format!("No job with id {}", id)))?;
+
+        return Ok(JobStatusResponse {
+            job_id: row.get(0),
+            tracking_id: row.get(1),
+            status: row.get(2),
+            priority: row.get(3),
+            attempts: row.get(4),
+            queue: row.get(5),
+            tenant_id: row.get(6),
+            progress: Some(JobProgress {
+                percent: row.get(7),
+                message: row.get(8),
+            }),
+            created_at: row.get(9),
+            finished_at: row.get(10),
+        });
+    }
+
+    /// Looks up the status of a job along with its result, if it completed with one.
+    ///
+    /// # Arguments
+    /// `job_id`: The id of the job.
+    ///
+    /// # Returns
+    /// A Result object that is either Ok((String, Option<JobResult>)) or Err(Status)
+    pub async fn get_result(&self, job_id: i64) -> Result<(String, Option<JobResult>), Status> {
+        let row = self
+            .db
+            .query_opt(
+                "SELECT status, result, result_content_type FROM jobs WHERE job_id = $1",
+                &[&job_id],
+            )
+            .await
+            .map_err(|_| {
+                error!("Failed to run SELECT query on job {}", job_id);
+                return Status::new(Code::Internal, format!("Failed to run SELECT query"));
+            })?
+            .ok_or_else(|| Status::not_found(format!("No job with job_id={}", job_id)))?;
+
+        let data: Option<Vec<u8>> = row.get(1);
+        let content_type: Option<String> = row.get(2);
+
+        return Ok((
+            row.get(0),
+            data.map(|data| JobResult {
+                data,
+                content_type: content_type.unwrap_or_default(),
+            }),
+        ));
+    }
+
+    /// Checks that every parent job exists and has not failed or been cancelled.
+    ///
+    /// # Arguments
+    /// `depends_on`: The ids of the parent jobs.
+    ///
+    /// # Returns
+    /// A Result object that is either Ok(()) or Err(Status)
+    pub async fn check_dependencies(&self, depends_on: &[i64]) -> Result<(), Status> {
+        let statuses: HashMap<i64, String> = self.parent_statuses(depends_on).await?;
+
+        return NodeState::check_parents(depends_on, &statuses);
+    }
+
+    /// Looks up the status of parent jobs with a single query.
+    ///
+    /// # Arguments
+    /// `parents`: The ids of the parent jobs.
+    ///
+    /// # Returns
+    /// A Result object that is either Ok(HashMap<i64, String>) holding the status of every parent
+    /// that exists, or Err(Status)
+    pub async fn parent_statuses(&self, parents: &[i64]) -> Result<HashMap<i64, String>, Status> {
+        if parents.is_empty() {
+            return Ok(HashMap::new());
+        }
+
+        let rows = self
+            .db
+            .query(
+                "SELECT job_id, status FROM jobs WHERE job_id = ANY($1)",
+                &[&parents],
+            )
+            .await
+            .map_err(|_| {
+                error!("Failed to run SELECT query on parent jobs");
+                return Status::new(Code::Internal, format!("Failed to run SELECT query"));
+            })?;
+
+        return Ok(rows.iter().map(|row| (row.get(0), row.get(1))).collect());
+    }
+
+    /// Checks the parents of a job against their statuses, as returned by `parent_statuses`.
+    ///
+    /// # Arguments
+    /// `depends_on`: The ids of the parent jobs.
+    /// `statuses`: The status of every parent that exists.
+    ///
+    /// # Returns
+    /// A Result object that is either Ok(()) or Err(Status)
+    pub fn check_parents(
+        depends_on: &[i64],
+        statuses: &HashMap<i64, String>,
+    ) -> Result<(), Status> {
+        for parent in depends_on {
+            match statuses.get(parent).map(|status| status.as_str()) {
+                None => return Err(Status::invalid_argument("Unknown parent job provided")),
+                Some(status @ ("dead_lettered" | "cancelled")) => {
+                    return Err(Status::failed_precondition(format!(
+                        "Parent job {} is {}",
+                        parent, status
+                    )));
+                }
+                Some(_) => {}
+            }
+        }
+
+        return Ok(());
+    }
+
+    /// Records the dependency edges of a newly created job. If any parent has not completed yet
+    /// the job is moved to the waiting state so it is held back until its parents are acked.
+    ///
+    /// # Arguments
+    /// `job_id`: The id of the child job.
+    /// `depends_on`: The ids of the parent jobs.
+    ///
+    /// # Returns
+    /// A Result object that is either Ok(bool), true if the job is ready to be queued, or
+    /// Err(Status)
+    pub async fn add_dependencies(
+        &mut self,
+        job_id: i64,
+        depends_on: &[i64],
+    ) -> Result<bool, Status> {
+        if depends_on.is_empty() {
+            return Ok(true);
+        }
+
+        self.db
+            .execute(
+                "INSERT INTO job_dependencies (parent_id, child_id)
+                 SELECT parent, $2 FROM UNNEST($1::BIGINT[]) AS parents(parent) ON CONFLICT DO NOTHING",
+                &[&depends_on, &job_id],
+            )
+            .await
+            .map_err(|_| {
+                error!("Failed to insert the dependencies of job {}", job_id);
+                return Status::new(Code::Internal, format!("Failed to run INSERT query"));
+            })?;
+
+        let waiting = self
+            .db
+            .execute(
+                "UPDATE jobs SET status = 'waiting' WHERE job_id = $1 AND EXISTS (
+                     SELECT 1 FROM job_dependencies d JOIN jobs p ON p.job_id = d.parent_id
+                     WHERE d.child_id = $1 AND p.status <> 'completed'
+                 )",
+                &[&job_id],
+            )
+            .await
+            .map_err(|_| {
+                error!("Failed to run UPDATE query on job {}", job_id);
+                return Status::new(Code::Internal, format!("Failed to run UPDATE query"));
+            })?;
+
+        return Ok(waiting == 0);
+    }
 }
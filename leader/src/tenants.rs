@@ -0,0 +1,114 @@
+use log::error;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::time::Instant;
+
+/// The tenant jobs belong to when no tenant id is provided.
+pub const DEFAULT_TENANT: &str = "default";
+
+/// TenantConfig holds the scheduling weight and quotas of a single tenant.
+/// `weight`: The tenant's share of dequeues relative to the other tenants of a queue.
+/// `max_depth`: The maximum number of unfinished jobs the tenant may have.
+/// `max_payload_bytes`: The maximum size of a single job payload.
+/// `max_enqueue_rate`: The maximum number of jobs the tenant may enqueue per second.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct TenantConfig {
+    pub weight: f32,
+    pub max_depth: i64,
+    pub max_payload_bytes: usize,
+    pub max_enqueue_rate: f32,
+}
+
+impl Default for TenantConfig {
+    fn default() -> Self {
+        TenantConfig {
+            weight: 1.0,
+            max_depth: 10_000,
+            max_payload_bytes: 1_048_576,
+            max_enqueue_rate: 100.0,
+        }
+    }
+}
+
+/// Token bucket used to limit the enqueue rate of a tenant.
+#[derive(Debug)]
+struct RateBucket {
+    tokens: f32,
+    last_refill: Instant,
+}
+
+/// TenantLimits keeps the configuration of every tenant along with the state needed to enforce
+/// their enqueue rate.
+#[derive(Debug)]
+pub struct TenantLimits {
+    configs: HashMap<String, TenantConfig>,
+    default_config: TenantConfig,
+    buckets: HashMap<String, RateBucket>,
+}
+
+impl TenantLimits {
+    pub fn new(configs: HashMap<String, TenantConfig>) -> Self {
+        TenantLimits {
+            configs,
+            default_config: TenantConfig::default(),
+            buckets: HashMap::new(),
+        }
+    }
+
+    /// Loads the tenant configuration from a JSON file mapping tenant ids to their config.
+    /// If the file does not exist every tenant uses the default configuration.
+    ///
+    /// # Arguments
+    /// `path`: The path to the configuration file.
+    ///
+    /// # Returns
+    /// A Result object that is either Ok(TenantLimits) or Err(String)
+    pub fn from_file(path: &str) -> Result<Self, String> {
+        let contents: String = match std::fs::read_to_string(path) {
+            Ok(c) => c,
+            Err(_) => return Ok(TenantLimits::new(HashMap::new())),
+        };
+
+        let configs: HashMap<String, TenantConfig> =
+            serde_json::from_str(&contents).map_err(|_| {
+                error!("Failed to parse tenant configuration file {}", path);
+                format!("Failed to parse tenant configuration file {}", path)
+            })?;
+
+        Ok(TenantLimits::new(configs))
+    }
+
+    /// Returns the configuration of the tenant, the default configuration if it is not configured.
+    pub fn config(&self, tenant_id: &str) -> &TenantConfig {
+        self.configs.get(tenant_id).unwrap_or(&self.default_config)
+    }
+
+    /// Takes a token from the tenant's bucket.
+    ///
+    /// # Returns
+    /// False if the tenant has exceeded its enqueue rate.
+    pub fn try_acquire(&mut self, tenant_id: &str) -> bool {
+        let rate: f32 = self.config(tenant_id).max_enqueue_rate;
+        let now: Instant = Instant::now();
+
+        let bucket: &mut RateBucket =
+            self.buckets
+                .entry(tenant_id.to_string())
+                .or_insert(RateBucket {
+                    tokens: rate,
+                    last_refill: now,
+                });
+
+        let elapsed: f32 = now.duration_since(bucket.last_refill).as_secs_f32();
+        bucket.tokens = (bucket.tokens + elapsed * rate).min(rate);
+        bucket.last_refill = now;
+
+        if bucket.tokens < 1.0 {
+            return false;
+        }
+
+        bucket.tokens -= 1.0;
+        true
+    }
+}
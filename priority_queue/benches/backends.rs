@@ -0,0 +1,90 @@
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion};
+use priority_queue::{BucketQueue, LinearAging, PriorityQueue, QueueBackend};
+use std::hint::black_box;
+
+/// The aging factor followers use by default.
+const AGING_FACTOR: f32 = 0.5;
+
+/// The number of jobs already waiting when each benchmark starts.
+const DEPTHS: [usize; 3] = [1_000, 10_000, 100_000];
+
+/// Generates priorities between 1 and 5 with a xorshift generator, skewed towards the less urgent
+/// priorities the way producers tend to use them.
+struct Priorities(u64);
+
+impl Priorities {
+    fn next(&mut self) -> u32 {
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 7;
+        self.0 ^= self.0 << 17;
+
+        match self.0 % 10 {
+            0 => 1,
+            1 => 2,
+            2..=4 => 3,
+            5..=7 => 4,
+            _ => 5,
+        }
+    }
+}
+
+/// A backend filled with `depth` jobs, along with the clock and priorities to carry on from.
+fn filled<Q: QueueBackend<u64>>(mut queue: Q, depth: usize) -> (Q, u64, Priorities) {
+    let mut priorities: Priorities = Priorities(0x9E37_79B9_7F4A_7C15);
+    for tick in 0..depth as u64 {
+        queue.push(priorities.next(), tick, tick, ());
+    }
+
+    (queue, depth as u64, priorities)
+}
+
+/// Every dequeue is matched by an enqueue, so the queue stays at the same depth, as on a
+/// follower whose consumers keep up with its producers.
+fn steady_state<Q: QueueBackend<u64>>(c: &mut Criterion, name: &str, new: impl Fn() -> Q) {
+    let mut group = c.benchmark_group("steady_state");
+
+    for depth in DEPTHS {
+        let (mut queue, mut tick, mut priorities) = filled(new(), depth);
+
+        group.bench_with_input(BenchmarkId::new(name, depth), &depth, |b, _| {
+            b.iter(|| {
+                queue.push(priorities.next(), tick, tick, ());
+                tick += 1;
+                black_box(queue.get_top());
+            })
+        });
+    }
+
+    group.finish();
+}
+
+/// A burst of enqueues drained by dequeues, as when a batch lands on a follower.
+fn burst<Q: QueueBackend<u64>>(c: &mut Criterion, name: &str, new: impl Fn() -> Q) {
+    let mut group = c.benchmark_group("burst");
+
+    for depth in DEPTHS {
+        group.bench_with_input(BenchmarkId::new(name, depth), &depth, |b, depth| {
+            b.iter(|| {
+                let (mut queue, _, _) = filled(new(), *depth);
+                while let Some(node) = queue.get_top() {
+                    black_box(node);
+                }
+            })
+        });
+    }
+
+    group.finish();
+}
+
+fn backends(c: &mut Criterion) {
+    let heap = || PriorityQueue::<u64>::new(LinearAging::new(AGING_FACTOR));
+    let buckets = || BucketQueue::<u64>::new(5, AGING_FACTOR);
+
+    steady_state(c, "heap", heap);
+    steady_state(c, "bucket", buckets);
+    burst(c, "heap", heap);
+    burst(c, "bucket", buckets);
+}
+
+criterion_group!(benches, backends);
+criterion_main!(benches);
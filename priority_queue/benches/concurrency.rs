@@ -0,0 +1,101 @@
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion, Throughput};
+use priority_queue::{LinearAging, PriorityQueue, ShardedQueue};
+use std::hint::black_box;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Mutex;
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// The aging factor followers use by default.
+const AGING_FACTOR: f32 = 0.5;
+
+/// The number of jobs waiting when each benchmark starts.
+const DEPTH: u64 = 10_000;
+
+/// The number of enqueue and dequeue pairs every thread makes per iteration.
+const OPERATIONS: u64 = 1_000;
+
+const THREADS: [u64; 4] = [1, 2, 4, 8];
+
+/// Runs `OPERATIONS` enqueue and dequeue pairs on each of `threads` threads at once, returning
+/// how long it took for all of them to finish.
+fn run(threads: u64, clock: &AtomicU64, operation: impl Fn(u64) + Sync) -> Duration {
+    let start: Instant = Instant::now();
+
+    thread::scope(|scope| {
+        for _ in 0..threads {
+            scope.spawn(|| {
+                for _ in 0..OPERATIONS {
+                    operation(clock.fetch_add(1, Ordering::Relaxed));
+                }
+            });
+        }
+    });
+
+    start.elapsed()
+}
+
+fn concurrency(c: &mut Criterion) {
+    let mut group = c.benchmark_group("concurrent_enqueue_dequeue");
+
+    for threads in THREADS {
+        group.throughput(Throughput::Elements(threads * OPERATIONS));
+
+        let clock: AtomicU64 = AtomicU64::new(DEPTH);
+        let locked: Mutex<PriorityQueue<u64>> =
+            Mutex::new(PriorityQueue::new(LinearAging::new(AGING_FACTOR)));
+        for tick in 0..DEPTH {
+            locked
+                .lock()
+                .unwrap()
+                .insert((tick % 5) as u32 + 1, tick, tick);
+        }
+
+        group.bench_with_input(
+            BenchmarkId::new("mutex", threads),
+            &threads,
+            |b, &threads| {
+                b.iter_custom(|iters| {
+                    (0..iters)
+                        .map(|_| {
+                            run(threads, &clock, |tick| {
+                                let mut queue = locked.lock().unwrap();
+                                queue.insert((tick % 5) as u32 + 1, tick, tick);
+                                black_box(queue.get_top());
+                            })
+                        })
+                        .sum()
+                })
+            },
+        );
+
+        let clock: AtomicU64 = AtomicU64::new(DEPTH);
+        let sharded: ShardedQueue<u64> =
+            ShardedQueue::new(LinearAging::new(AGING_FACTOR), 4 * threads as usize, 2);
+        for tick in 0..DEPTH {
+            sharded.insert((tick % 5) as u32 + 1, tick, tick);
+        }
+
+        group.bench_with_input(
+            BenchmarkId::new("sharded", threads),
+            &threads,
+            |b, &threads| {
+                b.iter_custom(|iters| {
+                    (0..iters)
+                        .map(|_| {
+                            run(threads, &clock, |tick| {
+                                sharded.insert((tick % 5) as u32 + 1, tick, tick);
+                                black_box(sharded.get_top());
+                            })
+                        })
+                        .sum()
+                })
+            },
+        );
+    }
+
+    group.finish();
+}
+
+criterion_group!(benches, concurrency);
+criterion_main!(benches);
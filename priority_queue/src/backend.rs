@@ -0,0 +1,48 @@
+use crate::heap::HeapNode;
+
+/// QueueBackend is the interface shared by the data structures a queue of jobs can be kept in.
+/// Lower effective priorities are handed out first, and jobs that wait age towards the front.
+///
+/// `PriorityQueue` supports every scheduling policy over any range of priorities, while
+/// `BucketQueue` only ages jobs linearly but is cheaper when there are few priority levels.
+pub trait QueueBackend<K, V = ()> {
+    /// The number of jobs in the queue.
+    fn len(&self) -> usize;
+
+    fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Inserts a new job carrying a value into the queue. Job ids must be unique within the
+    /// queue.
+    ///
+    /// # Arguments
+    /// `priority`: The original priority of the job.
+    /// `job_id`: The unique id of the job.
+    /// `timestamp`: The logical time at which the job is enqueued.
+    /// `value`: The value carried with the job.
+    fn push(&mut self, priority: u32, job_id: K, timestamp: u64, value: V);
+
+    /// Extracts the job that is handed out next.
+    fn get_top(&mut self) -> Option<HeapNode<K, V>>;
+
+    /// Retrieves the job that the next call to `get_top` would return.
+    fn peek(&self) -> Option<&HeapNode<K, V>>;
+
+    /// Removes the job with the given id from the queue, returning the removed node.
+    fn remove(&mut self, job_id: &K) -> Option<HeapNode<K, V>>;
+
+    /// Changes the priority of a job. The job keeps its enqueue time and with it the aging it has
+    /// earned.
+    ///
+    /// # Returns
+    /// Whether the job was in the queue.
+    fn change_priority(&mut self, job_id: &K, new_priority: u32) -> bool;
+
+    /// Computes the effective priority of a job at the given time.
+    ///
+    /// # Arguments
+    /// `node`: The job, as returned by `peek`.
+    /// `timestamp`: The current logical time.
+    fn effective_priority(&self, node: &HeapNode<K, V>, timestamp: u64) -> u32;
+}
@@ -0,0 +1,336 @@
+use crate::backend::QueueBackend;
+use crate::heap::HeapNode;
+use crate::policy::{LinearAging, SchedulingPolicy};
+use std::collections::{HashMap, VecDeque};
+use std::hash::Hash;
+
+/// BucketQueue is a queue of jobs kept in one FIFO per priority level, for when there are only a
+/// few priorities. Jobs age linearly, as under `LinearAging`, by being promoted to the next more
+/// urgent level every `1 / aging_factor` ticks they wait, so enqueues and dequeues only touch the
+/// ends of the levels instead of sifting through a heap.
+///
+/// Every job in a level entered it a fixed time before it is promoted out of it, so each level
+/// stays in the order of the rank `LinearAging` gives its jobs, and jobs are handed out in the
+/// same order as a `PriorityQueue` with `LinearAging` would hand them out.
+/// `K`: The type of the unique id of a job.
+/// `V`: The type of the value carried with each job, nothing by default.
+/// `levels`: The jobs at each effective priority from 0 up to the largest priority, in the order
+/// they are handed out. Jobs with a larger priority wait in the last level.
+/// `index`: The level each job is in.
+/// `policy`: The linear aging jobs are promoted by.
+/// `latest`: The latest enqueue time of any job, the time jobs are promoted up to.
+#[derive(Debug)]
+pub struct BucketQueue<K, V = ()> {
+    levels: Vec<VecDeque<HeapNode<K, V>>>,
+    index: HashMap<K, usize>,
+    policy: LinearAging,
+    latest: u64,
+}
+
+impl<K: Eq + Hash + Clone> BucketQueue<K> {
+    /// Inserts a new job into the queue.
+    pub fn insert(&mut self, priority: u32, job_id: K, timestamp: u64) {
+        self.push(priority, job_id, timestamp, ());
+    }
+}
+
+impl<K: Eq + Hash + Clone, V> BucketQueue<K, V> {
+    /// Creates a queue with a level for every priority from 0 up to `max_priority`.
+    ///
+    /// # Arguments
+    /// `max_priority`: The largest priority jobs are expected to have, 5 for the jobs table.
+    /// `aging_factor`: How much the effective priority of a job drops per tick it waits.
+    pub fn new(max_priority: u32, aging_factor: f32) -> Self {
+        BucketQueue {
+            levels: (0..=max_priority).map(|_| VecDeque::new()).collect(),
+            index: HashMap::new(),
+            policy: LinearAging::new(aging_factor),
+            latest: 0,
+        }
+    }
+
+    /// The number of jobs in the queue.
+    pub fn len(&self) -> usize {
+        self.index.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.index.is_empty()
+    }
+
+    /// Iterates over every job in the queue, in no particular order.
+    pub fn iter(&self) -> impl Iterator<Item = &HeapNode<K, V>> {
+        self.levels.iter().flatten()
+    }
+
+    /// Inserts a new job carrying a value into the level of its effective priority. Job ids must
+    /// be unique within the queue.
+    pub fn push(&mut self, priority: u32, job_id: K, timestamp: u64, value: V) {
+        let mut node: HeapNode<K, V> = HeapNode::with_value(job_id, priority, timestamp, value);
+        node.rank = self.policy.rank(priority, timestamp, None);
+
+        self.latest = self.latest.max(timestamp);
+        self.promote();
+
+        let level: usize = self.level(&node);
+        self.insert_node(level, node);
+    }
+
+    /// Extracts the job at the front of the most urgent non-empty level, once every job that is
+    /// due has been promoted.
+    pub fn get_top(&mut self) -> Option<HeapNode<K, V>> {
+        self.promote();
+
+        let node: HeapNode<K, V> = self.levels.iter_mut().find_map(|level| level.pop_front())?;
+        self.index.remove(&node.job_id);
+        Some(node)
+    }
+
+    /// Retrieves the job that the next call to `get_top` would return. Promotions that are due
+    /// are accounted for without being carried out: the front of a level has waited longest in
+    /// it, so the next job is the front that is due in the most urgent level.
+    pub fn peek(&self) -> Option<&HeapNode<K, V>> {
+        self.levels
+            .iter()
+            .filter_map(|level| level.front())
+            .min_by(|a, b| {
+                self.level(a)
+                    .cmp(&self.level(b))
+                    .then(a.rank.total_cmp(&b.rank))
+                    .then(a.enqueue_time.cmp(&b.enqueue_time))
+            })
+    }
+
+    /// Removes the job with the given id from the queue, returning the removed node.
+    /// Only the level holding the job is searched, so removal is O(n) in the size of that level.
+    pub fn remove(&mut self, job_id: &K) -> Option<HeapNode<K, V>> {
+        let level: usize = self.index.remove(job_id)?;
+        let nodes: &mut VecDeque<HeapNode<K, V>> = &mut self.levels[level];
+
+        let position: usize = nodes.iter().position(|node| &node.job_id == job_id)?;
+        nodes.remove(position)
+    }
+
+    /// Changes the priority of a job. The job keeps its enqueue time and with it the aging it has
+    /// earned, so it is moved to the level its new priority has aged to.
+    ///
+    /// # Returns
+    /// Whether the job was in the queue.
+    pub fn change_priority(&mut self, job_id: &K, new_priority: u32) -> bool {
+        let mut node: HeapNode<K, V> = match self.remove(job_id) {
+            Some(n) => n,
+            None => return false,
+        };
+
+        node.priority = new_priority;
+        node.rank = self.policy.rank(new_priority, node.enqueue_time, None);
+
+        let level: usize = self.level(&node);
+        self.insert_node(level, node);
+        true
+    }
+
+    /// Computes the effective priority of a job at the given time under linear aging.
+    ///
+    /// # Arguments
+    /// `node`: The job, as returned by `peek`.
+    /// `timestamp`: The current logical time.
+    pub fn effective_priority(&self, node: &HeapNode<K, V>, timestamp: u64) -> u32 {
+        self.policy
+            .effective_priority(node.priority, timestamp.saturating_sub(node.enqueue_time))
+    }
+
+    /// Moves every job that has waited long enough to the next more urgent level. Levels are
+    /// promoted starting from the least urgent, so a job that is due to move several levels is
+    /// carried all the way. Only the fronts of the levels are checked, as the front of a level is
+    /// always the first of it to be due.
+    fn promote(&mut self) {
+        for level in (1..self.levels.len()).rev() {
+            loop {
+                let due: bool = match self.levels[level].front() {
+                    Some(front) => self.level(front) < level,
+                    None => false,
+                };
+                if !due {
+                    break;
+                }
+
+                if let Some(node) = self.levels[level].pop_front() {
+                    self.insert_node(level - 1, node);
+                }
+            }
+        }
+    }
+
+    /// The level a job belongs in at the latest time: its effective priority, capped at the last
+    /// level.
+    fn level(&self, node: &HeapNode<K, V>) -> usize {
+        let effective: u32 = self.effective_priority(node, self.latest);
+        (effective as usize).min(self.levels.len() - 1)
+    }
+
+    /// Inserts a job into a level behind every job that precedes it. Jobs enter a level in rank
+    /// order unless they were enqueued out of order, so this is almost always the back.
+    fn insert_node(&mut self, level: usize, node: HeapNode<K, V>) {
+        let nodes: &mut VecDeque<HeapNode<K, V>> = &mut self.levels[level];
+        let position: usize = nodes.partition_point(|other| !node.precedes(other));
+
+        self.index.insert(node.job_id.clone(), level);
+        nodes.insert(position, node);
+    }
+}
+
+#[cfg(test)]
+impl<K: Eq + Hash + Clone + std::fmt::Debug, V> BucketQueue<K, V> {
+    /// Panics unless every level is in order, the index holds the level of each job, and no job
+    /// sits in a more urgent level than it has aged to.
+    pub(crate) fn assert_invariants(&self) {
+        assert_eq!(self.index.len(), self.iter().count(), "index size");
+
+        for (level, nodes) in self.levels.iter().enumerate() {
+            for (i, node) in nodes.iter().enumerate() {
+                assert_eq!(
+                    self.index.get(&node.job_id),
+                    Some(&level),
+                    "level of {:?}",
+                    node.job_id
+                );
+                assert!(
+                    self.level(node) <= level,
+                    "{:?} was promoted early",
+                    node.job_id
+                );
+
+                if i > 0 {
+                    assert!(
+                        !node.precedes(&nodes[i - 1]),
+                        "{:?} is behind a job it precedes",
+                        node.job_id
+                    );
+                }
+            }
+        }
+    }
+}
+
+impl<K: Eq + Hash + Clone, V> QueueBackend<K, V> for BucketQueue<K, V> {
+    fn len(&self) -> usize {
+        BucketQueue::len(self)
+    }
+
+    fn push(&mut self, priority: u32, job_id: K, timestamp: u64, value: V) {
+        BucketQueue::push(self, priority, job_id, timestamp, value)
+    }
+
+    fn get_top(&mut self) -> Option<HeapNode<K, V>> {
+        BucketQueue::get_top(self)
+    }
+
+    fn peek(&self) -> Option<&HeapNode<K, V>> {
+        BucketQueue::peek(self)
+    }
+
+    fn remove(&mut self, job_id: &K) -> Option<HeapNode<K, V>> {
+        BucketQueue::remove(self, job_id)
+    }
+
+    fn change_priority(&mut self, job_id: &K, new_priority: u32) -> bool {
+        BucketQueue::change_priority(self, job_id, new_priority)
+    }
+
+    fn effective_priority(&self, node: &HeapNode<K, V>, timestamp: u64) -> u32 {
+        BucketQueue::effective_priority(self, node, timestamp)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::BucketQueue;
+    use crate::backend::QueueBackend;
+    use crate::heap::PriorityQueue;
+    use crate::policy::LinearAging;
+
+    #[test]
+    fn test_promotion() {
+        let mut queue: BucketQueue<u64> = BucketQueue::new(5, 0.5);
+
+        queue.insert(5, 1, 0);
+        queue.insert(3, 2, 1);
+        assert_eq!(queue.peek().unwrap().job_id, 2);
+
+        // Job 1 is promoted every 2 ticks, so by tick 6 it has reached level 2 while job 2, which
+        // reached level 1 at tick 5, is still ahead of it
+        queue.insert(4, 3, 6);
+        let levels: Vec<Vec<u64>> = queue
+            .levels
+            .iter()
+            .map(|level| level.iter().map(|node| node.job_id).collect())
+            .collect();
+        assert_eq!(
+            levels,
+            vec![vec![], vec![2], vec![1], vec![], vec![3], vec![]]
+        );
+
+        // At tick 7 job 1 reaches level 1 behind job 2, which entered it first
+        queue.insert(5, 4, 7);
+        assert_eq!(queue.effective_priority(queue.peek().unwrap(), 7), 0);
+        let order: Vec<u64> = (0..4).map(|_| queue.get_top().unwrap().job_id).collect();
+        assert_eq!(order, vec![2, 1, 3, 4]);
+        assert!(queue.is_empty());
+    }
+
+    #[test]
+    fn test_remove_and_change_priority() {
+        let mut queue: BucketQueue<u64, &str> = BucketQueue::new(5, 0.0);
+
+        queue.push(3, 1, 0, "a");
+        queue.push(3, 2, 1, "b");
+        queue.push(4, 3, 2, "c");
+
+        assert_eq!(queue.remove(&2).unwrap().value, "b");
+        assert!(queue.remove(&2).is_none());
+        assert_eq!(queue.len(), 2);
+
+        // Priorities beyond the last level share it
+        assert!(queue.change_priority(&3, 9));
+        assert!(queue.change_priority(&1, 1));
+        assert!(!queue.change_priority(&2, 1));
+        assert_eq!(queue.levels[5].front().unwrap().job_id, 3);
+        assert_eq!(queue.get_top().unwrap().job_id, 1);
+    }
+
+    #[test]
+    fn test_same_order_as_priority_queue() {
+        let mut buckets: BucketQueue<u64> = BucketQueue::new(5, 0.25);
+        let mut heap: PriorityQueue<u64> = PriorityQueue::new(LinearAging::new(0.25));
+        let mut backends: [&mut dyn QueueBackend<u64>; 2] = [&mut buckets, &mut heap];
+
+        let mut seed: u64 = 0x2545_F491_4F6C_DD1D;
+        let mut next = || {
+            seed ^= seed << 13;
+            seed ^= seed >> 7;
+            seed ^= seed << 17;
+            seed
+        };
+
+        let mut order: [Vec<u64>; 2] = [Vec::new(), Vec::new()];
+        for tick in 0..2000u64 {
+            let roll: u64 = next();
+            let priority: u32 = (roll % 5) as u32 + 1;
+
+            for (backend, order) in backends.iter_mut().zip(order.iter_mut()) {
+                match roll % 7 {
+                    0..=3 => backend.push(priority, tick, tick, ()),
+                    4 | 5 => order.extend(backend.get_top().map(|node| node.job_id)),
+                    _ => {
+                        backend.change_priority(&(tick / 2), priority);
+                    }
+                }
+                order.extend(backend.peek().map(|node| node.job_id));
+            }
+        }
+
+        assert_eq!(order[0], order[1]);
+        assert_eq!(backends[0].len(), backends[1].len());
+    }
+}
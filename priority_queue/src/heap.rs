@@ -0,0 +1,1025 @@
+use crate::backend::QueueBackend;
+use crate::policy::{Candidate, SchedulingPolicy};
+use std::cmp::{Ordering, Reverse};
+use std::collections::{BTreeMap, BinaryHeap, HashMap, VecDeque};
+use std::fmt::{self, Display};
+use std::hash::Hash;
+use std::sync::Arc;
+
+/// The state the random rolls offered to the scheduling policy start from.
+const SEED: u64 = 0x9E37_79B9_7F4A_7C15;
+
+/// PriorityQueue is a priority queue of jobs handed out in the order decided by its scheduling
+/// policy. The policy splits jobs into bands, each a min heap ordered by a rank the policy
+/// computes once on insert, so the heaps stay in order as time passes without being rebuilt,
+/// and the effective priority of a job is only computed when asked for.
+/// `K`: The type of the unique id of a job.
+/// `V`: The type of the value carried with each job, nothing by default.
+/// `bands`: The min heap of every band a job was ever placed in, keyed by band.
+/// `policy`: Computes the band, rank and effective priority of each job and picks the band each
+/// job is taken from.
+/// `latest`: The latest enqueue time of any job, the current time as far as the policy knows.
+/// `seed`: The state of the random number generator behind the rolls offered to the policy.
+#[derive(Debug)]
+pub struct PriorityQueue<K, V = ()> {
+    bands: BTreeMap<u32, Band<K, V>>,
+    policy: Arc<dyn SchedulingPolicy>,
+    latest: u64,
+    seed: u64,
+}
+
+/// Band is the min heap of the jobs in one band, ordered by rank.
+/// `heap`: The nodes in heap order.
+/// `index`: Position of each job in `heap`, kept in sync on every swap so a job can be located in
+/// O(1).
+#[derive(Debug)]
+struct Band<K, V> {
+    heap: VecDeque<HeapNode<K, V>>,
+    index: HashMap<K, usize>,
+}
+
+pub struct HeapNode<K, V = ()> {
+    /// The unique job id of the job
+    pub job_id: K,
+    /// The original priority of the job
+    pub priority: u32,
+    /// The time at which the job was enqueued onto the heap, on whichever clock the owner of the
+    /// heap times jobs with, e.g. a Lamport timestamp or unix time in milliseconds
+    pub enqueue_time: u64,
+    /// The time by which the job should be handed out, if it has one, on the same clock as
+    /// `enqueue_time`
+    pub deadline: Option<u64>,
+    /// The value carried with the job
+    pub value: V,
+    /// The key the heap orders the job by, computed by the scheduling policy
+    pub(crate) rank: f64,
+}
+
+impl<K> HeapNode<K> {
+    pub fn new(job_id: K, priority: u32, timestamp: u64) -> Self {
+        HeapNode::with_value(job_id, priority, timestamp, ())
+    }
+}
+
+impl<K, V> HeapNode<K, V> {
+    pub fn with_value(job_id: K, priority: u32, timestamp: u64, value: V) -> Self {
+        HeapNode {
+            job_id,
+            priority,
+            enqueue_time: timestamp,
+            deadline: None,
+            value,
+            rank: priority as f64,
+        }
+    }
+
+    /// Whether the job is handed out before the other job of its band: it has a lower rank, or
+    /// the same rank and was enqueued first.
+    pub(crate) fn precedes(&self, other: &HeapNode<K, V>) -> bool {
+        match self.rank.total_cmp(&other.rank) {
+            Ordering::Less => true,
+            Ordering::Equal => self.enqueue_time < other.enqueue_time,
+            Ordering::Greater => false,
+        }
+    }
+
+    /// The job as offered to the scheduling policy.
+    pub(crate) fn candidate(&self, band: u32) -> Candidate {
+        Candidate {
+            band,
+            priority: self.priority,
+            enqueue_time: self.enqueue_time,
+            rank: self.rank,
+        }
+    }
+}
+
+// The rank is left out as it is derived from the other fields by the scheduling policy
+impl<K: PartialEq, V: PartialEq> PartialEq for HeapNode<K, V> {
+    fn eq(&self, other: &Self) -> bool {
+        self.job_id == other.job_id
+            && self.priority == other.priority
+            && self.enqueue_time == other.enqueue_time
+            && self.deadline == other.deadline
+            && self.value == other.value
+    }
+}
+
+impl<K: Eq, V: Eq> Eq for HeapNode<K, V> {}
+
+/// Rank orders the ranks of jobs, which are never NaN, so the frontier of `peek_n` can be kept in
+/// a `BinaryHeap`.
+#[derive(PartialEq)]
+struct Rank(f64);
+
+impl Eq for Rank {}
+
+impl PartialOrd for Rank {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Rank {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.0.total_cmp(&other.0)
+    }
+}
+
+/// The frontier of a band explored by `peek_n`: the rank, enqueue time and position of the nodes
+/// that may be next.
+type Frontier = BinaryHeap<Reverse<(Rank, u64, usize)>>;
+
+impl<K: Display, V> Display for HeapNode<K, V> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "HeapNode {{\n\tpriority: {}\n\tjob_id: {}\n}}\n",
+            self.priority, self.job_id
+        )
+    }
+}
+
+impl<K: fmt::Debug, V> fmt::Debug for HeapNode<K, V> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "HeapNode {{\n\tpriority: {}\n\tjob_id: {:?}\n}}\n",
+            self.priority, self.job_id
+        )
+    }
+}
+
+impl<K: Eq + Hash + Clone> PriorityQueue<K> {
+    /// Inserts a new job into the heap.
+    pub fn insert(&mut self, priority: u32, job_id: K, timestamp: u64) {
+        self.push(priority, job_id, timestamp, ());
+    }
+}
+
+impl<K: Eq + Hash + Clone, V> PriorityQueue<K, V> {
+    pub fn new<P: SchedulingPolicy + 'static>(policy: P) -> Self {
+        PriorityQueue::with_policy(Arc::new(policy))
+    }
+
+    /// Creates a queue with a policy that may be shared with other queues.
+    pub fn with_policy(policy: Arc<dyn SchedulingPolicy>) -> Self {
+        PriorityQueue {
+            bands: BTreeMap::new(),
+            policy,
+            latest: 0,
+            seed: SEED,
+        }
+    }
+
+    /// The number of jobs in the heap.
+    pub fn len(&self) -> usize {
+        self.bands.values().map(|band| band.heap.len()).sum()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.bands.values().all(|band| band.heap.is_empty())
+    }
+
+    /// Iterates over every job in the heap, in no particular order.
+    pub fn iter(&self) -> impl Iterator<Item = &HeapNode<K, V>> {
+        self.bands.values().flat_map(|band| band.heap.iter())
+    }
+
+    /// Returns the value carried with a job, to be modified in place.
+    pub fn get_mut(&mut self, job_id: &K) -> Option<&mut V> {
+        self.bands.values_mut().find_map(|band| {
+            let index: usize = *band.index.get(job_id)?;
+            Some(&mut band.heap[index].value)
+        })
+    }
+
+    /// Inserts a new job carrying a value into the heap. Job ids must be unique within the heap.
+    pub fn push(&mut self, priority: u32, job_id: K, timestamp: u64, value: V) {
+        self.push_with_deadline(priority, job_id, timestamp, None, value);
+    }
+
+    /// Inserts a new job carrying a value and an optional deadline into the heap. Job ids must be
+    /// unique within the heap.
+    pub fn push_with_deadline(
+        &mut self,
+        priority: u32,
+        job_id: K,
+        timestamp: u64,
+        deadline: Option<u64>,
+        value: V,
+    ) {
+        let mut node: HeapNode<K, V> = HeapNode::with_value(job_id, priority, timestamp, value);
+        node.deadline = deadline;
+        node.rank = self.policy.rank(priority, timestamp, deadline);
+
+        self.latest = self.latest.max(timestamp);
+        self.bands
+            .entry(self.policy.band(priority))
+            .or_insert_with(Band::new)
+            .push(node);
+    }
+
+    /// Computes the effective priority of a job at the given time with the scheduling policy.
+    ///
+    /// # Arguments
+    /// `node`: The job, as returned by `peek` or `peek_n`.
+    /// `timestamp`: The current time, on the same clock as the enqueue times of the jobs.
+    pub fn effective_priority(&self, node: &HeapNode<K, V>, timestamp: u64) -> u32 {
+        self.policy
+            .effective_priority(node.priority, timestamp.saturating_sub(node.enqueue_time))
+    }
+
+    /// Extracts the top node from the heap.
+    pub fn get_top(&mut self) -> Option<HeapNode<K, V>> {
+        let band: u32 = self.select(self.seed)?;
+        self.seed = next_seed(self.seed);
+
+        self.bands.get_mut(&band)?.pop()
+    }
+
+    /// Removes the job with the given id from the heap, returning the removed node.
+    /// The node is located through the index map of its band, so removal is O(log n).
+    pub fn remove(&mut self, job_id: &K) -> Option<HeapNode<K, V>> {
+        self.bands.values_mut().find_map(|band| band.remove(job_id))
+    }
+
+    /// Retrieves the value of the top node to see if there is a node in the heap.
+    pub fn peek(&self) -> Option<&HeapNode<K, V>> {
+        let band: u32 = self.select(self.seed)?;
+        self.bands.get(&band)?.heap.front()
+    }
+
+    /// Retrieves the `n` nodes that the next `n` calls to `get_top` would return, in order,
+    /// without removing them.
+    /// Only the frontier of each band is explored, so this is O(n log n) regardless of the size
+    /// of the heap.
+    pub fn peek_n(&self, n: usize) -> Vec<&HeapNode<K, V>> {
+        let mut top: Vec<&HeapNode<K, V>> = Vec::with_capacity(n.min(self.len()));
+        let mut frontiers: BTreeMap<u32, Frontier> = BTreeMap::new();
+        let mut seed: u64 = self.seed;
+
+        for (band, b) in self.bands.iter() {
+            if let Some(root) = b.heap.front() {
+                frontiers.insert(
+                    *band,
+                    BinaryHeap::from([Reverse((Rank(root.rank), root.enqueue_time, 0))]),
+                );
+            }
+        }
+
+        // Replay the choices `get_top` would make, with the same random rolls
+        while top.len() < n {
+            let candidates: Vec<Candidate> = frontiers
+                .iter()
+                .filter_map(|(band, frontier)| {
+                    let Reverse((_, _, i)) = frontier.peek()?;
+                    Some(self.bands[band].heap[*i].candidate(*band))
+                })
+                .collect();
+
+            let band: u32 = match self.choose(&candidates, seed) {
+                Some(b) => b,
+                None => break,
+            };
+            seed = next_seed(seed);
+
+            let heap: &VecDeque<HeapNode<K, V>> = &self.bands[&band].heap;
+            let frontier: &mut Frontier = match frontiers.get_mut(&band) {
+                Some(f) => f,
+                None => break,
+            };
+            let index: usize = match frontier.pop() {
+                Some(Reverse((_, _, i))) => i,
+                None => break,
+            };
+            top.push(&heap[index]);
+
+            for child in [2 * index + 1, 2 * index + 2] {
+                if let Some(node) = heap.get(child) {
+                    frontier.push(Reverse((Rank(node.rank), node.enqueue_time, child)));
+                }
+            }
+        }
+
+        top
+    }
+
+    /// Changes the priority of a `HeapNode`. The job keeps its enqueue time and with it the aging
+    /// it has earned, so its effective priority moves by as much as its priority does. It is
+    /// moved to another band if the policy places its new priority elsewhere. The node is
+    /// located through the index map of its band, so the change is O(log n).
+    ///
+    /// # Returns
+    /// Whether the job was in the heap.
+    pub fn change_priority(&mut self, job_id: &K, new_priority: u32) -> bool {
+        let new_band: u32 = self.policy.band(new_priority);
+        let band: u32 = match self
+            .bands
+            .iter()
+            .find(|(_, b)| b.index.contains_key(job_id))
+        {
+            Some((band, _)) => *band,
+            None => return false,
+        };
+
+        if band != new_band {
+            let mut node: HeapNode<K, V> =
+                match self.bands.get_mut(&band).and_then(|b| b.remove(job_id)) {
+                    Some(n) => n,
+                    None => return false,
+                };
+            node.priority = new_priority;
+            node.rank = self
+                .policy
+                .rank(new_priority, node.enqueue_time, node.deadline);
+            self.bands
+                .entry(new_band)
+                .or_insert_with(Band::new)
+                .push(node);
+
+            return true;
+        }
+
+        let b: &mut Band<K, V> = match self.bands.get_mut(&band) {
+            Some(b) => b,
+            None => return false,
+        };
+        let target_index: usize = b.index[job_id];
+        let target: &mut HeapNode<K, V> = &mut b.heap[target_index];
+        let old_rank: f64 = target.rank;
+
+        target.priority = new_priority;
+        target.rank = self
+            .policy
+            .rank(new_priority, target.enqueue_time, target.deadline);
+
+        // A lower rank moves the job towards the top of the min heap
+        if target.rank < old_rank {
+            b.bubble_up(target_index);
+        } else {
+            b.bubble_down(target_index);
+        }
+
+        true
+    }
+
+    /// The band the next job is taken from, None if the queue is empty.
+    fn select(&self, roll: u64) -> Option<u32> {
+        let candidates: Vec<Candidate> = self
+            .bands
+            .iter()
+            .filter_map(|(band, b)| b.heap.front().map(|node| node.candidate(*band)))
+            .collect();
+
+        self.choose(&candidates, roll)
+    }
+
+    /// Asks the policy to pick one of the candidates, unless there is only one to pick from.
+    fn choose(&self, candidates: &[Candidate], seed: u64) -> Option<u32> {
+        match candidates.len() {
+            0 => None,
+            1 => Some(candidates[0].band),
+            _ => {
+                let roll: u64 = seed.wrapping_mul(0x2545_F491_4F6C_DD1D);
+                let chosen: usize = self.policy.select(candidates, self.latest, roll);
+                candidates.get(chosen).or(candidates.last()).map(|c| c.band)
+            }
+        }
+    }
+}
+
+impl<K: Eq + Hash + Clone, V> QueueBackend<K, V> for PriorityQueue<K, V> {
+    fn len(&self) -> usize {
+        PriorityQueue::len(self)
+    }
+
+    fn push(&mut self, priority: u32, job_id: K, timestamp: u64, value: V) {
+        PriorityQueue::push(self, priority, job_id, timestamp, value)
+    }
+
+    fn get_top(&mut self) -> Option<HeapNode<K, V>> {
+        PriorityQueue::get_top(self)
+    }
+
+    fn peek(&self) -> Option<&HeapNode<K, V>> {
+        PriorityQueue::peek(self)
+    }
+
+    fn remove(&mut self, job_id: &K) -> Option<HeapNode<K, V>> {
+        PriorityQueue::remove(self, job_id)
+    }
+
+    fn change_priority(&mut self, job_id: &K, new_priority: u32) -> bool {
+        PriorityQueue::change_priority(self, job_id, new_priority)
+    }
+
+    fn effective_priority(&self, node: &HeapNode<K, V>, timestamp: u64) -> u32 {
+        PriorityQueue::effective_priority(self, node, timestamp)
+    }
+}
+
+/// Advances the xorshift random number generator behind the rolls offered to the policy.
+fn next_seed(seed: u64) -> u64 {
+    let mut x: u64 = seed;
+    x ^= x >> 12;
+    x ^= x << 25;
+    x ^= x >> 27;
+    x
+}
+
+impl<K: Eq + Hash + Clone, V> Band<K, V> {
+    fn new() -> Self {
+        Band {
+            heap: VecDeque::new(),
+            index: HashMap::new(),
+        }
+    }
+
+    fn push(&mut self, node: HeapNode<K, V>) {
+        self.index.insert(node.job_id.clone(), self.heap.len());
+        self.heap.push_back(node);
+        self.bubble_up(self.heap.len() - 1);
+    }
+
+    /// Extracts the top node from the heap.
+    fn pop(&mut self) -> Option<HeapNode<K, V>> {
+        if self.heap.is_empty() {
+            return None;
+        }
+
+        let last_index: usize = self.heap.len() - 1;
+        self.swap(0, last_index);
+        let top: HeapNode<K, V> = self.heap.pop_back()?;
+        self.index.remove(&top.job_id);
+
+        self.bubble_down(0);
+        Some(top)
+    }
+
+    fn remove(&mut self, job_id: &K) -> Option<HeapNode<K, V>> {
+        let target_index: usize = *self.index.get(job_id)?;
+        let last_index: usize = self.heap.len() - 1;
+
+        self.swap(target_index, last_index);
+        let node: HeapNode<K, V> = self.heap.pop_back()?;
+        self.index.remove(&node.job_id);
+
+        // The node moved into the gap may belong either above or below it
+        if target_index < self.heap.len() {
+            self.bubble_up(target_index);
+            self.bubble_down(target_index);
+        }
+
+        Some(node)
+    }
+
+    fn bubble_up(&mut self, index: usize) {
+        let mut i = index;
+        loop {
+            let current = match self.heap.get(i) {
+                Some(c) => c,
+                None => return,
+            };
+
+            let parent = match self.get_parent(i) {
+                Some(p) => p,
+                None => return,
+            };
+
+            if current.precedes(parent) {
+                let parent: usize = (i - 1) / 2;
+                self.swap(i, parent);
+
+                i = parent;
+            } else {
+                return;
+            }
+        }
+    }
+
+    fn bubble_down(&mut self, index: usize) {
+        let mut min: usize = index;
+
+        let current: &HeapNode<K, V> = match self.heap.get(index) {
+            Some(c) => c,
+            None => return,
+        };
+
+        let left_index: usize = (index * 2) + 1;
+        let right_index: usize = (index * 2) + 2;
+
+        let (left_child, right_child) = self.get_children(index);
+
+        if let Some(left) = left_child {
+            if left.precedes(current) {
+                min = left_index;
+            }
+        }
+
+        // The right child only moves up if it is smaller than both the node and its sibling
+        if let Some(right) = right_child {
+            if right.precedes(&self.heap[min]) {
+                min = right_index;
+            }
+        }
+
+        if index != min {
+            self.swap(index, min);
+
+            self.bubble_down(min);
+        }
+    }
+
+    /// Fetches the child of the node at the given index, if there are any.
+    /// Returns a tuple holding references to the children (left_child, right_child)
+    #[allow(clippy::type_complexity)]
+    fn get_children(
+        &self,
+        current_index: usize,
+    ) -> (Option<&HeapNode<K, V>>, Option<&HeapNode<K, V>>) {
+        let left_child: usize = (current_index * 2) + 1;
+        let right_child: usize = (current_index * 2) + 2;
+
+        (self.heap.get(left_child), self.heap.get(right_child))
+    }
+
+    /// Returns a reference to the parent node for the child at the provided index.
+    fn get_parent(&self, current_index: usize) -> Option<&HeapNode<K, V>> {
+        if current_index == 0 {
+            return None;
+        }
+        self.heap.get((current_index - 1) / 2)
+    }
+
+    /// Swaps the nodes at the two indices and updates their positions in the index map.
+    fn swap(&mut self, a: usize, b: usize) {
+        self.heap.swap(a, b);
+        self.index.insert(self.heap[a].job_id.clone(), a);
+        self.index.insert(self.heap[b].job_id.clone(), b);
+    }
+}
+
+#[cfg(test)]
+impl<K: Eq + Hash + Clone + fmt::Debug, V> PriorityQueue<K, V> {
+    /// Panics unless every band is a min heap whose index map holds the position of each of its
+    /// nodes, and every job is in the band the policy places it in.
+    pub(crate) fn assert_invariants(&self) {
+        for (band, b) in self.bands.iter() {
+            assert_eq!(b.index.len(), b.heap.len(), "band {} index size", band);
+
+            for (i, node) in b.heap.iter().enumerate() {
+                assert_eq!(
+                    b.index.get(&node.job_id),
+                    Some(&i),
+                    "index of {:?}",
+                    node.job_id
+                );
+                assert_eq!(
+                    self.policy.band(node.priority),
+                    *band,
+                    "band of {:?}",
+                    node.job_id
+                );
+
+                if let Some(parent) = b.get_parent(i) {
+                    assert!(
+                        !node.precedes(parent),
+                        "{:?} precedes its parent {:?}",
+                        node.job_id,
+                        parent.job_id
+                    );
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{HeapNode, PriorityQueue};
+    use crate::policy::{LinearAging, NoAging, PriorityBand, StrictPriority, WeightedBands};
+
+    #[test]
+    fn test_heap_insert() {
+        let mut min_heap: PriorityQueue<u64> = PriorityQueue::new(NoAging);
+        // priority,job_id
+        min_heap.insert(5, 1, 0);
+        assert_eq!(min_heap.bands[&0].heap, vec![HeapNode::new(1, 5, 0)]);
+
+        min_heap.insert(3, 2, 1);
+        assert_eq!(
+            min_heap.bands[&0].heap,
+            vec![HeapNode::new(2, 3, 1), HeapNode::new(1, 5, 0)]
+        );
+
+        min_heap.insert(2, 3, 2);
+        assert_eq!(
+            min_heap.bands[&0].heap,
+            vec![
+                HeapNode::new(3, 2, 2),
+                HeapNode::new(1, 5, 0),
+                HeapNode::new(2, 3, 1)
+            ]
+        );
+
+        min_heap.insert(1, 4, 3);
+        assert_eq!(
+            min_heap.bands[&0].heap,
+            vec![
+                HeapNode::new(4, 1, 3),
+                HeapNode::new(3, 2, 2),
+                HeapNode::new(2, 3, 1),
+                HeapNode::new(1, 5, 0)
+            ]
+        );
+
+        min_heap.insert(4, 5, 4);
+        assert_eq!(
+            min_heap.bands[&0].heap,
+            vec![
+                HeapNode::new(4, 1, 3),
+                HeapNode::new(3, 2, 2),
+                HeapNode::new(2, 3, 1),
+                HeapNode::new(1, 5, 0),
+                HeapNode::new(5, 4, 4)
+            ]
+        );
+
+        min_heap.insert(2, 6, 5);
+        assert_eq!(
+            min_heap.bands[&0].heap,
+            vec![
+                HeapNode::new(4, 1, 3),
+                HeapNode::new(3, 2, 2),
+                HeapNode::new(6, 2, 5),
+                HeapNode::new(1, 5, 0),
+                HeapNode::new(5, 4, 4),
+                HeapNode::new(2, 3, 1),
+            ]
+        );
+
+        min_heap.insert(2, 7, 6);
+        assert_eq!(
+            min_heap.bands[&0].heap,
+            vec![
+                HeapNode::new(4, 1, 3),
+                HeapNode::new(3, 2, 2),
+                HeapNode::new(6, 2, 5),
+                HeapNode::new(1, 5, 0),
+                HeapNode::new(5, 4, 4),
+                HeapNode::new(2, 3, 1),
+                HeapNode::new(7, 2, 6)
+            ]
+        );
+
+        min_heap.insert(2, 8, 7);
+        assert_eq!(
+            min_heap.bands[&0].heap,
+            vec![
+                HeapNode::new(4, 1, 3),
+                HeapNode::new(3, 2, 2),
+                HeapNode::new(6, 2, 5),
+                HeapNode::new(8, 2, 7),
+                HeapNode::new(5, 4, 4),
+                HeapNode::new(2, 3, 1),
+                HeapNode::new(7, 2, 6),
+                HeapNode::new(1, 5, 0),
+            ]
+        );
+
+        min_heap.insert(1, 9, 8);
+        assert_eq!(
+            min_heap.bands[&0].heap,
+            vec![
+                HeapNode::new(4, 1, 3),
+                HeapNode::new(9, 1, 8),
+                HeapNode::new(6, 2, 5),
+                HeapNode::new(3, 2, 2),
+                HeapNode::new(5, 4, 4),
+                HeapNode::new(2, 3, 1),
+                HeapNode::new(7, 2, 6),
+                HeapNode::new(1, 5, 0),
+                HeapNode::new(8, 2, 7),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_extraction() {
+        let mut min_heap: PriorityQueue<u64> = PriorityQueue::new(NoAging);
+        // priority,job_id
+        min_heap.insert(5, 1, 0);
+        min_heap.insert(3, 2, 1);
+        min_heap.insert(2, 3, 2);
+        min_heap.insert(1, 4, 3);
+        min_heap.insert(4, 5, 4);
+        min_heap.insert(2, 6, 5);
+        min_heap.insert(2, 7, 6);
+        min_heap.insert(2, 8, 7);
+        min_heap.insert(1, 9, 8);
+
+        assert_eq!(min_heap.peek().unwrap().priority, 1);
+        assert_eq!(min_heap.get_top().unwrap().priority, 1);
+        assert_eq!(
+            min_heap.bands[&0].heap,
+            vec![
+                HeapNode::new(9, 1, 8),
+                HeapNode::new(3, 2, 2),
+                HeapNode::new(6, 2, 5),
+                HeapNode::new(8, 2, 7),
+                HeapNode::new(5, 4, 4),
+                HeapNode::new(2, 3, 1),
+                HeapNode::new(7, 2, 6),
+                HeapNode::new(1, 5, 0)
+            ]
+        );
+
+        assert_eq!(min_heap.peek().unwrap().priority, 1);
+        assert_eq!(min_heap.get_top().unwrap().priority, 1);
+        assert_eq!(
+            min_heap.bands[&0].heap,
+            vec![
+                HeapNode::new(3, 2, 2),
+                HeapNode::new(8, 2, 7),
+                HeapNode::new(6, 2, 5),
+                HeapNode::new(1, 5, 0),
+                HeapNode::new(5, 4, 4),
+                HeapNode::new(2, 3, 1),
+                HeapNode::new(7, 2, 6)
+            ]
+        );
+
+        assert_eq!(min_heap.peek().unwrap().priority, 2);
+        assert_eq!(min_heap.get_top().unwrap().priority, 2);
+        assert_eq!(
+            min_heap.bands[&0].heap,
+            vec![
+                HeapNode::new(6, 2, 5),
+                HeapNode::new(8, 2, 7),
+                HeapNode::new(7, 2, 6),
+                HeapNode::new(1, 5, 0),
+                HeapNode::new(5, 4, 4),
+                HeapNode::new(2, 3, 1)
+            ]
+        );
+
+        assert_eq!(min_heap.peek().unwrap().priority, 2);
+        assert_eq!(min_heap.get_top().unwrap().priority, 2);
+        assert_eq!(
+            min_heap.bands[&0].heap,
+            vec![
+                HeapNode::new(7, 2, 6),
+                HeapNode::new(8, 2, 7),
+                HeapNode::new(2, 3, 1),
+                HeapNode::new(1, 5, 0),
+                HeapNode::new(5, 4, 4)
+            ]
+        );
+
+        assert_eq!(min_heap.peek().unwrap().priority, 2);
+        assert_eq!(min_heap.get_top().unwrap().priority, 2);
+        assert_eq!(
+            min_heap.bands[&0].heap,
+            vec![
+                HeapNode::new(8, 2, 7),
+                HeapNode::new(5, 4, 4),
+                HeapNode::new(2, 3, 1),
+                HeapNode::new(1, 5, 0)
+            ]
+        );
+
+        assert_eq!(min_heap.peek().unwrap().priority, 2);
+        assert_eq!(min_heap.get_top().unwrap().priority, 2);
+        assert_eq!(
+            min_heap.bands[&0].heap,
+            vec![
+                HeapNode::new(2, 3, 1),
+                HeapNode::new(5, 4, 4),
+                HeapNode::new(1, 5, 0)
+            ]
+        );
+
+        assert_eq!(min_heap.peek().unwrap().priority, 3);
+        assert_eq!(min_heap.get_top().unwrap().priority, 3);
+        assert_eq!(
+            min_heap.bands[&0].heap,
+            vec![HeapNode::new(5, 4, 4), HeapNode::new(1, 5, 0)]
+        );
+
+        assert_eq!(min_heap.peek().unwrap().priority, 4);
+        assert_eq!(min_heap.get_top().unwrap().priority, 4);
+        assert_eq!(min_heap.bands[&0].heap, vec![HeapNode::new(1, 5, 0),]);
+
+        assert_eq!(min_heap.peek().unwrap().priority, 5);
+        assert_eq!(min_heap.get_top().unwrap().priority, 5);
+        assert!(min_heap.bands[&0].heap.is_empty());
+
+        assert!(min_heap.peek().is_none());
+        assert!(min_heap.get_top().is_none());
+        assert!(min_heap.bands[&0].heap.is_empty());
+    }
+
+    #[test]
+    fn test_remove() {
+        let ids: Vec<u64> = (1..=5).collect();
+
+        let mut min_heap: PriorityQueue<u64> = PriorityQueue::new(NoAging);
+        min_heap.insert(5, ids[0], 0);
+        min_heap.insert(3, ids[1], 1);
+        min_heap.insert(2, ids[2], 2);
+        min_heap.insert(1, ids[3], 3);
+        min_heap.insert(4, ids[4], 4);
+
+        assert_eq!(min_heap.remove(&ids[2]).unwrap().job_id, ids[2]);
+        assert!(min_heap.remove(&ids[2]).is_none());
+        assert_eq!(min_heap.len(), 4);
+
+        assert_eq!(min_heap.remove(&ids[3]).unwrap().priority, 1);
+        assert_eq!(min_heap.peek().unwrap().job_id, ids[1]);
+
+        assert_eq!(min_heap.get_top().unwrap().job_id, ids[1]);
+        assert_eq!(min_heap.get_top().unwrap().job_id, ids[4]);
+        assert_eq!(min_heap.remove(&ids[0]).unwrap().priority, 5);
+        assert!(min_heap.bands[&0].heap.is_empty());
+        assert!(min_heap.remove(&ids[0]).is_none());
+    }
+
+    #[test]
+    fn test_peek_n() {
+        let ids: Vec<u64> = (1..=6).collect();
+
+        let mut min_heap: PriorityQueue<u64> = PriorityQueue::new(NoAging);
+        assert!(min_heap.peek_n(3).is_empty());
+
+        min_heap.insert(5, ids[0], 0);
+        min_heap.insert(3, ids[1], 1);
+        min_heap.insert(2, ids[2], 2);
+        min_heap.insert(1, ids[3], 3);
+        min_heap.insert(4, ids[4], 4);
+        min_heap.insert(6, ids[5], 5);
+
+        let top: Vec<u64> = min_heap.peek_n(4).iter().map(|n| n.job_id).collect();
+        assert_eq!(top, vec![ids[3], ids[2], ids[1], ids[4]]);
+        assert_eq!(min_heap.peek_n(10).len(), 6);
+        assert_eq!(min_heap.len(), 6);
+
+        for id in top {
+            assert_eq!(min_heap.get_top().unwrap().job_id, id);
+        }
+    }
+
+    #[test]
+    fn test_aging_and_values() {
+        let mut min_heap: PriorityQueue<&str, Vec<u8>> = PriorityQueue::new(LinearAging::new(0.5));
+        min_heap.push(4, "old", 0, b"old".to_vec());
+        min_heap.push(4, "mid", 6, b"mid".to_vec());
+        min_heap.push(2, "new", 8, b"new".to_vec());
+
+        min_heap.get_mut(&"mid").unwrap().push(b'!');
+        assert!(min_heap.get_mut(&"gone").is_none());
+
+        // By the time the newest job is enqueued the oldest one has aged past both of them,
+        // without the heap being reordered
+        let mut order: Vec<(&str, u32, Vec<u8>)> = Vec::new();
+        while let Some(top) = min_heap.peek() {
+            let effective_priority: u32 = min_heap.effective_priority(top, 8);
+            let top = min_heap.get_top().unwrap();
+            order.push((top.job_id, effective_priority, top.value));
+        }
+        assert_eq!(
+            order,
+            vec![
+                ("old", 0, b"old".to_vec()),
+                ("new", 2, b"new".to_vec()),
+                ("mid", 3, b"mid!".to_vec()),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_change_priority() {
+        let ids: Vec<u64> = (1..=6).collect();
+
+        let mut min_heap: PriorityQueue<u64> = PriorityQueue::new(LinearAging::new(0.5));
+        for (i, priority) in [3, 1, 4, 2, 5, 3].into_iter().enumerate() {
+            min_heap.insert(priority, ids[i], 0);
+        }
+        assert!(!min_heap.change_priority(&7, 1));
+
+        // Raising the priority value sinks the top job, lowering it brings a job to the top
+        assert!(min_heap.change_priority(&ids[1], 5));
+        assert_eq!(min_heap.peek().unwrap().job_id, ids[3]);
+        assert!(min_heap.change_priority(&ids[4], 0));
+        assert_eq!(min_heap.peek().unwrap().job_id, ids[4]);
+
+        // The index map still points at every job after the moves
+        assert_eq!(min_heap.remove(&ids[1]).unwrap().priority, 5);
+        let order: Vec<u64> = std::iter::from_fn(|| min_heap.get_top())
+            .map(|n| n.job_id)
+            .collect();
+        assert_eq!(order, vec![ids[4], ids[3], ids[0], ids[5], ids[2]]);
+    }
+
+    #[test]
+    fn test_change_priority_keeps_aging() {
+        let mut min_heap: PriorityQueue<u64> = PriorityQueue::new(LinearAging::new(1.0));
+        min_heap.insert(5, 1, 0);
+        min_heap.insert(4, 2, 3);
+        let top = min_heap.peek().unwrap();
+        assert_eq!((top.job_id, min_heap.effective_priority(top, 3)), (1, 2));
+
+        // Job 1 has aged by 3, so moving it to priority 4 keeps it ahead of job 2
+        min_heap.change_priority(&1, 4);
+        let top = min_heap.peek().unwrap();
+        assert_eq!(
+            (
+                top.job_id,
+                top.priority,
+                min_heap.effective_priority(top, 3)
+            ),
+            (1, 4, 1)
+        );
+
+        // Moving it behind job 2 is undone by the aging it keeps earning
+        min_heap.change_priority(&1, 8);
+        assert_eq!(min_heap.peek().unwrap().job_id, 2);
+        assert_eq!(
+            min_heap.effective_priority(min_heap.peek().unwrap(), 100),
+            0
+        );
+        assert_eq!(min_heap.get_top().unwrap().job_id, 2);
+        assert_eq!(min_heap.get_top().unwrap().job_id, 1);
+    }
+
+    #[test]
+    fn test_aging_without_rescanning() {
+        let mut min_heap: PriorityQueue<u64> = PriorityQueue::new(LinearAging::new(0.4));
+        // A steady stream of urgent jobs arrives after one low priority job
+        min_heap.insert(10, 0, 0);
+        for t in 1..=60 {
+            min_heap.insert(1, t, t);
+        }
+
+        // The low priority job is handed out once the urgent jobs ahead of it waited less than
+        // it has aged, (10 - 1) / 0.4 = 22.5 ticks later
+        let order: Vec<u64> = std::iter::from_fn(|| min_heap.get_top())
+            .map(|n| n.job_id)
+            .collect();
+        let position: usize = order.iter().position(|id| *id == 0).unwrap();
+        assert_eq!(position, 22);
+        assert_eq!(order[..position], (1..=22).collect::<Vec<u64>>()[..]);
+    }
+
+    #[test]
+    fn test_strict_priority_bands() {
+        let mut min_heap: PriorityQueue<u64> = PriorityQueue::new(StrictPriority::new(10));
+        min_heap.insert(5, 1, 0);
+        min_heap.insert(3, 2, 1);
+        min_heap.insert(1, 3, 2);
+        min_heap.insert(1, 4, 3);
+        assert_eq!(min_heap.peek().unwrap().job_id, 3);
+
+        // Job 1 starves once a job arrives 10 ticks after it and jumps every more urgent job,
+        // job 2 has only waited 9
+        min_heap.insert(1, 5, 10);
+        let order: Vec<u64> = min_heap.peek_n(5).iter().map(|n| n.job_id).collect();
+        assert_eq!(order, vec![1, 3, 4, 5, 2]);
+
+        // Moving a job to another priority moves it to that priority's band
+        assert!(min_heap.change_priority(&2, 0));
+        let order: Vec<u64> = std::iter::from_fn(|| min_heap.get_top())
+            .map(|n| n.job_id)
+            .collect();
+        assert_eq!(order, vec![1, 2, 3, 4, 5]);
+    }
+
+    #[test]
+    fn test_weighted_bands() {
+        let mut min_heap: PriorityQueue<u64> = PriorityQueue::new(WeightedBands::new(vec![
+            PriorityBand {
+                max_priority: 2,
+                weight: 3.0,
+            },
+            PriorityBand {
+                max_priority: 5,
+                weight: 1.0,
+            },
+        ]));
+        for id in 0..400 {
+            min_heap.insert(if id % 2 == 0 { 1 } else { 4 }, id, id);
+        }
+
+        // `peek_n` replays the same random choices `get_top` makes
+        let peeked: Vec<u64> = min_heap.peek_n(200).iter().map(|n| n.job_id).collect();
+        let taken: Vec<u64> = (0..200)
+            .map(|_| min_heap.get_top().unwrap().job_id)
+            .collect();
+        assert_eq!(peeked, taken);
+
+        // Both bands are served, roughly 3 to 1, while both have jobs
+        let urgent: usize = taken.iter().filter(|id| *id % 2 == 0).count();
+        assert!((130..=170).contains(&urgent), "{} urgent jobs", urgent);
+        assert_eq!(min_heap.len(), 200);
+    }
+}
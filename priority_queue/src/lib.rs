@@ -0,0 +1,16 @@
+pub mod backend;
+pub mod bucket;
+pub mod heap;
+pub mod policy;
+#[cfg(test)]
+mod proptests;
+pub mod sharded;
+
+pub use backend::QueueBackend;
+pub use bucket::BucketQueue;
+pub use heap::{HeapNode, PriorityQueue};
+pub use policy::{
+    Candidate, EarliestDeadline, ExponentialAging, LinearAging, NoAging, PriorityBand,
+    SchedulingPolicy, StrictPriority, WeightedBands,
+};
+pub use sharded::ShardedQueue;
@@ -0,0 +1,345 @@
+use std::fmt::Debug;
+
+/// SchedulingPolicy decides the order jobs are handed out in. Lower effective priorities are
+/// handed out first.
+///
+/// Jobs are split into bands by `band`, and each band is a min heap ordered by `rank`. On every
+/// dequeue the policy is offered the next job of every non-empty band and `select` picks the one
+/// handed out. Most policies only use a single band, in which case `select` is never called.
+pub trait SchedulingPolicy: Debug + Send + Sync {
+    /// Computes the effective priority of a job, as reported to operators.
+    ///
+    /// # Arguments
+    /// `priority`: The original priority of the job.
+    /// `waited`: The logical time that has passed since the job was enqueued.
+    fn effective_priority(&self, priority: u32, waited: u64) -> u32;
+
+    /// Computes the key a job is ordered by within its band. Jobs with a lower rank have a lower
+    /// effective priority at every point in time, so the rank is computed once when the job is
+    /// enqueued and a band never has to be reordered as time passes.
+    ///
+    /// # Arguments
+    /// `priority`: The original priority of the job.
+    /// `enqueue_time`: The logical time at which the job was enqueued.
+    /// `deadline`: The logical time by which the job should be handed out, if it has one.
+    fn rank(&self, priority: u32, enqueue_time: u64, deadline: Option<u64>) -> f64;
+
+    /// The band a job with the given priority is placed in.
+    fn band(&self, _priority: u32) -> u32 {
+        0
+    }
+
+    /// Picks which of the next jobs of the non-empty bands is handed out, the one with the lowest
+    /// rank by default.
+    ///
+    /// # Arguments
+    /// `candidates`: The next job of every non-empty band, in band order. Never empty.
+    /// `now`: The latest logical time the queue has seen.
+    /// `roll`: A uniformly distributed random number, for policies that choose at random.
+    ///
+    /// # Returns
+    /// The index of the chosen candidate.
+    fn select(&self, candidates: &[Candidate], _now: u64, _roll: u64) -> usize {
+        candidates
+            .iter()
+            .enumerate()
+            .min_by(|(_, a), (_, b)| a.rank.total_cmp(&b.rank))
+            .map_or(0, |(i, _)| i)
+    }
+}
+
+/// Candidate is the next job of a band, offered to `SchedulingPolicy::select`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Candidate {
+    pub band: u32,
+    pub priority: u32,
+    pub enqueue_time: u64,
+    pub rank: f64,
+}
+
+/// LinearAging lowers the effective priority of a job by `factor` for every tick it waits.
+/// `factor`: How much the effective priority drops per unit of logical time.
+#[derive(Debug, Clone, Copy)]
+pub struct LinearAging {
+    pub factor: f32,
+}
+
+impl LinearAging {
+    pub fn new(factor: f32) -> Self {
+        LinearAging { factor }
+    }
+}
+
+impl SchedulingPolicy for LinearAging {
+    fn effective_priority(&self, priority: u32, waited: u64) -> u32 {
+        priority.saturating_sub((self.factor * waited as f32) as u32)
+    }
+
+    // priority - factor * (now - enqueue_time) only depends on the job through
+    // priority + factor * enqueue_time, the same `now` is subtracted from every job
+    fn rank(&self, priority: u32, enqueue_time: u64, _deadline: Option<u64>) -> f64 {
+        priority as f64 + self.factor as f64 * enqueue_time as f64
+    }
+}
+
+/// ExponentialAging halves the effective priority of a job every `half_life` ticks it waits, so
+/// jobs with a large priority value catch up on urgent ones faster than under linear aging.
+/// `half_life`: The logical time it takes for the effective priority to halve, greater than 0.
+#[derive(Debug, Clone, Copy)]
+pub struct ExponentialAging {
+    pub half_life: f64,
+}
+
+impl ExponentialAging {
+    pub fn new(half_life: f64) -> Self {
+        ExponentialAging { half_life }
+    }
+}
+
+impl SchedulingPolicy for ExponentialAging {
+    fn effective_priority(&self, priority: u32, waited: u64) -> u32 {
+        (priority as f64 / (waited as f64 / self.half_life).exp2()) as u32
+    }
+
+    // log2(priority / 2^((now - enqueue_time) / half_life)) only depends on the job through
+    // log2(priority) + enqueue_time / half_life. A priority of 0 ranks ahead of everything.
+    fn rank(&self, priority: u32, enqueue_time: u64, _deadline: Option<u64>) -> f64 {
+        (priority as f64).log2() + enqueue_time as f64 / self.half_life
+    }
+}
+
+/// NoAging keeps every job at its original priority, however long it waits.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct NoAging;
+
+impl SchedulingPolicy for NoAging {
+    fn effective_priority(&self, priority: u32, _waited: u64) -> u32 {
+        priority
+    }
+
+    fn rank(&self, priority: u32, _enqueue_time: u64, _deadline: Option<u64>) -> f64 {
+        priority as f64
+    }
+}
+
+/// StrictPriority always hands out the most urgent job, oldest first within a priority, unless a
+/// job has waited `max_wait` ticks or more. Starved jobs are handed out first, oldest first.
+/// Waiting is measured against the latest job enqueued, as a job can only be starved by newer
+/// jobs arriving.
+/// `max_wait`: The logical time after which a job is handed out ahead of more urgent ones.
+#[derive(Debug, Clone, Copy)]
+pub struct StrictPriority {
+    pub max_wait: u64,
+}
+
+impl StrictPriority {
+    pub fn new(max_wait: u64) -> Self {
+        StrictPriority { max_wait }
+    }
+}
+
+impl SchedulingPolicy for StrictPriority {
+    fn effective_priority(&self, priority: u32, waited: u64) -> u32 {
+        if waited >= self.max_wait {
+            0
+        } else {
+            priority
+        }
+    }
+
+    // Every priority has its own band, so the oldest job of each priority is a candidate
+    fn rank(&self, _priority: u32, enqueue_time: u64, _deadline: Option<u64>) -> f64 {
+        enqueue_time as f64
+    }
+
+    fn band(&self, priority: u32) -> u32 {
+        priority
+    }
+
+    fn select(&self, candidates: &[Candidate], now: u64, _roll: u64) -> usize {
+        let starved: Option<usize> = candidates
+            .iter()
+            .enumerate()
+            .filter(|(_, c)| now.saturating_sub(c.enqueue_time) >= self.max_wait)
+            .min_by_key(|(_, c)| c.enqueue_time)
+            .map(|(i, _)| i);
+
+        // Candidates are in band order, which is priority order
+        starved.unwrap_or(0)
+    }
+}
+
+/// PriorityBand is a range of priorities that share a weight under `WeightedBands`.
+/// `max_priority`: The largest priority in the band, the band starts after the previous one.
+/// `weight`: The share of dequeues the band receives while it has jobs.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PriorityBand {
+    pub max_priority: u32,
+    pub weight: f32,
+}
+
+/// WeightedBands splits priorities into bands and picks the band each job is taken from at
+/// random, in proportion to the weights of the bands that have jobs, so every band keeps being
+/// served however many urgent jobs arrive. Within a band jobs are handed out by priority.
+/// `bands`: The bands in increasing priority order, at least one with a weight greater than 0.
+/// Priorities past the last band fall into it.
+#[derive(Debug, Clone)]
+pub struct WeightedBands {
+    pub bands: Vec<PriorityBand>,
+}
+
+impl WeightedBands {
+    pub fn new(bands: Vec<PriorityBand>) -> Self {
+        WeightedBands { bands }
+    }
+}
+
+impl SchedulingPolicy for WeightedBands {
+    fn effective_priority(&self, priority: u32, _waited: u64) -> u32 {
+        priority
+    }
+
+    fn rank(&self, priority: u32, _enqueue_time: u64, _deadline: Option<u64>) -> f64 {
+        priority as f64
+    }
+
+    fn band(&self, priority: u32) -> u32 {
+        self.bands
+            .iter()
+            .position(|band| priority <= band.max_priority)
+            .unwrap_or(self.bands.len().saturating_sub(1)) as u32
+    }
+
+    fn select(&self, candidates: &[Candidate], _now: u64, roll: u64) -> usize {
+        let weight = |c: &Candidate| -> f64 {
+            self.bands
+                .get(c.band as usize)
+                .map_or(0.0, |band| band.weight.max(0.0) as f64)
+        };
+
+        let total: f64 = candidates.iter().map(weight).sum();
+        if total <= 0.0 {
+            return 0;
+        }
+
+        let mut target: f64 = roll as f64 / u64::MAX as f64 * total;
+        for (i, candidate) in candidates.iter().enumerate() {
+            target -= weight(candidate);
+            if target < 0.0 {
+                return i;
+            }
+        }
+
+        // Rounding can leave the roll just past the last band
+        candidates.len() - 1
+    }
+}
+
+/// EarliestDeadline hands out the job that is due first. Every job is due `slack` ticks per unit
+/// of priority after it was enqueued, or by its deadline if that is earlier, so both the priority
+/// and the time left until the deadline decide when a job is handed out. Deadlines are expressed
+/// on the same clock as enqueue times.
+/// `slack`: The logical time a job may wait per unit of its priority.
+#[derive(Debug, Clone, Copy)]
+pub struct EarliestDeadline {
+    pub slack: u64,
+}
+
+impl EarliestDeadline {
+    pub fn new(slack: u64) -> Self {
+        EarliestDeadline { slack }
+    }
+}
+
+impl SchedulingPolicy for EarliestDeadline {
+    fn effective_priority(&self, priority: u32, _waited: u64) -> u32 {
+        priority
+    }
+
+    fn rank(&self, priority: u32, enqueue_time: u64, deadline: Option<u64>) -> f64 {
+        let due: u64 = enqueue_time.saturating_add(self.slack.saturating_mul(priority as u64));
+        deadline.map_or(due, |deadline| deadline.min(due)) as f64
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        Candidate, EarliestDeadline, ExponentialAging, PriorityBand, SchedulingPolicy,
+        StrictPriority, WeightedBands,
+    };
+
+    fn candidate(band: u32, enqueue_time: u64) -> Candidate {
+        Candidate {
+            band,
+            priority: band,
+            enqueue_time,
+            rank: enqueue_time as f64,
+        }
+    }
+
+    #[test]
+    fn test_exponential_aging() {
+        let policy: ExponentialAging = ExponentialAging::new(10.0);
+        assert_eq!(policy.effective_priority(8, 0), 8);
+        assert_eq!(policy.effective_priority(8, 10), 4);
+        assert_eq!(policy.effective_priority(8, 30), 1);
+
+        // A priority 8 job enqueued at 0 catches up with a priority 2 job enqueued at 20
+        assert!(policy.rank(8, 0, None) == policy.rank(2, 20, None));
+        assert!(policy.rank(0, 100, None) < policy.rank(1, 0, None));
+    }
+
+    #[test]
+    fn test_strict_priority_starvation_cap() {
+        let policy: StrictPriority = StrictPriority::new(50);
+        let candidates: Vec<Candidate> = vec![candidate(1, 40), candidate(3, 5), candidate(5, 0)];
+
+        assert_eq!(policy.select(&candidates, 45, 0), 0);
+        // The priority 5 job starves first, then both are starved and the oldest wins
+        assert_eq!(policy.select(&candidates, 50, 0), 2);
+        assert_eq!(policy.select(&candidates[..2], 60, 0), 1);
+        assert_eq!(policy.effective_priority(3, 50), 0);
+    }
+
+    #[test]
+    fn test_weighted_bands() {
+        let policy: WeightedBands = WeightedBands::new(vec![
+            PriorityBand {
+                max_priority: 2,
+                weight: 3.0,
+            },
+            PriorityBand {
+                max_priority: 5,
+                weight: 1.0,
+            },
+        ]);
+        assert_eq!(
+            (
+                policy.band(1),
+                policy.band(2),
+                policy.band(3),
+                policy.band(9)
+            ),
+            (0, 0, 1, 1)
+        );
+
+        let candidates: Vec<Candidate> = vec![candidate(0, 0), candidate(1, 0)];
+        assert_eq!(policy.select(&candidates, 0, 0), 0);
+        assert_eq!(policy.select(&candidates, 0, u64::MAX / 4 * 3 - 1024), 0);
+        assert_eq!(policy.select(&candidates, 0, u64::MAX / 4 * 3 + 1024), 1);
+        assert_eq!(policy.select(&candidates, 0, u64::MAX), 1);
+        assert_eq!(policy.select(&candidates[1..], 0, 0), 0);
+    }
+
+    #[test]
+    fn test_earliest_deadline() {
+        let policy: EarliestDeadline = EarliestDeadline::new(10);
+        assert!(policy.rank(1, 0, Some(5)) < policy.rank(1, 0, None));
+        assert!(policy.rank(1, 30, None) > policy.rank(3, 0, None));
+
+        // A deadline later than the job's priority allows does not hold it back
+        assert_eq!(policy.rank(5, 0, Some(60)), policy.rank(5, 0, None));
+        assert_eq!(policy.rank(5, 0, Some(20)), 20.0);
+    }
+}
@@ -0,0 +1,231 @@
+//! Checks the queues against a reference model over random sequences of operations, asserting
+//! the structural invariants of each queue after every step.
+
+use crate::backend::QueueBackend;
+use crate::bucket::BucketQueue;
+use crate::heap::PriorityQueue;
+use crate::policy::{ExponentialAging, LinearAging, NoAging, SchedulingPolicy};
+use proptest::prelude::*;
+use proptest::test_runner::TestCaseError;
+use std::sync::Arc;
+
+/// An operation applied to both the queue and the model. Jobs are picked by their position in
+/// the model, modulo its length, so they usually exist; a pick on an empty model names a job
+/// that does not.
+#[derive(Debug, Clone)]
+enum Op {
+    Insert(u32),
+    GetTop,
+    ChangePriority(usize, u32),
+    Remove(usize),
+    PeekN(usize),
+}
+
+fn op() -> impl Strategy<Value = Op> {
+    prop_oneof![
+        4 => (0u32..=6).prop_map(Op::Insert),
+        3 => Just(Op::GetTop),
+        1 => (any::<usize>(), 0u32..=6).prop_map(|(i, p)| Op::ChangePriority(i, p)),
+        1 => any::<usize>().prop_map(Op::Remove),
+        1 => (1usize..8).prop_map(Op::PeekN),
+    ]
+}
+
+/// Aging factors whose products with the logical time are exact in floating point, so the model
+/// and the queues cannot disagree over rounding.
+fn factor() -> impl Strategy<Value = f32> {
+    prop::sample::select(vec![0.0f32, 0.125, 0.25, 0.5, 1.0, 2.0])
+}
+
+/// Model is the reference the queues are checked against: a Vec of jobs kept sorted in the order
+/// they should be handed out, by the rank the policy gives them and then by enqueue time.
+/// `jobs`: The id, priority and enqueue time of every job in the queue.
+struct Model {
+    policy: Arc<dyn SchedulingPolicy>,
+    jobs: Vec<(u64, u32, u64)>,
+}
+
+impl Model {
+    fn new(policy: Arc<dyn SchedulingPolicy>) -> Self {
+        Model {
+            policy,
+            jobs: Vec::new(),
+        }
+    }
+
+    fn sort(&mut self) {
+        let policy: Arc<dyn SchedulingPolicy> = self.policy.clone();
+        self.jobs.sort_by(|a, b| {
+            policy
+                .rank(a.1, a.2, None)
+                .total_cmp(&policy.rank(b.1, b.2, None))
+                .then(a.2.cmp(&b.2))
+        });
+    }
+
+    fn pick(&self, i: usize) -> u64 {
+        match self.jobs.len() {
+            0 => u64::MAX,
+            len => self.jobs[i % len].0,
+        }
+    }
+
+    fn effective_priorities(&self, now: u64) -> Vec<u32> {
+        self.jobs
+            .iter()
+            .map(|(_, priority, enqueue_time)| {
+                self.policy
+                    .effective_priority(*priority, now.saturating_sub(*enqueue_time))
+            })
+            .collect()
+    }
+}
+
+/// Applies the operations to the queue and the model in turn, checking after every step that
+/// the queue hands out what the model does and that its invariants hold.
+///
+/// # Arguments
+/// `queue`: The queue under test, empty.
+/// `policy`: The policy the queue orders jobs by.
+/// `ops`: The operations to apply.
+/// `check`: Asserts the invariants of the queue, along with anything only the queue supports.
+fn run<Q: QueueBackend<u64>>(
+    queue: &mut Q,
+    policy: Arc<dyn SchedulingPolicy>,
+    ops: &[Op],
+    check: impl Fn(&Q, &Model, &Op) -> Result<(), TestCaseError>,
+) -> Result<(), TestCaseError> {
+    let mut model: Model = Model::new(policy);
+
+    // Every operation advances the clock, so jobs age between steps and enqueue times are unique
+    for (tick, op) in ops.iter().enumerate() {
+        let tick: u64 = tick as u64;
+
+        match op {
+            Op::Insert(priority) => {
+                queue.push(*priority, tick, tick, ());
+                model.jobs.push((tick, *priority, tick));
+                model.sort();
+            }
+            Op::GetTop => {
+                let expected: Option<u64> =
+                    (!model.jobs.is_empty()).then(|| model.jobs.remove(0).0);
+                prop_assert_eq!(queue.get_top().map(|node| node.job_id), expected);
+            }
+            Op::ChangePriority(i, priority) => {
+                let job_id: u64 = model.pick(*i);
+                let found: Option<&mut (u64, u32, u64)> =
+                    model.jobs.iter_mut().find(|job| job.0 == job_id);
+                let expected: bool = found.is_some();
+                if let Some(job) = found {
+                    job.1 = *priority;
+                }
+                model.sort();
+
+                prop_assert_eq!(queue.change_priority(&job_id, *priority), expected);
+            }
+            Op::Remove(i) => {
+                let job_id: u64 = model.pick(*i);
+                let expected: Option<u64> = model
+                    .jobs
+                    .iter()
+                    .position(|job| job.0 == job_id)
+                    .map(|position| model.jobs.remove(position).0);
+
+                prop_assert_eq!(queue.remove(&job_id).map(|node| node.job_id), expected);
+            }
+            Op::PeekN(_) => {}
+        }
+
+        prop_assert_eq!(queue.len(), model.jobs.len());
+        prop_assert_eq!(
+            queue.peek().map(|node| node.job_id),
+            model.jobs.first().map(|job| job.0)
+        );
+        if let Some(top) = queue.peek() {
+            prop_assert_eq!(
+                queue.effective_priority(top, tick),
+                model.effective_priorities(tick)[0]
+            );
+        }
+
+        check(queue, &model, op)?;
+    }
+
+    Ok(())
+}
+
+/// The checks specific to `PriorityQueue`: its heap invariants, and `peek_n` agreeing with the
+/// model.
+fn check_heap(queue: &PriorityQueue<u64>, model: &Model, op: &Op) -> Result<(), TestCaseError> {
+    queue.assert_invariants();
+
+    if let Op::PeekN(n) = op {
+        let top: Vec<u64> = queue.peek_n(*n).iter().map(|node| node.job_id).collect();
+        let expected: Vec<u64> = model.jobs.iter().take(*n).map(|job| job.0).collect();
+        prop_assert_eq!(top, expected);
+    }
+
+    Ok(())
+}
+
+proptest! {
+    #[test]
+    fn test_linear_aging_matches_model(factor in factor(), ops in prop::collection::vec(op(), 1..200)) {
+        let policy: Arc<dyn SchedulingPolicy> = Arc::new(LinearAging::new(factor));
+        let mut queue: PriorityQueue<u64> = PriorityQueue::with_policy(policy.clone());
+
+        run(&mut queue, policy, &ops, check_heap)?;
+    }
+
+    #[test]
+    fn test_exponential_aging_matches_model(
+        half_life in prop::sample::select(vec![1.0f64, 4.0, 16.0]),
+        ops in prop::collection::vec(op(), 1..200),
+    ) {
+        let policy: Arc<dyn SchedulingPolicy> = Arc::new(ExponentialAging::new(half_life));
+        let mut queue: PriorityQueue<u64> = PriorityQueue::with_policy(policy.clone());
+
+        run(&mut queue, policy, &ops, check_heap)?;
+    }
+
+    #[test]
+    fn test_no_aging_matches_model(ops in prop::collection::vec(op(), 1..200)) {
+        let policy: Arc<dyn SchedulingPolicy> = Arc::new(NoAging);
+        let mut queue: PriorityQueue<u64> = PriorityQueue::with_policy(policy.clone());
+
+        run(&mut queue, policy, &ops, check_heap)?;
+    }
+
+    #[test]
+    fn test_bucket_queue_matches_model(factor in factor(), ops in prop::collection::vec(op(), 1..200)) {
+        let policy: Arc<dyn SchedulingPolicy> = Arc::new(LinearAging::new(factor));
+        let mut queue: BucketQueue<u64> = BucketQueue::new(6, factor);
+
+        run(&mut queue, policy, &ops, |queue, _, _| {
+            queue.assert_invariants();
+            Ok(())
+        })?;
+    }
+
+    /// Ordering jobs by rank must order them by effective priority at every point in time, which
+    /// is what lets the heap age jobs without being rebuilt.
+    #[test]
+    fn test_rank_orders_effective_priorities(
+        factor in factor(),
+        jobs in prop::collection::vec((0u32..=6, 0u64..100), 1..50),
+        later in 0u64..100,
+    ) {
+        let mut model: Model = Model::new(Arc::new(LinearAging::new(factor)));
+        model.jobs = jobs
+            .iter()
+            .enumerate()
+            .map(|(i, (priority, enqueue_time))| (i as u64, *priority, *enqueue_time))
+            .collect();
+        model.sort();
+
+        let now: u64 = jobs.iter().map(|(_, t)| *t).max().unwrap_or(0) + later;
+        let effective: Vec<u32> = model.effective_priorities(now);
+        prop_assert!(effective.windows(2).all(|pair| pair[0] <= pair[1]), "{:?}", effective);
+    }
+}
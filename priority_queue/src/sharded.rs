@@ -0,0 +1,332 @@
+use crate::heap::{HeapNode, PriorityQueue};
+use crate::policy::{Candidate, SchedulingPolicy};
+use std::cell::Cell;
+use std::collections::hash_map::RandomState;
+use std::hash::{BuildHasher, Hash};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex, MutexGuard, TryLockError};
+
+thread_local! {
+    /// The state of the xorshift generator each thread samples shards with, so threads never
+    /// contend on it.
+    static SEED: Cell<u64> = Cell::new(RandomState::new().hash_one(0u64) | 1);
+}
+
+/// ShardedQueue is a priority queue that can be shared between threads without a lock around the
+/// whole queue. Jobs are spread over independent `PriorityQueue` shards by the hash of their id,
+/// each behind its own lock, so concurrent enqueues and dequeues mostly touch different shards.
+/// A dequeue samples `choices` shards at random, skipping any another thread holds, and takes
+/// the job the scheduling policy prefers out of the tops of the sampled shards.
+///
+/// # Ordering guarantees
+/// - Every job is handed out exactly once, unless it is removed first.
+/// - Jobs in the same shard are handed out in the order of the scheduling policy.
+/// - A dequeue can overtake better jobs at the top of shards it did not sample. Jobs are
+///   overtaken further the more shards there are, and less the more shards are sampled.
+/// - With `choices` equal to the number of shards and no other thread using the queue, jobs are
+///   handed out in the same order as a single `PriorityQueue` whose policy uses one band.
+/// - A job at the top of its shard is a candidate whenever its shard is sampled, so under an
+///   aging policy every job is eventually handed out.
+/// - `get_top` only returns None if every shard was empty when it was checked.
+///
+/// Policies that pick between bands at random pick within each shard and again between the
+/// sampled shards, so their weights only hold approximately.
+/// `K`: The type of the unique id of a job.
+/// `V`: The type of the value carried with each job, nothing by default.
+/// `shards`: The queues jobs are spread over.
+/// `policy`: The scheduling policy of every shard, also used to choose between shards.
+/// `choices`: The number of shards sampled on every dequeue.
+/// `hasher`: Places each job in a shard by its id.
+/// `latest`: The latest enqueue time of any job, the current time as far as the policy knows.
+#[derive(Debug)]
+pub struct ShardedQueue<K, V = ()> {
+    shards: Vec<Mutex<PriorityQueue<K, V>>>,
+    policy: Arc<dyn SchedulingPolicy>,
+    choices: usize,
+    hasher: RandomState,
+    latest: AtomicU64,
+}
+
+impl<K: Eq + Hash + Clone> ShardedQueue<K> {
+    /// Inserts a new job into the queue.
+    pub fn insert(&self, priority: u32, job_id: K, timestamp: u64) {
+        self.push(priority, job_id, timestamp, ());
+    }
+}
+
+impl<K: Eq + Hash + Clone, V> ShardedQueue<K, V> {
+    pub fn new<P: SchedulingPolicy + 'static>(policy: P, shards: usize, choices: usize) -> Self {
+        ShardedQueue::with_policy(Arc::new(policy), shards, choices)
+    }
+
+    /// Creates a queue with a policy that may be shared with other queues.
+    ///
+    /// # Arguments
+    /// `policy`: The scheduling policy jobs are handed out by.
+    /// `shards`: The number of shards, at least 1. A few per core keeps contention low.
+    /// `choices`: The number of shards sampled on every dequeue, between 1 and `shards`. Two is
+    /// usually enough to keep jobs close to the order of the policy.
+    pub fn with_policy(policy: Arc<dyn SchedulingPolicy>, shards: usize, choices: usize) -> Self {
+        let shards: usize = shards.max(1);
+
+        ShardedQueue {
+            shards: (0..shards)
+                .map(|_| Mutex::new(PriorityQueue::with_policy(policy.clone())))
+                .collect(),
+            policy,
+            choices: choices.clamp(1, shards),
+            hasher: RandomState::new(),
+            latest: AtomicU64::new(0),
+        }
+    }
+
+    /// The number of jobs in the queue. Shards are counted one at a time, so the count may be
+    /// stale while other threads use the queue.
+    pub fn len(&self) -> usize {
+        (0..self.shards.len()).map(|i| self.lock(i).len()).sum()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        (0..self.shards.len()).all(|i| self.lock(i).is_empty())
+    }
+
+    /// Inserts a new job carrying a value into its shard. Job ids must be unique within the
+    /// queue.
+    pub fn push(&self, priority: u32, job_id: K, timestamp: u64, value: V) {
+        self.push_with_deadline(priority, job_id, timestamp, None, value);
+    }
+
+    /// Inserts a new job carrying a value and an optional deadline into its shard. Job ids must
+    /// be unique within the queue.
+    pub fn push_with_deadline(
+        &self,
+        priority: u32,
+        job_id: K,
+        timestamp: u64,
+        deadline: Option<u64>,
+        value: V,
+    ) {
+        self.latest.fetch_max(timestamp, Ordering::Relaxed);
+        self.lock(self.shard(&job_id))
+            .push_with_deadline(priority, job_id, timestamp, deadline, value);
+    }
+
+    /// Extracts the job the policy prefers out of the tops of `choices` distinct shards, starting
+    /// from one picked at random. If the sampled shards are empty or held by other threads, every
+    /// shard is checked instead.
+    pub fn get_top(&self) -> Option<HeapNode<K, V>> {
+        let start: usize = (next_random() % self.shards.len() as u64) as usize;
+        let sampled: Vec<usize> = (0..self.choices)
+            .map(|i| (start + i) % self.shards.len())
+            .collect();
+
+        let candidates: Vec<(usize, Candidate)> = self.candidates(&sampled, false);
+        if let Some(node) = self.take(&candidates) {
+            return Some(node);
+        }
+
+        // Another thread may empty the chosen shard between the two locks, so keep going until a
+        // job is taken or every shard is seen empty
+        let all: Vec<usize> = (0..self.shards.len()).collect();
+        loop {
+            let candidates: Vec<(usize, Candidate)> = self.candidates(&all, true);
+            if candidates.is_empty() {
+                return None;
+            }
+
+            if let Some(node) = self.take(&candidates) {
+                return Some(node);
+            }
+        }
+    }
+
+    /// Removes the job with the given id from its shard, returning the removed node.
+    pub fn remove(&self, job_id: &K) -> Option<HeapNode<K, V>> {
+        self.lock(self.shard(job_id)).remove(job_id)
+    }
+
+    /// Changes the priority of a job in its shard. The job keeps its enqueue time and with it
+    /// the aging it has earned.
+    ///
+    /// # Returns
+    /// Whether the job was in the queue.
+    pub fn change_priority(&self, job_id: &K, new_priority: u32) -> bool {
+        self.lock(self.shard(job_id))
+            .change_priority(job_id, new_priority)
+    }
+
+    /// Computes the effective priority of a job at the given time with the scheduling policy.
+    ///
+    /// # Arguments
+    /// `node`: The job, as returned by `get_top`.
+    /// `timestamp`: The current logical time.
+    pub fn effective_priority(&self, node: &HeapNode<K, V>, timestamp: u64) -> u32 {
+        self.policy
+            .effective_priority(node.priority, timestamp.saturating_sub(node.enqueue_time))
+    }
+
+    /// The top job of each of the given shards that is not empty, as offered to the policy, in
+    /// band order. Only one shard is locked at a time.
+    ///
+    /// # Arguments
+    /// `shards`: The shards to look at.
+    /// `wait`: Whether to wait for shards held by other threads rather than skip them.
+    fn candidates(&self, shards: &[usize], wait: bool) -> Vec<(usize, Candidate)> {
+        let mut candidates: Vec<(usize, Candidate)> = Vec::with_capacity(shards.len());
+
+        for &i in shards {
+            let shard: MutexGuard<PriorityQueue<K, V>> = match self.shards[i].try_lock() {
+                Ok(guard) => guard,
+                Err(TryLockError::Poisoned(poisoned)) => poisoned.into_inner(),
+                Err(TryLockError::WouldBlock) if wait => self.lock(i),
+                Err(TryLockError::WouldBlock) => continue,
+            };
+
+            if let Some(node) = shard.peek() {
+                candidates.push((i, node.candidate(self.policy.band(node.priority))));
+            }
+        }
+
+        // The policy expects the candidates in band order, and ties within a band go to the job
+        // enqueued first as they do within a shard
+        candidates.sort_by(|(_, a), (_, b)| {
+            a.band
+                .cmp(&b.band)
+                .then(a.rank.total_cmp(&b.rank))
+                .then(a.enqueue_time.cmp(&b.enqueue_time))
+        });
+        candidates
+    }
+
+    /// Asks the policy to pick one of the candidates and takes the top job of its shard, which
+    /// may no longer be the candidate if another thread got to the shard first. Returns None if
+    /// there are no candidates or the shard was emptied in the meantime.
+    fn take(&self, candidates: &[(usize, Candidate)]) -> Option<HeapNode<K, V>> {
+        let chosen: usize = match candidates.len() {
+            0 => return None,
+            1 => 0,
+            _ => {
+                let offered: Vec<Candidate> = candidates.iter().map(|(_, c)| *c).collect();
+                let latest: u64 = self.latest.load(Ordering::Relaxed);
+                self.policy.select(&offered, latest, next_random())
+            }
+        };
+
+        let (shard, _) = candidates.get(chosen).or(candidates.last())?;
+        self.lock(*shard).get_top()
+    }
+
+    /// The shard a job is placed in.
+    fn shard(&self, job_id: &K) -> usize {
+        (self.hasher.hash_one(job_id) % self.shards.len() as u64) as usize
+    }
+
+    /// Locks a shard. A shard is never left half updated, so one whose lock was poisoned by a
+    /// panicking thread is still used.
+    fn lock(&self, shard: usize) -> MutexGuard<'_, PriorityQueue<K, V>> {
+        self.shards[shard]
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+}
+
+/// Advances the calling thread's xorshift generator.
+fn next_random() -> u64 {
+    SEED.with(|seed| {
+        let mut x: u64 = seed.get();
+        x ^= x >> 12;
+        x ^= x << 25;
+        x ^= x >> 27;
+        seed.set(x);
+        x.wrapping_mul(0x2545_F491_4F6C_DD1D)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::ShardedQueue;
+    use crate::heap::PriorityQueue;
+    use crate::policy::{LinearAging, NoAging, StrictPriority};
+    use std::collections::HashSet;
+    use std::sync::Arc;
+    use std::thread;
+
+    #[test]
+    fn test_sampling_every_shard_keeps_the_order() {
+        let sharded: ShardedQueue<u64> = ShardedQueue::new(LinearAging::new(0.25), 4, 4);
+        let mut heap: PriorityQueue<u64> = PriorityQueue::new(LinearAging::new(0.25));
+
+        for tick in 0..500u64 {
+            let priority: u32 = (tick * 7 % 5) as u32 + 1;
+            sharded.insert(priority, tick, tick);
+            heap.insert(priority, tick, tick);
+
+            if tick % 3 == 0 {
+                assert_eq!(
+                    sharded.get_top().map(|node| node.job_id),
+                    heap.get_top().map(|node| node.job_id)
+                );
+            }
+        }
+
+        assert!(sharded.change_priority(&499, 1));
+        assert!(heap.change_priority(&499, 1));
+        assert_eq!(sharded.remove(&498).unwrap().job_id, 498);
+        heap.remove(&498);
+
+        while let Some(node) = heap.get_top() {
+            assert_eq!(sharded.get_top().unwrap().job_id, node.job_id);
+        }
+        assert!(sharded.get_top().is_none());
+        assert!(sharded.is_empty());
+    }
+
+    #[test]
+    fn test_policy_chooses_between_shards() {
+        let sharded: ShardedQueue<u64> = ShardedQueue::new(StrictPriority::new(100), 8, 8);
+
+        for job_id in 0..64u64 {
+            sharded.insert((job_id % 4) as u32 + 1, job_id, job_id);
+        }
+
+        let priorities: Vec<u32> = (0..64)
+            .map(|_| sharded.get_top().unwrap().priority)
+            .collect();
+        assert!(priorities.windows(2).all(|pair| pair[0] <= pair[1]));
+    }
+
+    #[test]
+    fn test_concurrent_jobs_are_handed_out_once() {
+        let sharded: Arc<ShardedQueue<u64>> = Arc::new(ShardedQueue::new(NoAging, 8, 2));
+
+        let handles: Vec<thread::JoinHandle<Vec<u64>>> = (0..8u64)
+            .map(|worker| {
+                let sharded: Arc<ShardedQueue<u64>> = sharded.clone();
+                thread::spawn(move || {
+                    let mut taken: Vec<u64> = Vec::new();
+                    for i in 0..2_000u64 {
+                        let job_id: u64 = worker * 2_000 + i;
+                        sharded.insert((job_id % 5) as u32 + 1, job_id, job_id);
+
+                        if i % 2 == 0 {
+                            taken.extend(sharded.get_top().map(|node| node.job_id));
+                        }
+                    }
+                    taken
+                })
+            })
+            .collect();
+
+        let mut taken: Vec<u64> = handles
+            .into_iter()
+            .flat_map(|handle| handle.join().unwrap())
+            .collect();
+        taken.extend(std::iter::from_fn(|| {
+            sharded.get_top().map(|node| node.job_id)
+        }));
+
+        let unique: HashSet<u64> = taken.iter().copied().collect();
+        assert_eq!(taken.len(), 16_000);
+        assert_eq!(unique.len(), 16_000);
+    }
+}
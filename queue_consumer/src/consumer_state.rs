@@ -1,37 +1,39 @@
 use std::sync::Arc;
+use std::time::Duration;
 
+use dpq_client::ClientConfig;
 use tokio::sync::Mutex;
 
-use crate::job_management::long_polling_service_client::LongPollingServiceClient;
-use crate::job_management::{Job, PollJobRequest, PollJobResponse};
-use crate::min_heap::MinHeap;
+use crate::runtime::WorkerConfig;
 
 /// Consumer state represented with:
 /// consumer_id: The id of the consumer provided in command line arguments at startup.
-/// nodes: A list of nodes that the consumer pulls from to get jobs.
-/// heap: A loacal min heap implementation for fetched jobs.
-/// lamport_timestamp: A logical clock
+/// nodes: A list of nodes that the consumer pulls from to get jobs. In a group, the consumer only
+/// pulls from the nodes the group assigns it.
+/// queues: The named queues the consumer polls, every queue if empty.
 /// timeout: Specified timeout for long polling
+/// prefetch: How many jobs are leased ahead of time, none if 0.
+/// group: The consumer group the consumer joins, if any.
 ///
 /// # Example
-/// ```
+/// ```ignore
 /// use queue_consumer::consumer_state;
-/// let aging_factor: f32 = 0.5;
-///
 /// let state: ConsumerState = {
 ///     consumer_id: 1,
 ///     nodes : vec!["http://node1", "http://node2"],
-///     heap: MinHeap::new(aging_factor),
-///     lamport_timestamp: 0,
-///     timeout: 30
+///     queues: vec!["emails"],
+///     timeout: 30,
+///     prefetch: 8,
+///     group: Some("emailers")
 /// }
 /// ```
 pub struct ConsumerState {
     consumer_id: i32,
     nodes: Vec<String>,
-    heap: MinHeap,
-    lamport_timestamp: i64,
+    queues: Vec<String>,
     timeout: i32,
+    prefetch: usize,
+    group: Option<String>,
 }
 
 impl ConsumerState {
@@ -46,7 +48,7 @@ impl ConsumerState {
             None => std::process::exit(1),
         };
 
-        let timeout: i32 = match std::env::args().collect::<Vec<String>>().get(1) {
+        let timeout: i32 = match std::env::args().collect::<Vec<String>>().get(2) {
             Some(id) => match id.parse::<i32>() {
                 Ok(i) => i,
                 Err(_) => 5,
@@ -54,73 +56,58 @@ impl ConsumerState {
             None => 5,
         };
 
+        // Comma separated list of queues to poll, e.g. QUEUES=emails,reports
+        let queues: Vec<String> = match std::env::var("QUEUES") {
+            Ok(q) => q
+                .split(',')
+                .map(|name| name.trim().to_string())
+                .filter(|name| !name.is_empty())
+                .collect(),
+            Err(_) => Vec::new(),
+        };
+
+        // Number of jobs to lease ahead of time, e.g. PREFETCH=8
+        let prefetch: usize = match std::env::var("PREFETCH") {
+            Ok(p) => p.trim().parse::<usize>().unwrap_or(0),
+            Err(_) => 0,
+        };
+
+        // Consumer group to join, e.g. GROUP=emailers
+        let group: Option<String> = std::env::var("GROUP")
+            .ok()
+            .map(|g| g.trim().to_string())
+            .filter(|g| !g.is_empty());
+
         return Arc::new(Mutex::new(ConsumerState {
             consumer_id,
             nodes,
-            heap: MinHeap::new(0.5),
-            lamport_timestamp: 0,
+            queues,
             timeout,
+            prefetch,
+            group,
         }));
     }
 
-    pub fn insert_job(&mut self, job: Job) {
-        let time: i64 = self.increment_time();
-        self.heap
-            .insert(job.priority as u32, job.job_id as u64, time as u64);
-    }
-
-    fn increment_time(&mut self) -> i64 {
-        let temp = self.lamport_timestamp;
-        self.lamport_timestamp += 1;
-        return temp;
-    }
-}
-
-pub struct LocalLongPollService {
-    consumer_state: Arc<Mutex<ConsumerState>>,
-}
-
-impl LocalLongPollService {
-    pub async fn get_jobs(&mut self) {
-        let gaurd = &mut self.consumer_state.lock().await;
-
-        // create a request to get jobs from the nodes
-        let request: PollJobRequest = PollJobRequest {
-            consumer_id: gaurd.consumer_id,
-            timeout_seconds: gaurd.timeout,
-        };
-
-        let mut responses: Vec<Result<tonic::Response<PollJobResponse>, tonic::Status>> = vec![];
-
-        for node in &gaurd.nodes {
-            let mut client = LongPollingServiceClient::connect(node.to_string())
-                .await
-                .unwrap();
-            let response = client.poll(request.clone()).await;
-            responses.push(response);
+    /// The configuration of the client the consumer polls the nodes with.
+    ///
+    /// # Arguments
+    /// `base`: The configuration to start from, e.g. `ClientConfig::from_env()`.
+    pub fn client_config(&self, base: ClientConfig) -> ClientConfig {
+        ClientConfig {
+            followers: self.nodes.clone(),
+            consumer_id: self.consumer_id,
+            ..base
         }
+    }
 
-        for response in responses {
-            match response {
-                Ok(res) => {
-                    let res = res.into_inner().clone();
-                    let (success, option_job) = (&res.success.clone(), &res.job.clone());
-
-                    if *success {
-                        let job = match option_job {
-                            Some(job) => job,
-                            None => continue,
-                        };
-
-                        gaurd.insert_job(job.clone());
-                    } else {
-                        continue;
-                    }
-                }
-                Err(_) => {
-                    continue;
-                }
-            }
+    /// The configuration of the worker runtime, polling the consumer's queues with its timeout.
+    pub fn worker_config(&self) -> WorkerConfig {
+        WorkerConfig {
+            queues: self.queues.clone(),
+            poll_timeout: Duration::from_secs(self.timeout.max(0) as u64),
+            prefetch: self.prefetch,
+            group: self.group.clone(),
+            ..WorkerConfig::default()
         }
     }
 }
@@ -1,3 +1,6 @@
 pub mod consumer_state;
-pub mod job_management;
-pub mod min_heap;
+pub mod job_management {
+    include!("proto/job_management.rs");
+}
+pub mod prefetch;
+pub mod runtime;
@@ -1,9 +1,36 @@
+use dpq_client::{Client, ClientConfig, JobResult};
+use log::info;
 use queue_consumer::consumer_state::ConsumerState;
+use queue_consumer::runtime::{HandlerError, JobContext, JobHandler, Worker};
 
-fn main() {
-    let nodes = vec!["http://node1", "http://node2"]; // Example list of followers
+/// Logs every job it is handed and acks it without a result.
+struct LogHandler;
 
-    let consumer_state = ConsumerState::new(nodes);
+#[tonic::async_trait]
+impl JobHandler for LogHandler {
+    async fn handle(&self, context: &JobContext) -> Result<Option<JobResult>, HandlerError> {
+        info!(target:"request_logger","Processing job {} with a {} byte payload",context.job().job.job_id,context.payload().len());
+        Ok(None)
+    }
+}
+
+#[tokio::main]
+async fn main() {
+    let base: ClientConfig = ClientConfig::from_env();
+    let consumer_state = ConsumerState::new(base.followers.clone());
+
+    let worker: Worker<LogHandler> = {
+        let state = consumer_state.lock().await;
+        Worker::new(
+            Client::new(state.client_config(base)),
+            LogHandler,
+            state.worker_config(),
+        )
+    };
 
-    println!("Hello, world!");
+    worker
+        .run(async {
+            let _ = tokio::signal::ctrl_c().await;
+        })
+        .await;
 }
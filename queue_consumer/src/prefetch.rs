@@ -0,0 +1,171 @@
+use dpq_client::LeasedJob;
+use priority_queue::{NoAging, PriorityQueue};
+use std::time::{Duration, Instant};
+
+/// A leased job waiting in the prefetch buffer.
+/// `job`: The leased job.
+/// `renewed_at`: When the lease on the job was last taken or extended.
+struct Prefetched {
+    job: LeasedJob,
+    renewed_at: Instant,
+}
+
+/// PrefetchBuffer holds jobs leased ahead of time, so a worker that finishes a job can start on
+/// the next one without waiting for a poll. Jobs are handed out lowest priority value first.
+/// `heap`: The buffered jobs keyed by job id, ordered by priority.
+/// `buffered`: The number of jobs buffered so far, used as their enqueue time.
+/// `capacity`: The most jobs the buffer holds at once.
+pub struct PrefetchBuffer {
+    heap: PriorityQueue<String, Prefetched>,
+    buffered: u64,
+    capacity: usize,
+}
+
+impl PrefetchBuffer {
+    pub fn new(capacity: usize) -> Self {
+        PrefetchBuffer {
+            heap: PriorityQueue::new(NoAging),
+            buffered: 0,
+            capacity,
+        }
+    }
+
+    /// The number of jobs in the buffer.
+    pub fn len(&self) -> usize {
+        self.heap.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.heap.is_empty()
+    }
+
+    /// Whether the buffer holds as many jobs as it may.
+    pub fn is_full(&self) -> bool {
+        self.heap.len() >= self.capacity
+    }
+
+    /// Adds a job that was just leased to the buffer.
+    ///
+    /// # Arguments
+    /// `job`: The leased job.
+    /// `now`: When the job was leased.
+    pub fn push(&mut self, job: LeasedJob, now: Instant) {
+        self.buffered += 1;
+        self.heap.push(
+            job.job.priority.max(0) as u32,
+            job.job.job_id.clone(),
+            self.buffered,
+            Prefetched {
+                job,
+                renewed_at: now,
+            },
+        );
+    }
+
+    /// Takes the most urgent job out of the buffer.
+    pub fn pop(&mut self) -> Option<LeasedJob> {
+        self.heap.get_top().map(|node| node.value.job)
+    }
+
+    /// Takes a job out of the buffer without handing it to a worker, e.g. once its lease is lost.
+    ///
+    /// # Returns
+    /// The job if it was still in the buffer.
+    pub fn remove(&mut self, job_id: &str) -> Option<LeasedJob> {
+        self.heap
+            .remove(&job_id.to_string())
+            .map(|node| node.value.job)
+    }
+
+    /// The jobs whose lease is due to be renewed.
+    ///
+    /// # Arguments
+    /// `now`: The current time.
+    /// `interval`: How long after it was last renewed the lease on a job is due to be renewed.
+    pub fn due<F: Fn(&LeasedJob) -> Duration>(&self, now: Instant, interval: F) -> Vec<LeasedJob> {
+        self.heap
+            .iter()
+            .map(|node| &node.value)
+            .filter(|prefetched| {
+                now.duration_since(prefetched.renewed_at) >= interval(&prefetched.job)
+            })
+            .map(|prefetched| prefetched.job.clone())
+            .collect()
+    }
+
+    /// Records that the lease on a job was renewed.
+    pub fn renewed(&mut self, job_id: &str, now: Instant) {
+        if let Some(prefetched) = self.heap.get_mut(&job_id.to_string()) {
+            prefetched.renewed_at = now;
+        }
+    }
+
+    /// Empties the buffer, returning every job that was in it.
+    pub fn drain(&mut self) -> Vec<LeasedJob> {
+        std::iter::from_fn(|| self.pop()).collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::PrefetchBuffer;
+    use dpq_client::{Job, LeasedJob};
+    use std::time::{Duration, Instant};
+
+    fn leased(job_id: &str, priority: i32) -> LeasedJob {
+        LeasedJob {
+            job: Job {
+                job_id: job_id.to_string(),
+                priority,
+                ..Job::default()
+            },
+            lease_id: format!("lease-{}", job_id),
+            visibility_timeout: Duration::from_secs(30),
+            node: "http://follower".to_string(),
+        }
+    }
+
+    #[test]
+    fn test_pop_in_priority_order() {
+        let now: Instant = Instant::now();
+        let mut buffer: PrefetchBuffer = PrefetchBuffer::new(3);
+        buffer.push(leased("a", 5), now);
+        buffer.push(leased("b", 1), now);
+        assert!(!buffer.is_full());
+        buffer.push(leased("c", 3), now);
+        assert!(buffer.is_full());
+
+        assert_eq!(buffer.remove("b").unwrap().job.job_id, "b");
+        assert!(buffer.remove("b").is_none());
+        assert_eq!(buffer.len(), 2);
+
+        let order: Vec<String> = std::iter::from_fn(|| buffer.pop())
+            .map(|job| job.job.job_id)
+            .collect();
+        assert_eq!(order, vec!["c".to_string(), "a".to_string()]);
+        assert!(buffer.is_empty());
+    }
+
+    #[test]
+    fn test_due_and_drain() {
+        let start: Instant = Instant::now();
+        let mut buffer: PrefetchBuffer = PrefetchBuffer::new(2);
+        buffer.push(leased("a", 1), start);
+        buffer.push(leased("b", 1), start + Duration::from_secs(5));
+
+        let later: Instant = start + Duration::from_secs(10);
+        let due: Vec<LeasedJob> = buffer.due(later, |job| job.visibility_timeout / 3);
+        assert_eq!(due.len(), 1);
+        assert_eq!(due[0].job.job_id, "a");
+
+        buffer.renewed("a", later);
+        assert!(buffer
+            .due(later, |job| job.visibility_timeout / 3)
+            .is_empty());
+
+        let mut drained: Vec<String> = buffer.drain().into_iter().map(|j| j.job.job_id).collect();
+        drained.sort();
+        assert_eq!(drained, vec!["a".to_string(), "b".to_string()]);
+        assert!(buffer.pop().is_none());
+    }
+}
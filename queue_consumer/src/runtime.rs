@@ -0,0 +1,517 @@
+use crate::prefetch::PrefetchBuffer;
+use dpq_client::{Client, ClientError, JobProgress, JobResult, LeasedJob};
+use log::{error, info};
+use std::future::Future;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::sync::{watch, Notify};
+use tokio::task::{JoinError, JoinSet};
+use tokio::time::Instant;
+
+/// How long a worker waits before polling again after a failed poll.
+const POLL_ERROR_BACKOFF: Duration = Duration::from_secs(1);
+
+/// How often the leases on prefetched jobs are checked for renewal.
+const RENEWAL_CHECK_INTERVAL: Duration = Duration::from_millis(100);
+
+/// How long a worker waits before joining its consumer group again after failing to.
+const GROUP_RETRY_INTERVAL: Duration = Duration::from_secs(1);
+
+/// The error a handler returns when it fails to process a job.
+pub type HandlerError = Box<dyn std::error::Error + Send + Sync>;
+
+/// JobHandler processes the jobs handed to a consumer.
+/// A job is acked with the returned result if the handler succeeds, and nacked so it is retried
+/// if the handler fails or panics.
+#[tonic::async_trait]
+pub trait JobHandler: Send + Sync + 'static {
+    async fn handle(&self, context: &JobContext) -> Result<Option<JobResult>, HandlerError>;
+}
+
+/// JobContext is the job being processed along with the means to report on it.
+/// `job`: The leased job.
+/// `progress`: The progress sent with the next heartbeat.
+/// `cancelled`: Set once the job has been cancelled, the handler should stop working on it.
+#[derive(Clone)]
+pub struct JobContext {
+    job: LeasedJob,
+    progress: Arc<Mutex<Option<JobProgress>>>,
+    cancelled: Arc<AtomicBool>,
+}
+
+impl JobContext {
+    pub fn new(job: LeasedJob) -> Self {
+        JobContext {
+            job,
+            progress: Arc::new(Mutex::new(None)),
+            cancelled: Arc::new(AtomicBool::new(false)),
+        }
+    }
+
+    /// The leased job.
+    pub fn job(&self) -> &LeasedJob {
+        &self.job
+    }
+
+    /// The payload of the job.
+    pub fn payload(&self) -> &[u8] {
+        &self.job.job.payload
+    }
+
+    /// Records the progress of the job, reported with the next heartbeat.
+    pub fn report_progress(&self, percent: i32, message: &str) {
+        if let Ok(mut progress) = self.progress.lock() {
+            *progress = Some(JobProgress {
+                percent: percent.clamp(0, 100),
+                message: message.to_string(),
+            });
+        }
+    }
+
+    /// Whether the job was cancelled while it was being processed.
+    pub fn is_cancelled(&self) -> bool {
+        self.cancelled.load(Ordering::Relaxed)
+    }
+
+    fn progress(&self) -> Option<JobProgress> {
+        self.progress.lock().ok().and_then(|p| p.clone())
+    }
+}
+
+/// WorkerConfig holds the settings of a worker runtime.
+/// `concurrency`: The number of jobs processed at once.
+/// `queues`: The queues to poll, every queue if empty.
+/// `poll_timeout`: How long each long poll may wait for a job.
+/// `heartbeat_interval`: How often the lease on a job being processed is renewed. A third of the
+/// job's visibility timeout if None.
+/// `drain_timeout`: How long jobs being processed may run after shutdown before they are
+/// abandoned and nacked.
+/// `prefetch`: How many leased jobs are kept waiting for a free worker, so a worker that finishes
+/// a job starts on the next one without waiting for a poll. Jobs are not prefetched if 0.
+/// `group`: The consumer group the worker joins. The group's members split the followers between
+/// them, rebalancing as members join and leave. Every follower is polled if None.
+#[derive(Debug, Clone)]
+pub struct WorkerConfig {
+    pub concurrency: usize,
+    pub queues: Vec<String>,
+    pub poll_timeout: Duration,
+    pub heartbeat_interval: Option<Duration>,
+    pub drain_timeout: Duration,
+    pub prefetch: usize,
+    pub group: Option<String>,
+}
+
+impl Default for WorkerConfig {
+    fn default() -> Self {
+        WorkerConfig {
+            concurrency: 4,
+            queues: Vec::new(),
+            poll_timeout: Duration::from_secs(5),
+            heartbeat_interval: None,
+            drain_timeout: Duration::from_secs(30),
+            prefetch: 0,
+            group: None,
+        }
+    }
+}
+
+/// Prefetch is the buffer of jobs leased ahead of time, shared by the workers taking jobs from it
+/// and the task filling it.
+/// `buffer`: The jobs waiting for a worker.
+/// `available`: Notified when a job is added to the buffer.
+/// `space`: Notified when a job leaves the buffer.
+struct Prefetch {
+    buffer: Mutex<PrefetchBuffer>,
+    available: Notify,
+    space: Notify,
+}
+
+impl Prefetch {
+    fn new(capacity: usize) -> Self {
+        Prefetch {
+            buffer: Mutex::new(PrefetchBuffer::new(capacity)),
+            available: Notify::new(),
+            space: Notify::new(),
+        }
+    }
+
+    /// Waits for a job to be buffered and takes the most urgent one.
+    async fn take(&self) -> LeasedJob {
+        loop {
+            let taken: Option<LeasedJob> = {
+                let mut buffer = self.buffer.lock().unwrap();
+                let job: Option<LeasedJob> = buffer.pop();
+                // Only one wakeup is stored, so pass it on while jobs are left
+                if job.is_some() && !buffer.is_empty() {
+                    self.available.notify_one();
+                }
+                job
+            };
+
+            match taken {
+                Some(job) => {
+                    self.space.notify_one();
+                    return job;
+                }
+                None => self.available.notified().await,
+            }
+        }
+    }
+}
+
+/// Worker runs a `JobHandler` on `concurrency` jobs at a time, taking care of polling, lease
+/// heartbeats and acking or nacking each job.
+pub struct Worker<H: JobHandler> {
+    client: Client,
+    handler: Arc<H>,
+    config: Arc<WorkerConfig>,
+}
+
+impl<H: JobHandler> Worker<H> {
+    pub fn new(client: Client, handler: H, config: WorkerConfig) -> Self {
+        Worker {
+            client,
+            handler: Arc::new(handler),
+            config: Arc::new(config),
+        }
+    }
+
+    /// Processes jobs until `shutdown` completes, then stops polling, leaves its consumer group,
+    /// releases the jobs that were prefetched but not started and waits for the jobs being processed to finish, up to
+    /// the drain timeout.
+    ///
+    /// # Arguments
+    /// `shutdown`: Completes when the worker should shut down, e.g. `tokio::signal::ctrl_c()`.
+    pub async fn run<F: Future<Output = ()>>(self, shutdown: F) {
+        let (stop, stopped) = watch::channel(false);
+        let mut workers: JoinSet<()> = JoinSet::new();
+        let mut background: JoinSet<()> = JoinSet::new();
+
+        if let Some(group) = &self.config.group {
+            // Start polling the assigned followers straight away rather than every follower
+            let mut generation: i64 = 0;
+            let rejoin: Duration = join(&self.client, group, &mut generation).await;
+            background.spawn(coordinate(
+                self.client.clone(),
+                group.clone(),
+                generation,
+                rejoin,
+                stopped.clone(),
+            ));
+        }
+
+        let prefetch: Option<Arc<Prefetch>> = match self.config.prefetch {
+            0 => None,
+            capacity => Some(Arc::new(Prefetch::new(capacity))),
+        };
+        if let Some(prefetch) = &prefetch {
+            background.spawn(fill(
+                self.client.clone(),
+                self.config.clone(),
+                prefetch.clone(),
+                stopped.clone(),
+            ));
+            background.spawn(renew(
+                self.client.clone(),
+                self.config.clone(),
+                prefetch.clone(),
+                stopped.clone(),
+            ));
+        }
+
+        for _ in 0..self.config.concurrency.max(1) {
+            workers.spawn(work(
+                self.client.clone(),
+                self.handler.clone(),
+                self.config.clone(),
+                prefetch.clone(),
+                stopped.clone(),
+            ));
+        }
+
+        shutdown.await;
+        info!(target:"request_logger","Shutting down, draining jobs in progress");
+        let _ = stop.send(true);
+
+        while background.join_next().await.is_some() {}
+        if let Some(prefetch) = &prefetch {
+            release(&self.client, prefetch).await;
+        }
+
+        while workers.join_next().await.is_some() {}
+        info!(target:"request_logger","Worker stopped");
+    }
+}
+
+/// Polls for jobs, or takes them from the prefetch buffer if there is one, and processes them one
+/// at a time until shutdown.
+async fn work<H: JobHandler>(
+    client: Client,
+    handler: Arc<H>,
+    config: Arc<WorkerConfig>,
+    prefetch: Option<Arc<Prefetch>>,
+    mut stopped: watch::Receiver<bool>,
+) {
+    loop {
+        if *stopped.borrow() {
+            return;
+        }
+
+        // A job leased by a poll that is interrupted is requeued once its lease expires
+        let polled: Result<Option<LeasedJob>, ClientError> = match &prefetch {
+            Some(prefetch) => tokio::select! {
+                _ = stopped.changed() => return,
+                job = prefetch.take() => Ok(Some(job)),
+            },
+            None => tokio::select! {
+                _ = stopped.changed() => return,
+                polled = client.poll(&config.queues, config.poll_timeout) => polled,
+            },
+        };
+
+        match polled {
+            Ok(Some(job)) => process(&client, &handler, &config, job, stopped.clone()).await,
+            Ok(None) => (),
+            Err(e) => {
+                error!(target:"error_logger","Failed to poll for jobs: {}",e);
+                tokio::time::sleep(POLL_ERROR_BACKOFF).await;
+            }
+        }
+    }
+}
+
+/// Keeps the worker in its consumer group until shutdown, switching to the followers the group
+/// assigns it whenever the group is rebalanced. The group is left on shutdown so the other members
+/// take over its followers straight away.
+async fn coordinate(
+    client: Client,
+    group: String,
+    mut generation: i64,
+    mut rejoin: Duration,
+    mut stopped: watch::Receiver<bool>,
+) {
+    loop {
+        tokio::select! {
+            _ = stopped.changed() => break,
+            _ = tokio::time::sleep(rejoin) => (),
+        }
+        rejoin = join(&client, &group, &mut generation).await;
+    }
+
+    if let Err(e) = client.leave_group(&group).await {
+        error!(target:"error_logger","Failed to leave consumer group {}: {}",group,e);
+    }
+}
+
+/// Joins a consumer group, or stays in it, and polls the followers it assigns if the group has
+/// been rebalanced since `generation`.
+///
+/// # Returns
+/// How long to wait before joining again.
+async fn join(client: &Client, group: &str, generation: &mut i64) -> Duration {
+    match client.join_group(group).await {
+        Ok(assignment) => {
+            if assignment.generation != *generation {
+                info!(target:"request_logger","Consumer group {} rebalanced to {} members, polling {:?}",group,assignment.members,assignment.followers);
+                *generation = assignment.generation;
+                if !assignment.followers.is_empty() {
+                    client.set_followers(assignment.followers);
+                }
+            }
+            Duration::from_secs(assignment.session_timeout_seconds.max(1) as u64) / 3
+        }
+        Err(e) => {
+            error!(target:"error_logger","Failed to join consumer group {}: {}",group,e);
+            GROUP_RETRY_INTERVAL
+        }
+    }
+}
+
+/// Polls for jobs to keep the prefetch buffer full until shutdown.
+async fn fill(
+    client: Client,
+    config: Arc<WorkerConfig>,
+    prefetch: Arc<Prefetch>,
+    mut stopped: watch::Receiver<bool>,
+) {
+    loop {
+        if *stopped.borrow() {
+            return;
+        }
+
+        let full: bool = prefetch.buffer.lock().unwrap().is_full();
+        if full {
+            tokio::select! {
+                _ = stopped.changed() => return,
+                _ = prefetch.space.notified() => continue,
+            }
+        }
+
+        let polled: Result<Option<LeasedJob>, ClientError> = tokio::select! {
+            _ = stopped.changed() => return,
+            polled = client.poll(&config.queues, config.poll_timeout) => polled,
+        };
+
+        match polled {
+            Ok(Some(job)) => {
+                prefetch
+                    .buffer
+                    .lock()
+                    .unwrap()
+                    .push(job, Instant::now().into_std());
+                prefetch.available.notify_one();
+            }
+            Ok(None) => (),
+            Err(e) => {
+                error!(target:"error_logger","Failed to poll for jobs: {}",e);
+                tokio::time::sleep(POLL_ERROR_BACKOFF).await;
+            }
+        }
+    }
+}
+
+/// Renews the leases on the jobs waiting in the prefetch buffer until shutdown. Jobs that were
+/// cancelled, or whose lease was lost, are dropped from the buffer.
+async fn renew(
+    client: Client,
+    config: Arc<WorkerConfig>,
+    prefetch: Arc<Prefetch>,
+    mut stopped: watch::Receiver<bool>,
+) {
+    let mut check = tokio::time::interval(RENEWAL_CHECK_INTERVAL);
+
+    loop {
+        tokio::select! {
+            _ = stopped.changed() => return,
+            _ = check.tick() => (),
+        }
+
+        let due: Vec<LeasedJob> = prefetch
+            .buffer
+            .lock()
+            .unwrap()
+            .due(Instant::now().into_std(), |job| {
+                heartbeat_interval(&config, job)
+            });
+
+        for job in due {
+            let renewed = client.heartbeat(&job, None).await;
+            let mut buffer = prefetch.buffer.lock().unwrap();
+            match renewed {
+                Ok(response) if response.cancelled || !response.success => {
+                    info!(target:"request_logger","Dropping prefetched job {}, it was cancelled or its lease was lost",job.job.job_id);
+                    if buffer.remove(&job.job.job_id).is_some() {
+                        prefetch.space.notify_one();
+                    }
+                }
+                Ok(_) => buffer.renewed(&job.job.job_id, Instant::now().into_std()),
+                Err(e) => {
+                    error!(target:"error_logger","Heartbeat for prefetched job {} failed: {}",job.job.job_id,e);
+                }
+            }
+        }
+    }
+}
+
+/// Hands the jobs left in the prefetch buffer back to the followers, so other consumers can take
+/// them straight away instead of once their leases expire.
+async fn release(client: &Client, prefetch: &Prefetch) {
+    let jobs: Vec<LeasedJob> = prefetch.buffer.lock().unwrap().drain();
+
+    for job in jobs {
+        if let Err(e) = client.release(&job).await {
+            error!(target:"error_logger","Failed to release prefetched job {}: {}",job.job.job_id,e);
+        }
+    }
+}
+
+/// How often the lease on a job is renewed.
+fn heartbeat_interval(config: &WorkerConfig, job: &LeasedJob) -> Duration {
+    config
+        .heartbeat_interval
+        .unwrap_or(job.visibility_timeout / 3)
+        .max(Duration::from_millis(100))
+}
+
+/// Runs the handler on a job in its own task, so a panic only fails that job, while renewing the
+/// job's lease. The job is acked or nacked depending on the outcome.
+async fn process<H: JobHandler>(
+    client: &Client,
+    handler: &Arc<H>,
+    config: &WorkerConfig,
+    job: LeasedJob,
+    mut stopped: watch::Receiver<bool>,
+) {
+    let context: JobContext = JobContext::new(job.clone());
+    let task = tokio::spawn({
+        let handler: Arc<H> = handler.clone();
+        let context: JobContext = context.clone();
+        async move { handler.handle(&context).await }
+    });
+    let abort = task.abort_handle();
+    tokio::pin!(task);
+
+    let interval: Duration = heartbeat_interval(config, &job);
+    let mut heartbeat = tokio::time::interval_at(Instant::now() + interval, interval);
+
+    let drain = tokio::time::sleep(Duration::MAX);
+    tokio::pin!(drain);
+    let mut draining: bool = *stopped.borrow();
+    if draining {
+        drain.as_mut().reset(Instant::now() + config.drain_timeout);
+    }
+
+    let outcome: Option<Result<Result<Option<JobResult>, HandlerError>, JoinError>> = loop {
+        tokio::select! {
+            joined = &mut task => break Some(joined),
+            _ = heartbeat.tick() => {
+                match client.heartbeat(&job, context.progress()).await {
+                    Ok(response) if response.cancelled => {
+                        info!(target:"request_logger","Job {} was cancelled, abandoning it",job.job.job_id);
+                        context.cancelled.store(true, Ordering::Relaxed);
+                        abort.abort();
+                        return;
+                    }
+                    Ok(response) if !response.success => {
+                        error!(target:"error_logger","Lost the lease on job {}, abandoning it",job.job.job_id);
+                        abort.abort();
+                        return;
+                    }
+                    Ok(_) => (),
+                    Err(e) => {
+                        error!(target:"error_logger","Heartbeat for job {} failed: {}",job.job.job_id,e);
+                    }
+                }
+            }
+            _ = stopped.changed(), if !draining => {
+                draining = true;
+                drain.as_mut().reset(Instant::now() + config.drain_timeout);
+            }
+            _ = &mut drain, if draining => {
+                abort.abort();
+                break None;
+            }
+        }
+    };
+
+    let acked = match outcome {
+        Some(Ok(Ok(result))) => client.ack(&job, result).await,
+        Some(Ok(Err(e))) => {
+            error!(target:"error_logger","Handler failed on job {}: {}",job.job.job_id,e);
+            client.nack(&job).await
+        }
+        Some(Err(e)) => {
+            error!(target:"error_logger","Handler panicked on job {}: {}",job.job.job_id,e);
+            client.nack(&job).await
+        }
+        None => {
+            error!(target:"error_logger","Job {} did not finish before shutdown",job.job.job_id);
+            client.nack(&job).await
+        }
+    };
+
+    if let Err(e) = acked {
+        error!(target:"error_logger","Failed to report the outcome of job {}: {}",job.job.job_id,e);
+    }
+}
@@ -0,0 +1,394 @@
+use dpq_client::job_management::consumer_group_service_server::{
+    ConsumerGroupService, ConsumerGroupServiceServer,
+};
+use dpq_client::job_management::long_polling_service_server::{
+    LongPollingService, LongPollingServiceServer,
+};
+use dpq_client::job_management::{
+    AckRequest, AckResponse, GroupAssignment, HeartbeatRequest, HeartbeatResponse, Job,
+    JoinGroupRequest, LeaveGroupRequest, NackRequest, PollJobRequest, PollJobResponse,
+};
+use dpq_client::{Client, ClientConfig, JobResult};
+use queue_consumer::runtime::{HandlerError, JobContext, JobHandler, Worker, WorkerConfig};
+use std::collections::VecDeque;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::net::TcpListener;
+use tokio_stream::wrappers::TcpListenerStream;
+use tonic::transport::Server;
+use tonic::{Request, Response, Status};
+
+/// What the fake follower has handed out and been told.
+#[derive(Default)]
+struct FollowerLog {
+    jobs: VecDeque<Job>,
+    acked: Vec<(String, Option<JobResult>)>,
+    nacked: Vec<String>,
+    released: Vec<String>,
+    heartbeats: Vec<String>,
+}
+
+/// A follower serving jobs from memory.
+struct FakeFollower {
+    log: Arc<Mutex<FollowerLog>>,
+}
+
+#[tonic::async_trait]
+impl LongPollingService for FakeFollower {
+    async fn poll(&self, _: Request<PollJobRequest>) -> Result<Response<PollJobResponse>, Status> {
+        let job: Option<Job> = self.log.lock().unwrap().jobs.pop_front();
+        if job.is_none() {
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+
+        Ok(Response::new(PollJobResponse {
+            success: job.is_some(),
+            lease_id: job
+                .as_ref()
+                .map(|j| format!("lease-{}", j.job_id))
+                .unwrap_or_default(),
+            job,
+            visibility_timeout_seconds: 1,
+        }))
+    }
+
+    async fn heartbeat(
+        &self,
+        request: Request<HeartbeatRequest>,
+    ) -> Result<Response<HeartbeatResponse>, Status> {
+        self.log
+            .lock()
+            .unwrap()
+            .heartbeats
+            .push(request.into_inner().job_id);
+        Ok(Response::new(HeartbeatResponse {
+            success: true,
+            cancelled: false,
+            visibility_timeout_seconds: 1,
+        }))
+    }
+
+    async fn ack(&self, request: Request<AckRequest>) -> Result<Response<AckResponse>, Status> {
+        let request = request.into_inner();
+        self.log
+            .lock()
+            .unwrap()
+            .acked
+            .push((request.job_id, request.result));
+        Ok(Response::new(AckResponse {
+            success: true,
+            message: String::new(),
+        }))
+    }
+
+    async fn nack(&self, request: Request<NackRequest>) -> Result<Response<AckResponse>, Status> {
+        self.log
+            .lock()
+            .unwrap()
+            .nacked
+            .push(request.into_inner().job_id);
+        Ok(Response::new(AckResponse {
+            success: true,
+            message: String::new(),
+        }))
+    }
+
+    async fn release(
+        &self,
+        request: Request<NackRequest>,
+    ) -> Result<Response<AckResponse>, Status> {
+        self.log
+            .lock()
+            .unwrap()
+            .released
+            .push(request.into_inner().job_id);
+        Ok(Response::new(AckResponse {
+            success: true,
+            message: String::new(),
+        }))
+    }
+}
+
+/// A leader assigning every member of a group the same followers.
+/// `joined`: The member id sent with every join.
+/// `left`: The members that left.
+struct FakeLeader {
+    followers: Vec<String>,
+    joined: Arc<Mutex<Vec<String>>>,
+    left: Arc<Mutex<Vec<String>>>,
+}
+
+#[tonic::async_trait]
+impl ConsumerGroupService for FakeLeader {
+    async fn join_group(
+        &self,
+        request: Request<JoinGroupRequest>,
+    ) -> Result<Response<GroupAssignment>, Status> {
+        self.joined
+            .lock()
+            .unwrap()
+            .push(request.into_inner().member_id);
+        Ok(Response::new(GroupAssignment {
+            success: true,
+            generation: 1,
+            followers: self.followers.clone(),
+            members: 1,
+            session_timeout_seconds: 3,
+        }))
+    }
+
+    async fn leave_group(
+        &self,
+        request: Request<LeaveGroupRequest>,
+    ) -> Result<Response<GroupAssignment>, Status> {
+        self.left
+            .lock()
+            .unwrap()
+            .push(request.into_inner().member_id);
+        Ok(Response::new(GroupAssignment {
+            success: true,
+            ..GroupAssignment::default()
+        }))
+    }
+}
+
+/// Acts on the instruction in the payload of each job.
+struct ScriptedHandler;
+
+#[tonic::async_trait]
+impl JobHandler for ScriptedHandler {
+    async fn handle(&self, context: &JobContext) -> Result<Option<JobResult>, HandlerError> {
+        match context.payload() {
+            b"ok" => Ok(Some(JobResult {
+                data: b"done".to_vec(),
+                content_type: "text/plain".to_string(),
+            })),
+            b"fail" => Err("failed on purpose".into()),
+            b"panic" => panic!("panicked on purpose"),
+            _ => {
+                context.report_progress(50, "sleeping");
+                tokio::time::sleep(Duration::from_secs(10)).await;
+                Ok(None)
+            }
+        }
+    }
+}
+
+fn job(job_id: &str, payload: &[u8]) -> Job {
+    Job {
+        job_id: job_id.to_string(),
+        priority: 1,
+        payload: payload.to_vec(),
+        ..Job::default()
+    }
+}
+
+/// Starts a fake follower holding the given jobs and returns its address and log.
+async fn start_follower(jobs: Vec<Job>) -> (String, Arc<Mutex<FollowerLog>>) {
+    let log: Arc<Mutex<FollowerLog>> = Arc::new(Mutex::new(FollowerLog {
+        jobs: jobs.into(),
+        ..FollowerLog::default()
+    }));
+
+    let listener: TcpListener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let address: String = format!("http://{}", listener.local_addr().unwrap());
+
+    let follower: FakeFollower = FakeFollower { log: log.clone() };
+    tokio::spawn(
+        Server::builder()
+            .add_service(LongPollingServiceServer::new(follower))
+            .serve_with_incoming(TcpListenerStream::new(listener)),
+    );
+
+    (address, log)
+}
+
+fn worker(address: String, config: WorkerConfig) -> Worker<ScriptedHandler> {
+    let client: Client = Client::new(ClientConfig {
+        followers: vec![address],
+        ..ClientConfig::default()
+    });
+    Worker::new(client, ScriptedHandler, config)
+}
+
+/// Waits until the follower has been told the outcome of `count` jobs.
+async fn reported(log: Arc<Mutex<FollowerLog>>, count: usize) {
+    loop {
+        {
+            let log = log.lock().unwrap();
+            if log.acked.len() + log.nacked.len() >= count {
+                return;
+            }
+        }
+        tokio::time::sleep(Duration::from_millis(10)).await;
+    }
+}
+
+#[tokio::test]
+async fn test_jobs_are_acked_or_nacked_by_outcome() {
+    let (address, log) = start_follower(vec![
+        job("1", b"ok"),
+        job("2", b"fail"),
+        job("3", b"panic"),
+        job("4", b"ok"),
+    ])
+    .await;
+
+    let config: WorkerConfig = WorkerConfig {
+        concurrency: 2,
+        poll_timeout: Duration::from_millis(50),
+        ..WorkerConfig::default()
+    };
+
+    tokio::time::timeout(
+        Duration::from_secs(10),
+        worker(address, config).run(reported(log.clone(), 4)),
+    )
+    .await
+    .expect("worker did not shut down");
+
+    let log = log.lock().unwrap();
+    let mut acked: Vec<&str> = log.acked.iter().map(|(id, _)| id.as_str()).collect();
+    acked.sort();
+    let mut nacked: Vec<&str> = log.nacked.iter().map(|id| id.as_str()).collect();
+    nacked.sort();
+
+    // The panic only failed its own job
+    assert_eq!(acked, vec!["1", "4"]);
+    assert_eq!(nacked, vec!["2", "3"]);
+    assert!(log
+        .acked
+        .iter()
+        .all(|(_, result)| result.as_ref().unwrap().data == b"done"));
+}
+
+#[tokio::test]
+async fn test_shutdown_drains_then_nacks_unfinished_jobs() {
+    let (address, log) = start_follower(vec![job("1", b"slow")]).await;
+
+    let config: WorkerConfig = WorkerConfig {
+        concurrency: 1,
+        poll_timeout: Duration::from_millis(50),
+        heartbeat_interval: Some(Duration::from_millis(100)),
+        drain_timeout: Duration::from_millis(500),
+        ..WorkerConfig::default()
+    };
+
+    let shutdown = {
+        let log = log.clone();
+        async move {
+            while !log.lock().unwrap().jobs.is_empty() {
+                tokio::time::sleep(Duration::from_millis(10)).await;
+            }
+            tokio::time::sleep(Duration::from_millis(250)).await;
+        }
+    };
+
+    tokio::time::timeout(
+        Duration::from_secs(5),
+        worker(address, config).run(shutdown),
+    )
+    .await
+    .expect("worker did not drain in time");
+
+    let log = log.lock().unwrap();
+    assert!(log.acked.is_empty());
+    assert_eq!(log.nacked, vec!["1".to_string()]);
+    // The lease was kept alive while the job ran
+    assert!(log.heartbeats.len() >= 2);
+}
+
+#[tokio::test]
+async fn test_prefetched_jobs_are_renewed_then_released_on_shutdown() {
+    let (address, log) = start_follower(vec![
+        job("1", b"slow"),
+        job("2", b"slow"),
+        job("3", b"slow"),
+    ])
+    .await;
+
+    let config: WorkerConfig = WorkerConfig {
+        concurrency: 1,
+        poll_timeout: Duration::from_millis(50),
+        heartbeat_interval: Some(Duration::from_millis(100)),
+        drain_timeout: Duration::from_millis(200),
+        prefetch: 2,
+        ..WorkerConfig::default()
+    };
+
+    let shutdown = {
+        let log = log.clone();
+        async move {
+            while !log.lock().unwrap().jobs.is_empty() {
+                tokio::time::sleep(Duration::from_millis(10)).await;
+            }
+            tokio::time::sleep(Duration::from_millis(350)).await;
+        }
+    };
+
+    tokio::time::timeout(
+        Duration::from_secs(5),
+        worker(address, config).run(shutdown),
+    )
+    .await
+    .expect("worker did not drain in time");
+
+    let log = log.lock().unwrap();
+    assert!(log.acked.is_empty());
+    // The job being processed is nacked, the ones still waiting are handed back untouched
+    assert_eq!(log.nacked.len(), 1);
+    let mut released: Vec<String> = log.released.clone();
+    released.sort();
+    let mut all: Vec<String> = [log.nacked.clone(), released.clone()].concat();
+    all.sort();
+    assert_eq!(all, vec!["1", "2", "3"]);
+    assert_eq!(released.len(), 2);
+    // The leases on the waiting jobs were kept alive
+    assert!(released.iter().all(|id| log.heartbeats.contains(id)));
+}
+
+#[tokio::test]
+async fn test_group_members_poll_their_assigned_followers() {
+    let (unassigned, unassigned_log) = start_follower(vec![job("1", b"ok")]).await;
+    let (assigned, assigned_log) = start_follower(vec![job("2", b"ok"), job("3", b"ok")]).await;
+
+    let leader: FakeLeader = FakeLeader {
+        followers: vec![assigned],
+        joined: Arc::new(Mutex::new(Vec::new())),
+        left: Arc::new(Mutex::new(Vec::new())),
+    };
+    let (joined, left) = (leader.joined.clone(), leader.left.clone());
+
+    let listener: TcpListener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let leader_address: String = format!("http://{}", listener.local_addr().unwrap());
+    tokio::spawn(
+        Server::builder()
+            .add_service(ConsumerGroupServiceServer::new(leader))
+            .serve_with_incoming(TcpListenerStream::new(listener)),
+    );
+
+    let client: Client = Client::new(ClientConfig {
+        leaders: vec![leader_address],
+        followers: vec![unassigned],
+        ..ClientConfig::default()
+    });
+    let config: WorkerConfig = WorkerConfig {
+        concurrency: 2,
+        poll_timeout: Duration::from_millis(50),
+        group: Some("group".to_string()),
+        ..WorkerConfig::default()
+    };
+
+    tokio::time::timeout(
+        Duration::from_secs(10),
+        Worker::new(client.clone(), ScriptedHandler, config).run(reported(assigned_log.clone(), 2)),
+    )
+    .await
+    .expect("worker did not shut down");
+
+    assert_eq!(assigned_log.lock().unwrap().acked.len(), 2);
+    // The follower the group did not assign was never polled
+    assert_eq!(unassigned_log.lock().unwrap().jobs.len(), 1);
+    assert_eq!(joined.lock().unwrap()[0], client.member_id());
+    assert_eq!(*left.lock().unwrap(), vec![client.member_id().to_string()]);
+}